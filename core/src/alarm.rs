@@ -0,0 +1,356 @@
+use core::{cell::RefCell, fmt, str::FromStr};
+
+use chrono::{
+    DateTime, Datelike, LocalResult, NaiveDate, NaiveTime, TimeDelta, TimeZone, Timelike, Utc,
+    Weekday,
+};
+use chrono_tz::Tz;
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+use heapless::{String, Vec};
+
+use crate::settings::Settings;
+
+/// Most alarms that can be set.
+pub const MAX_ALARMS: usize = 8;
+/// Longest label of an alarm in bytes.
+pub const MAX_LABEL_LEN: usize = 12;
+/// How long a snoozed alarm waits before it rings again.
+pub const SNOOZE: TimeDelta = TimeDelta::minutes(9);
+/// Alarms missed by more than this, e.g. when the clock stepped forward, are
+/// skipped instead of ringing late.
+const MAX_LATE: TimeDelta = TimeDelta::minutes(5);
+
+/// Size of a serialized alarm without its label.
+const ALARM_HEADER_SIZE: usize = 4;
+/// Maximum size of a serialized alarm.
+pub const ALARM_SIZE: usize = ALARM_HEADER_SIZE + MAX_LABEL_LEN;
+const FLAG_RECURRING: u8 = 1 << 0;
+const FLAG_ENABLED: u8 = 1 << 1;
+
+/// Days of the week an alarm rings on, bit 0 is Monday.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Weekdays(u8);
+
+impl Weekdays {
+    pub const NONE: Weekdays = Weekdays(0);
+    pub const EVERY_DAY: Weekdays = Weekdays(0x7F);
+
+    pub const fn from_bits(bits: u8) -> Self {
+        Weekdays(bits & Self::EVERY_DAY.0)
+    }
+
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(&self, day: Weekday) -> bool {
+        self.0 & (1 << day.num_days_from_monday()) != 0
+    }
+
+    pub fn with(self, day: Weekday) -> Self {
+        Weekdays(self.0 | (1 << day.num_days_from_monday()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl fmt::Display for Weekdays {
+    /// `daily` or the days with runs of three or more as ranges, e.g.
+    /// `mon-fri,sun`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if *self == Self::EVERY_DAY {
+            return f.write_str("daily");
+        }
+        let mut first = true;
+        let mut start = 0;
+        while start < DAYS.len() {
+            if !self.contains(DAYS[start]) {
+                start += 1;
+                continue;
+            }
+            let mut end = start;
+            while end + 1 < DAYS.len() && self.contains(DAYS[end + 1]) {
+                end += 1;
+            }
+            if !first {
+                f.write_str(",")?;
+            }
+            first = false;
+            match end - start {
+                0 => f.write_str(DAY_NAMES[start])?,
+                1 => write!(f, "{},{}", DAY_NAMES[start], DAY_NAMES[end])?,
+                _ => write!(f, "{}-{}", DAY_NAMES[start], DAY_NAMES[end])?,
+            }
+            start = end + 1;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Weekdays {
+    type Err = ();
+
+    /// Parses `daily` or a comma separated list of days and ranges, e.g.
+    /// `mon-fri,sun`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "daily" {
+            return Ok(Self::EVERY_DAY);
+        }
+        let mut weekdays = Self::NONE;
+        for item in s.split(',') {
+            let (first, last) = match item.split_once('-') {
+                Some((first, last)) => (day_index(first)?, day_index(last)?),
+                None => (day_index(item)?, day_index(item)?),
+            };
+            if first > last {
+                return Err(());
+            }
+            for day in &DAYS[first..=last] {
+                weekdays = weekdays.with(*day);
+            }
+        }
+        Ok(weekdays)
+    }
+}
+
+const DAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+const DAY_NAMES: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+fn day_index(name: &str) -> Result<usize, ()> {
+    DAY_NAMES.iter().position(|day| *day == name).ok_or(())
+}
+
+/// An alarm at a wall clock time in the time zone of the clock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alarm {
+    /// Hour and minute, the seconds are ignored.
+    pub time: NaiveTime,
+    pub weekdays: Weekdays,
+    /// Rings on every day in `weekdays`, otherwise only on the next one and
+    /// is disabled afterwards.
+    pub recurring: bool,
+    pub enabled: bool,
+    pub label: String<MAX_LABEL_LEN>,
+}
+
+impl Alarm {
+    /// When the alarm rings on the local `date` in `tz`, `None` if not on that
+    /// weekday. A time skipped by the switch to summer time rings as if the
+    /// clock hadn't switched yet, e.g. 02:30 at 03:30. A time repeated by the
+    /// switch back rings the first time only.
+    pub fn on_date(&self, date: NaiveDate, tz: Tz) -> Option<DateTime<Utc>> {
+        if !self.weekdays.contains(date.weekday()) {
+            return None;
+        }
+        let local = date.and_time(self.time);
+        match tz.from_local_datetime(&local) {
+            LocalResult::Single(time) => Some(time.to_utc()),
+            LocalResult::Ambiguous(first, _) => Some(first.to_utc()),
+            LocalResult::None => {
+                // The offset before the gap, taken from an hour earlier
+                let before = tz
+                    .from_local_datetime(&(local - TimeDelta::hours(1)))
+                    .earliest()?;
+                Some(before.to_utc() + TimeDelta::hours(1))
+            }
+        }
+    }
+
+    /// The first time the alarm rings after `from` and up to `to`.
+    pub fn next_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        tz: Tz,
+    ) -> Option<DateTime<Utc>> {
+        // A day earlier, the local date at `from` may be behind the UTC one
+        let mut date = from.with_timezone(&tz).date_naive().pred_opt()?;
+        let last = to.with_timezone(&tz).date_naive();
+        while date <= last {
+            if let Some(at) = self.on_date(date, tz).filter(|at| from < *at && *at <= to) {
+                return Some(at);
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
+    /// Serializes into `buf`: hour, minute, weekdays, flags and the label.
+    /// Returns the number of bytes used.
+    pub fn to_bytes(&self, buf: &mut [u8; ALARM_SIZE]) -> usize {
+        let mut flags = 0;
+        if self.recurring {
+            flags |= FLAG_RECURRING;
+        }
+        if self.enabled {
+            flags |= FLAG_ENABLED;
+        }
+        buf[..ALARM_HEADER_SIZE].copy_from_slice(&[
+            self.time.hour() as u8,
+            self.time.minute() as u8,
+            self.weekdays.bits(),
+            flags,
+        ]);
+        let label = self.label.as_bytes();
+        buf[ALARM_HEADER_SIZE..ALARM_HEADER_SIZE + label.len()].copy_from_slice(label);
+        ALARM_HEADER_SIZE + label.len()
+    }
+
+    /// Restores an alarm serialized by [`Alarm::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (header, label) = bytes.split_at_checked(ALARM_HEADER_SIZE)?;
+        let time = NaiveTime::from_hms_opt(header[0] as u32, header[1] as u32, 0)?;
+        let label = core::str::from_utf8(label).ok()?.try_into().ok()?;
+        Some(Alarm {
+            time,
+            weekdays: Weekdays::from_bits(header[2]),
+            recurring: header[3] & FLAG_RECURRING != 0,
+            enabled: header[3] & FLAG_ENABLED != 0,
+            label,
+        })
+    }
+}
+
+impl fmt::Display for Alarm {
+    /// e.g. `07:30 mon-fri Work` or `06:00 sat,once`, the way it is entered on
+    /// the console.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02} ", self.time.hour(), self.time.minute())?;
+        match (self.recurring, self.weekdays) {
+            (false, Weekdays::EVERY_DAY) => f.write_str("once")?,
+            (false, weekdays) => write!(f, "{},once", weekdays)?,
+            (true, weekdays) => write!(f, "{}", weekdays)?,
+        }
+        if !self.enabled {
+            f.write_str(" off")?;
+        }
+        if !self.label.is_empty() {
+            write!(f, " {}", self.label)?;
+        }
+        Ok(())
+    }
+}
+
+/// An alarm that went off.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ringing {
+    pub alarm: Alarm,
+    /// When it started to ring, the first time if it was snoozed.
+    pub since: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+struct AlarmState {
+    last_check: Option<DateTime<Utc>>,
+    ringing: Option<Ringing>,
+    snoozed: Option<(Ringing, DateTime<Utc>)>,
+}
+
+/// Rings the alarms in the settings. [`AlarmClock::check`] is called
+/// regularly with the current time, the clockface shows
+/// [`AlarmClock::ringing`] until it is dismissed or snoozed.
+pub struct AlarmClock {
+    state: Mutex<CriticalSectionRawMutex, RefCell<AlarmState>>,
+}
+
+impl AlarmClock {
+    pub const fn new() -> Self {
+        AlarmClock {
+            state: Mutex::new(RefCell::new(AlarmState {
+                last_check: None,
+                ringing: None,
+                snoozed: None,
+            })),
+        }
+    }
+
+    /// Rings the alarms due since the last check, `now` being in the time
+    /// zone of the clock. Alarms due before the first check aren't rung, nor
+    /// ones due more than a few minutes ago. One-shot alarms are disabled in
+    /// `settings` once they rang, all of them if several ring at once.
+    /// Returns the ringing alarm.
+    pub fn check(&self, settings: &Settings, now: DateTime<Tz>) -> Option<Ringing> {
+        let tz = now.timezone();
+        let now = now.to_utc();
+        let alarms = settings.alarms();
+        let (ringing, rung) = self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            let Some(last) = state.last_check.replace(now) else {
+                return (state.ringing.clone(), Vec::new());
+            };
+
+            if let Some((ringing, until)) = state.snoozed.clone() {
+                if now >= until {
+                    state.snoozed = None;
+                    state.ringing = Some(ringing);
+                }
+            }
+
+            let from = last.max(now - MAX_LATE);
+            let mut rung = Vec::<Alarm, MAX_ALARMS>::new();
+            for alarm in &alarms {
+                if !alarm.enabled {
+                    continue;
+                }
+                if let Some(at) = alarm.next_between(from, now, tz) {
+                    state.ringing = Some(Ringing {
+                        alarm: alarm.clone(),
+                        since: at,
+                    });
+                    state.snoozed = None;
+                    if !alarm.recurring {
+                        // Can't fail, there are no more alarms than that
+                        let _ = rung.push(alarm.clone());
+                    }
+                }
+            }
+            (state.ringing.clone(), rung)
+        });
+
+        if !rung.is_empty() {
+            settings.disable_alarms(&rung);
+        }
+        ringing
+    }
+
+    pub fn ringing(&self) -> Option<Ringing> {
+        self.state.lock(|state| state.borrow().ringing.clone())
+    }
+
+    /// Stops the ringing or snoozed alarm, returns it.
+    pub fn dismiss(&self) -> Option<Ringing> {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            let snoozed = state.snoozed.take().map(|(ringing, _)| ringing);
+            state.ringing.take().or(snoozed)
+        })
+    }
+
+    /// Stops the ringing alarm and rings it again [`SNOOZE`] after `now`.
+    /// Returns when it rings again, `None` if no alarm is ringing.
+    pub fn snooze(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            let ringing = state.ringing.take()?;
+            let until = now + SNOOZE;
+            state.snoozed = Some((ringing, until));
+            Some(until)
+        })
+    }
+}
+
+impl Default for AlarmClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
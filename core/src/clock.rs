@@ -0,0 +1,356 @@
+use core::{cell::Cell, cmp::Reverse, time::Duration};
+
+use chrono::{DateTime, Datelike, NaiveDate, TimeDelta, Timelike, Utc};
+use embassy_sync::{
+    blocking_mutex::{raw::CriticalSectionRawMutex, Mutex},
+    signal::Signal,
+};
+
+use crate::source::{Sample, SourceKind};
+
+/// Syncs closer together than this are too short to estimate the drift.
+const MIN_DRIFT_INTERVAL: TimeDelta = TimeDelta::minutes(10);
+
+/// First year a two digit RTC year is taken to be in, see [`RtcDateTime::year`].
+pub const RTC_BASE_YEAR: i32 = 2025;
+
+/// Calendar fields as stored by the PCF8563 RTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RtcDateTime {
+    /// Two digit year. The century flag of the PCF8563 isn't used, the year is
+    /// the one within the hundred years from [`RTC_BASE_YEAR`], so 25 is 2025
+    /// and 24 is 2124.
+    pub year: u8,
+    pub month: u8,
+    pub day: u8,
+    pub weekday: u8,
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+}
+
+/// I2C address of the PCF8563.
+pub const PCF8563_ADDRESS: u8 = 0x51;
+/// First of the seven PCF8563 time registers, VL_seconds.
+pub const PCF8563_TIME_REGISTER: u8 = 0x02;
+
+/// Why the time in the RTC can't be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtcError {
+    /// The voltage low flag is set, the RTC lost power and its time is
+    /// unreliable.
+    VoltageLow,
+    /// The registers don't hold a valid date and time.
+    InvalidData,
+}
+
+impl RtcDateTime {
+    /// Decodes the seven PCF8563 time registers from VL_seconds to years.
+    pub fn from_registers(registers: &[u8; 7]) -> Result<Self, RtcError> {
+        if registers[0] & 0x80 != 0 {
+            return Err(RtcError::VoltageLow);
+        }
+        let bcd = |value: u8, mask: u8| {
+            let value = value & mask;
+            let (tens, units) = (value >> 4, value & 0x0F);
+            if units > 9 {
+                return Err(RtcError::InvalidData);
+            }
+            Ok(tens * 10 + units)
+        };
+        let weekday = registers[4] & 0x07;
+        if weekday > 6 {
+            return Err(RtcError::InvalidData);
+        }
+        Ok(RtcDateTime {
+            seconds: bcd(registers[0], 0x7F)?,
+            minutes: bcd(registers[1], 0x7F)?,
+            hours: bcd(registers[2], 0x3F)?,
+            day: bcd(registers[3], 0x3F)?,
+            weekday,
+            // The century flag is ignored, see RtcDateTime::year
+            month: bcd(registers[5], 0x1F)?,
+            year: bcd(registers[6], 0xFF)?,
+        })
+    }
+}
+
+impl RtcDateTime {
+    /// Encodes the seven PCF8563 time registers from VL_seconds to years, the
+    /// voltage low flag cleared.
+    pub fn to_registers(&self) -> [u8; 7] {
+        let bcd = |value: u8| ((value / 10) << 4) | (value % 10);
+        [
+            bcd(self.seconds),
+            bcd(self.minutes),
+            bcd(self.hours),
+            bcd(self.day),
+            self.weekday,
+            bcd(self.month),
+            bcd(self.year),
+        ]
+    }
+}
+
+/// Decodes and checks the PCF8563 time registers, returns the unix timestamp
+/// they hold.
+pub fn rtc_registers_to_timestamp(registers: &[u8; 7]) -> Result<i64, RtcError> {
+    let datetime = RtcDateTime::from_registers(registers)?;
+    rtc_datetime_to_timestamp(&datetime).ok_or(RtcError::InvalidData)
+}
+
+/// The year within the hundred years from [`RTC_BASE_YEAR`] that ends in the
+/// two digits `year`, `None` if `year` has more than two digits.
+pub fn two_digit_year(year: u8) -> Option<i32> {
+    if year > 99 {
+        return None;
+    }
+    let year = RTC_BASE_YEAR - RTC_BASE_YEAR % 100 + year as i32;
+    Some(if year < RTC_BASE_YEAR {
+        year + 100
+    } else {
+        year
+    })
+}
+
+/// Converts the RTC calendar fields to a unix timestamp, `None` if the fields
+/// don't form a valid date and time.
+pub fn rtc_datetime_to_timestamp(datetime: &RtcDateTime) -> Option<i64> {
+    let year = two_digit_year(datetime.year)?;
+    let month = datetime.month as u32;
+    let day = datetime.day as u32;
+    let hour = datetime.hours as u32;
+    let minute = datetime.minutes as u32;
+    let second = datetime.seconds as u32;
+
+    let naive = NaiveDate::from_ymd_opt(year, month, day)
+        .and_then(|date| date.and_hms_opt(hour, minute, second))?;
+
+    // Get the Unix timestamp (seconds since epoch)
+    Some(naive.and_utc().timestamp())
+}
+
+/// Converts a unix timestamp to the RTC calendar fields, `None` if it is out
+/// of the hundred years the RTC can store.
+pub fn timestamp_to_rtc_datetime(timestamp: i64) -> Option<RtcDateTime> {
+    let t = DateTime::<Utc>::from_timestamp(timestamp, 0)?;
+    if !(RTC_BASE_YEAR..RTC_BASE_YEAR + 100).contains(&t.year()) {
+        return None;
+    }
+    Some(RtcDateTime {
+        year: (t.year() % 100) as u8,
+        month: t.month() as u8,
+        day: t.day() as u8,
+        weekday: t.weekday().num_days_from_monday() as u8,
+        hours: t.hour() as u8,
+        minutes: t.minute() as u8,
+        seconds: t.second() as u8,
+    })
+}
+
+/// Wall clock time from the unix time at boot in microseconds, `offset_micros`,
+/// and the uptime in microseconds. Saturates at the range of [`DateTime`].
+pub fn time_from_offset(offset_micros: i64, uptime_micros: u64) -> DateTime<Utc> {
+    let uptime_micros = i64::try_from(uptime_micros).unwrap_or(i64::MAX);
+    let time_micros = offset_micros.saturating_add(uptime_micros);
+    DateTime::from_timestamp_micros(time_micros).unwrap_or(if time_micros < 0 {
+        DateTime::<Utc>::MIN_UTC
+    } else {
+        DateTime::<Utc>::MAX_UTC
+    })
+}
+
+/// Unix time at boot in microseconds for the clock to read `time` after
+/// `uptime_micros`, the inverse of [`time_from_offset`].
+pub fn offset_from_time(time: DateTime<Utc>, uptime_micros: u64) -> i64 {
+    let uptime_micros = i64::try_from(uptime_micros).unwrap_or(i64::MAX);
+    time.timestamp_micros().saturating_sub(uptime_micros)
+}
+
+/// Corrections up to this size are slewed, larger ones step the clock.
+pub const SLEW_THRESHOLD: TimeDelta = TimeDelta::seconds(2);
+
+/// How a new offset was applied, see [`SlewedOffset::correct`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Correction {
+    /// The clock moves to the new offset gradually.
+    Slewed(TimeDelta),
+    /// The clock jumped to the new offset.
+    Stepped(TimeDelta),
+}
+
+/// Unix time at boot in microseconds like the offset of [`time_from_offset`],
+/// moving linearly from one value to another over a window of uptime. This
+/// slews small corrections in, the clock runs slightly fast or slow until it
+/// caught up but never goes backwards nor skips ahead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlewedOffset {
+    from_micros: i64,
+    to_micros: i64,
+    start_uptime_micros: u64,
+    window_micros: u64,
+}
+
+impl SlewedOffset {
+    pub const fn new(offset_micros: i64) -> Self {
+        SlewedOffset {
+            from_micros: offset_micros,
+            to_micros: offset_micros,
+            start_uptime_micros: 0,
+            window_micros: 0,
+        }
+    }
+
+    /// The offset at `uptime_micros`.
+    pub fn at(&self, uptime_micros: u64) -> i64 {
+        let elapsed = uptime_micros.saturating_sub(self.start_uptime_micros);
+        if elapsed >= self.window_micros {
+            return self.to_micros;
+        }
+        let delta = self.to_micros as i128 - self.from_micros as i128;
+        let slewed = delta * elapsed as i128 / self.window_micros as i128;
+        (self.from_micros as i128 + slewed) as i64
+    }
+
+    /// The offset once the slew is done.
+    pub fn target(&self) -> i64 {
+        self.to_micros
+    }
+
+    pub fn is_slewing(&self, uptime_micros: u64) -> bool {
+        self.at(uptime_micros) != self.to_micros
+    }
+
+    /// Moves to `offset_micros` from the offset at `uptime_micros`.
+    /// Corrections up to `threshold` are slewed over `window`, larger ones
+    /// and ones too large for the window to keep the clock monotonic step.
+    pub fn correct(
+        &mut self,
+        offset_micros: i64,
+        uptime_micros: u64,
+        window: Duration,
+        threshold: TimeDelta,
+    ) -> Correction {
+        let current = self.at(uptime_micros);
+        let correction = TimeDelta::microseconds(offset_micros.saturating_sub(current));
+        let window_micros = u64::try_from(window.as_micros()).unwrap_or(u64::MAX);
+        // Slewing by the whole window or more would stop or reverse the clock
+        let slew = correction.abs() <= threshold
+            && TimeDelta::from_std(window).is_ok_and(|window| correction.abs() < window);
+
+        *self = if slew {
+            SlewedOffset {
+                from_micros: current,
+                to_micros: offset_micros,
+                start_uptime_micros: uptime_micros,
+                window_micros,
+            }
+        } else {
+            SlewedOffset::new(offset_micros)
+        };
+        if slew {
+            Correction::Slewed(correction)
+        } else {
+            Correction::Stepped(correction)
+        }
+    }
+}
+
+/// Converts the fraction of an NTP timestamp, in units of 2^-32 seconds, to
+/// microseconds.
+pub fn ntp_fraction_to_micros(fraction: u32) -> u32 {
+    ((fraction as u64 * 1_000_000) >> 32) as u32
+}
+
+/// Time left from `now` until the next full second.
+pub fn until_next_second(now: DateTime<Utc>) -> TimeDelta {
+    let subsec = TimeDelta::nanoseconds(now.timestamp_subsec_nanos() as i64);
+    TimeDelta::seconds(1) - subsec
+}
+
+/// Outcome of the last clock synchronisation, see [`SyncTracker::offer`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyncStatus {
+    /// The sample the clock was last set from.
+    pub sample: Sample,
+    /// How far the local clock was off at the last sync, positive if it was ahead.
+    pub offset: TimeDelta,
+    /// Estimated frequency error of the local clock in parts per million,
+    /// positive if it runs fast. `None` until two syncs far enough apart.
+    pub drift_ppm: Option<f32>,
+}
+
+/// Picks the reference times the clock is set from and keeps track of the
+/// synchronisations to estimate how much the local clock drifts between them.
+pub struct SyncTracker {
+    status: Mutex<CriticalSectionRawMutex, Cell<Option<SyncStatus>>>,
+    /// Signaled to request a sync right away instead of waiting for the interval.
+    pub requested: Signal<CriticalSectionRawMutex, ()>,
+    /// Signaled with a time set by hand, see [`crate::source::ManualSource`].
+    pub manual: Signal<CriticalSectionRawMutex, DateTime<Utc>>,
+}
+
+impl SyncTracker {
+    pub const fn new() -> Self {
+        SyncTracker {
+            status: Mutex::new(Cell::new(None)),
+            requested: Signal::new(),
+            manual: Signal::new(),
+        }
+    }
+
+    pub fn status(&self) -> Option<SyncStatus> {
+        self.status.lock(|status| status.get())
+    }
+
+    /// Offers a sample to set the clock from, at which the local clock read
+    /// `local`. Returns `None` if the clock is already set from a better
+    /// source: one whose uncertainty, grown by the drift since, is lower, or
+    /// the same with a preferred [`SourceKind`]. Otherwise the sample is taken
+    /// and the local clock is expected to be set to it, so the offset at the
+    /// next sync is the drift since this one.
+    pub fn offer(&self, sample: &Sample, local: DateTime<Utc>) -> Option<SyncStatus> {
+        let offset = local - sample.time;
+        self.status.lock(|status| {
+            let previous = status.get();
+            if let Some(previous) = previous {
+                let current = previous.sample.uncertainty_at(sample.uptime_micros);
+                if (sample.uncertainty, Reverse(sample.kind))
+                    > (current, Reverse(previous.sample.kind))
+                {
+                    return None;
+                }
+            }
+
+            // The RTC only counts whole seconds, too coarse to tell the drift
+            let drift_ppm = match previous {
+                Some(previous)
+                    if sample.kind != SourceKind::Rtc
+                        && previous.sample.kind != SourceKind::Rtc
+                        && sample.time - previous.sample.time >= MIN_DRIFT_INTERVAL =>
+                {
+                    let elapsed = (sample.time - previous.sample.time).num_microseconds();
+                    offset
+                        .num_microseconds()
+                        .zip(elapsed)
+                        .map(|(offset, elapsed)| offset as f32 / elapsed as f32 * 1_000_000.0)
+                }
+                Some(previous) => previous.drift_ppm,
+                None => None,
+            };
+            let new = SyncStatus {
+                sample: *sample,
+                offset,
+                drift_ppm,
+            };
+            status.set(Some(new));
+            Some(new)
+        })
+    }
+}
+
+impl Default for SyncTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,624 @@
+use core::time::Duration;
+
+use alloc::{format, string::String};
+use chrono::{DateTime, NaiveTime, Timelike, Utc};
+use chrono_tz::Tz;
+use heapless::Vec;
+
+use crate::{
+    alarm::{Alarm, AlarmClock, Weekdays},
+    clock::SyncTracker,
+    faces::parse_rotation,
+    sensor::SensorReadings,
+    settings::{
+        FaceId, HourFormat, RotationStep, Scenery, Settings, SettingsError, TemperatureUnit,
+        MAX_ROTATION_STEPS, SETTINGS_SIZE,
+    },
+    sun::{self, Location, SunEvents},
+    time::TimeSource,
+    timer::{parse_duration, Timer},
+    transition::TransitionKind,
+    weather::{Weather, WeatherCondition},
+};
+
+/// Most arguments any command takes, more are rejected. Only the label of
+/// `alarm add` goes on over as many words as fit.
+const MAX_ARGUMENTS: usize = 4;
+
+/// A command entered on the console, one per line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// `tz` - show the current time zone
+    ShowTimeZone,
+    /// `tz <IANA name>` - change the time zone, e.g. `tz America/New_York`
+    SetTimeZone(Tz),
+    /// `sync` - show the outcome of the last clock synchronisation
+    ShowSync,
+    /// `sync now` - synchronise the clock right away
+    SyncNow,
+    /// `sync interval <minutes>` - change the time between two synchronisations
+    SetSyncInterval(u16),
+    /// `sync slew <seconds>` - change the time small corrections are spread
+    /// over, 0 to always step the clock
+    SetSlewWindow(u16),
+    /// `sync serve on|off` - serve the time to the LAN over NTP
+    SetNtpServer(bool),
+    /// `hours` - show how the hour is displayed
+    ShowHours,
+    /// `hours 12|24` - switch between the 12 and the 24 hour clock
+    SetHourFormat(HourFormat),
+    /// `hours zero on|off` - show hours below 10 with or without a leading zero
+    SetLeadingZero(bool),
+    /// `time` - show the current time
+    ShowTime,
+    /// `time set <RFC 3339>` - set the clock by hand, e.g. `time set 2025-06-15T12:00:00+02:00`
+    SetTime(DateTime<Utc>),
+    /// `alarm` - list the alarms
+    ShowAlarms,
+    /// `alarm add <HH:MM> <days|once> [label]` - add an alarm, days are
+    /// `daily` or a list like `mon-fri,sun`, `once` rings the next time only,
+    /// the label is the rest of the line, e.g. `alarm add 07:30 mon-fri Go
+    /// to work`
+    AddAlarm(Alarm),
+    /// `alarm remove <n>` - remove the alarm numbered `n` in the list
+    RemoveAlarm(usize),
+    /// `alarm on|off <n>` - enable or disable the alarm numbered `n`
+    EnableAlarm(usize, bool),
+    /// `alarm dismiss` - stop the ringing alarm
+    DismissAlarm,
+    /// `alarm snooze` - stop the ringing alarm and ring again in 9 minutes
+    SnoozeAlarm,
+    /// `timer` - show the countdown or the stopwatch
+    ShowTimer,
+    /// `timer <[hh:]mm:ss>` - count down on the blocks, e.g. `timer 05:00`
+    StartCountdown(Duration),
+    /// `timer watch` - start the stopwatch on the blocks
+    StartStopwatch,
+    /// `timer pause` - pause the countdown or the stopwatch
+    PauseTimer,
+    /// `timer resume` - continue after a pause
+    ResumeTimer,
+    /// `timer stop` - back to the clock
+    StopTimer,
+    /// `face` - show the active face and the rotation
+    ShowFace,
+    /// `face mario|date` - always show this face, stops the rotation
+    SetFace(FaceId),
+    /// `face rotate <face:seconds[:transition],...>` - show the faces in
+    /// turn, e.g. `face rotate mario:55,date:5:iris`, `face rotate off` stops it
+    SetRotation(Vec<RotationStep, MAX_ROTATION_STEPS>),
+    /// `face transition` - show the transition between faces
+    ShowTransition,
+    /// `face transition cut|fade|wipe|dissolve|iris` - set the transition of
+    /// rotation steps without their own one and of switches by command
+    SetTransition(TransitionKind),
+    /// `location` - show the location with today's sunrise and sunset
+    ShowLocation,
+    /// `location <latitude> <longitude>` - set where the clock is for the
+    /// day and night sky, e.g. `location 47.37 8.54`, `location off` keeps
+    /// the sky blue
+    SetLocation(Option<Location>),
+    /// `scenery` - show how much of the background moves
+    ShowScenery,
+    /// `scenery still|clouds|scroll` - keep the background still, let the
+    /// clouds drift or scroll the hills and bushes too
+    SetScenery(Scenery),
+    /// `weather` - show the weather condition shown in the scene and the last
+    /// report fetched
+    ShowWeather,
+    /// `weather clear|cloudy|rain|snow|fog` - set the weather condition by
+    /// hand until a weather source sets another one, `weather off` shows no
+    /// weather
+    SetWeather(Option<WeatherCondition>),
+    /// `weather now` - fetch the weather for the location right away
+    FetchWeather,
+    /// `temperature` - show the temperature and how it is displayed
+    ShowTemperature,
+    /// `temperature celsius|fahrenheit` - change the unit of the temperature
+    SetTemperatureUnit(TemperatureUnit),
+    /// `temperature on|off` - show the temperature in the scene or not
+    SetShowTemperature(bool),
+    /// `sensor` - show the latest reading of the local sensor
+    ShowSensor,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    Empty,
+    Unknown,
+    InvalidArgument,
+}
+
+/// State the commands act on.
+pub struct CommandContext<'a> {
+    pub settings: &'a Settings,
+    pub sync: &'a SyncTracker,
+    pub time: &'a dyn TimeSource,
+    pub alarms: &'a AlarmClock,
+    pub timer: &'a Timer,
+    pub weather: &'a Weather,
+    pub sensor: &'a SensorReadings,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self, CommandError> {
+        let mut words = line.split_whitespace();
+        let name = words.next().ok_or(CommandError::Empty)?;
+        let mut args: Vec<&str, MAX_ARGUMENTS> = Vec::new();
+        for word in words.by_ref() {
+            args.push(word).map_err(|_| CommandError::InvalidArgument)?;
+            if args.is_full() {
+                break;
+            }
+        }
+
+        if let ("alarm", ["add", time, days, label @ ..]) = (name, args.as_slice()) {
+            let label = label.iter().copied().chain(words);
+            return parse_alarm(time, days, label)
+                .map(Command::AddAlarm)
+                .ok_or(CommandError::InvalidArgument);
+        }
+        if words.next().is_some() {
+            return Err(CommandError::InvalidArgument);
+        }
+
+        match (name, args.as_slice()) {
+            ("tz", []) => Ok(Command::ShowTimeZone),
+            ("tz", [zone]) => zone
+                .parse()
+                .map(Command::SetTimeZone)
+                .map_err(|_| CommandError::InvalidArgument),
+            ("sync", []) => Ok(Command::ShowSync),
+            ("sync", ["now"]) => Ok(Command::SyncNow),
+            ("sync", ["interval", minutes]) => match minutes.parse() {
+                Ok(minutes) if minutes > 0 => Ok(Command::SetSyncInterval(minutes)),
+                _ => Err(CommandError::InvalidArgument),
+            },
+            ("sync", ["slew", seconds]) => seconds
+                .parse()
+                .map(Command::SetSlewWindow)
+                .map_err(|_| CommandError::InvalidArgument),
+            ("sync", ["serve", "on"]) => Ok(Command::SetNtpServer(true)),
+            ("sync", ["serve", "off"]) => Ok(Command::SetNtpServer(false)),
+            ("hours", []) => Ok(Command::ShowHours),
+            ("hours", ["zero", "on"]) => Ok(Command::SetLeadingZero(true)),
+            ("hours", ["zero", "off"]) => Ok(Command::SetLeadingZero(false)),
+            ("hours", [hours]) => hours
+                .parse()
+                .ok()
+                .and_then(HourFormat::from_hours)
+                .map(Command::SetHourFormat)
+                .ok_or(CommandError::InvalidArgument),
+            ("time", []) => Ok(Command::ShowTime),
+            ("time", ["set", time]) => DateTime::parse_from_rfc3339(time)
+                .map(|time| Command::SetTime(time.to_utc()))
+                .map_err(|_| CommandError::InvalidArgument),
+            ("alarm", []) => Ok(Command::ShowAlarms),
+            ("alarm", ["remove", number]) => parse_number(number).map(Command::RemoveAlarm),
+            ("alarm", ["on", number]) => {
+                parse_number(number).map(|n| Command::EnableAlarm(n, true))
+            }
+            ("alarm", ["off", number]) => {
+                parse_number(number).map(|n| Command::EnableAlarm(n, false))
+            }
+            ("alarm", ["dismiss"]) => Ok(Command::DismissAlarm),
+            ("alarm", ["snooze"]) => Ok(Command::SnoozeAlarm),
+            ("timer", []) => Ok(Command::ShowTimer),
+            ("timer", ["watch"]) => Ok(Command::StartStopwatch),
+            ("timer", ["pause"]) => Ok(Command::PauseTimer),
+            ("timer", ["resume"]) => Ok(Command::ResumeTimer),
+            ("timer", ["stop"]) => Ok(Command::StopTimer),
+            ("timer", [duration]) => parse_duration(duration)
+                .map(Command::StartCountdown)
+                .ok_or(CommandError::InvalidArgument),
+            ("face", []) => Ok(Command::ShowFace),
+            ("face", ["transition"]) => Ok(Command::ShowTransition),
+            ("face", ["transition", kind]) => TransitionKind::from_name(kind)
+                .map(Command::SetTransition)
+                .ok_or(CommandError::InvalidArgument),
+            ("face", ["rotate", "off"]) => Ok(Command::SetRotation(Vec::new())),
+            ("face", ["rotate", rotation]) => parse_rotation(rotation)
+                .map(Command::SetRotation)
+                .ok_or(CommandError::InvalidArgument),
+            ("face", [face]) => FaceId::from_name(face)
+                .map(Command::SetFace)
+                .ok_or(CommandError::InvalidArgument),
+            ("location", []) => Ok(Command::ShowLocation),
+            ("location", ["off"]) => Ok(Command::SetLocation(None)),
+            ("location", [latitude, longitude]) => latitude
+                .parse()
+                .ok()
+                .zip(longitude.parse().ok())
+                .and_then(|(latitude, longitude)| Location::new(latitude, longitude))
+                .map(|location| Command::SetLocation(Some(location)))
+                .ok_or(CommandError::InvalidArgument),
+            ("scenery", []) => Ok(Command::ShowScenery),
+            ("scenery", [scenery]) => Scenery::from_name(scenery)
+                .map(Command::SetScenery)
+                .ok_or(CommandError::InvalidArgument),
+            ("weather", []) => Ok(Command::ShowWeather),
+            ("weather", ["off"]) => Ok(Command::SetWeather(None)),
+            ("weather", ["now"]) => Ok(Command::FetchWeather),
+            ("weather", [condition]) => WeatherCondition::from_name(condition)
+                .map(|condition| Command::SetWeather(Some(condition)))
+                .ok_or(CommandError::InvalidArgument),
+            ("sensor", []) => Ok(Command::ShowSensor),
+            ("temperature", []) => Ok(Command::ShowTemperature),
+            ("temperature", ["on"]) => Ok(Command::SetShowTemperature(true)),
+            ("temperature", ["off"]) => Ok(Command::SetShowTemperature(false)),
+            ("temperature", [unit]) => TemperatureUnit::from_name(unit)
+                .map(Command::SetTemperatureUnit)
+                .ok_or(CommandError::InvalidArgument),
+            (
+                "tz" | "sync" | "hours" | "time" | "alarm" | "timer" | "face" | "location"
+                | "scenery" | "weather" | "temperature" | "sensor",
+                _,
+            ) => Err(CommandError::InvalidArgument),
+            _ => Err(CommandError::Unknown),
+        }
+    }
+
+    /// Applies the command and returns the reply for the console. Settings
+    /// changed by the command are undone if they can't be saved.
+    pub fn execute(&self, context: &CommandContext) -> String {
+        let before = context.settings.values();
+        let reply = self.apply(context);
+        let mut buf = [0u8; SETTINGS_SIZE];
+        match context.settings.save(&mut buf) {
+            Ok(_) => reply,
+            Err(SettingsError::TooLarge) => {
+                context.settings.set_values(before);
+                String::from("the settings don't fit into flash, nothing changed")
+            }
+        }
+    }
+
+    fn apply(&self, context: &CommandContext) -> String {
+        match self {
+            Command::ShowTimeZone => time_zone(context),
+            Command::SetTimeZone(zone) => {
+                context.settings.set_time_zone(*zone);
+                time_zone(context)
+            }
+            Command::ShowSync => sync_status(context),
+            Command::SyncNow => {
+                context.sync.requested.signal(());
+                String::from("sync requested")
+            }
+            Command::SetSyncInterval(minutes) => {
+                context.settings.set_sync_interval_minutes(*minutes);
+                sync_status(context)
+            }
+            Command::SetSlewWindow(seconds) => {
+                context.settings.set_slew_window_seconds(*seconds);
+                sync_status(context)
+            }
+            Command::SetNtpServer(enabled) => {
+                context.settings.set_ntp_server(*enabled);
+                if *enabled {
+                    String::from("ntp server on, WiFi stays on after the next sync")
+                } else {
+                    String::from("ntp server off")
+                }
+            }
+            Command::ShowHours => hours(context),
+            Command::SetHourFormat(format) => {
+                context.settings.set_hour_format(*format);
+                hours(context)
+            }
+            Command::SetLeadingZero(enabled) => {
+                context.settings.set_leading_zero(*enabled);
+                hours(context)
+            }
+            Command::ShowTime => {
+                let now = context
+                    .time
+                    .now()
+                    .with_timezone(&context.settings.time_zone());
+                if context.time.is_known() {
+                    format!("time: {}", now)
+                } else {
+                    format!("time: unknown, clock reads {}", now)
+                }
+            }
+            Command::SetTime(time) => {
+                // Taken unless the clock is synced from a better source
+                context.sync.manual.signal(*time);
+                format!("time set requested: {}", time)
+            }
+            Command::ShowAlarms => alarms(context),
+            Command::AddAlarm(alarm) => match context.settings.add_alarm(alarm.clone()) {
+                Ok(()) => alarms(context),
+                Err(_) => String::from("no more alarms can be added"),
+            },
+            Command::RemoveAlarm(number) => match context.settings.remove_alarm(*number - 1) {
+                Some(alarm) => format!("removed alarm {}", alarm),
+                None => format!("no alarm {}", number),
+            },
+            Command::EnableAlarm(number, enabled) => {
+                if context
+                    .settings
+                    .update_alarm(*number - 1, |alarm| alarm.enabled = *enabled)
+                {
+                    alarms(context)
+                } else {
+                    format!("no alarm {}", number)
+                }
+            }
+            Command::DismissAlarm => match context.alarms.dismiss() {
+                Some(ringing) => format!("dismissed alarm {}", ringing.alarm),
+                None => String::from("no alarm is ringing"),
+            },
+            Command::SnoozeAlarm => match context.alarms.snooze(context.time.now()) {
+                Some(until) => format!(
+                    "snoozed until {}",
+                    until.with_timezone(&context.settings.time_zone()).time()
+                ),
+                None => String::from("no alarm is ringing"),
+            },
+            Command::ShowTimer => timer(context),
+            Command::StartCountdown(duration) => {
+                context
+                    .timer
+                    .start_countdown(*duration, context.time.millis());
+                timer(context)
+            }
+            Command::StartStopwatch => {
+                context.timer.start_stopwatch(context.time.millis());
+                timer(context)
+            }
+            Command::PauseTimer => {
+                context.timer.pause(context.time.millis());
+                timer(context)
+            }
+            Command::ResumeTimer => {
+                context.timer.resume(context.time.millis());
+                timer(context)
+            }
+            Command::StopTimer => {
+                context.timer.stop();
+                timer(context)
+            }
+            Command::ShowFace => face(context),
+            Command::SetFace(face_id) => {
+                context.settings.set_rotation(Vec::new());
+                context.settings.set_active_face(*face_id);
+                face(context)
+            }
+            Command::SetRotation(rotation) => {
+                context.settings.set_rotation(rotation.clone());
+                face(context)
+            }
+            Command::ShowTransition => transition(context),
+            Command::SetTransition(kind) => {
+                context.settings.set_transition(*kind);
+                transition(context)
+            }
+            Command::ShowScenery => scenery(context),
+            Command::SetScenery(scenery_value) => {
+                context.settings.set_scenery(*scenery_value);
+                scenery(context)
+            }
+            Command::ShowWeather => weather(context),
+            Command::SetWeather(condition) => {
+                context.weather.set_condition(*condition);
+                weather(context)
+            }
+            Command::FetchWeather => {
+                if context.settings.location().is_none() {
+                    return String::from("weather needs a location, see the location command");
+                }
+                context.weather.requested.signal(());
+                String::from("weather requested")
+            }
+            Command::ShowTemperature => temperature(context),
+            Command::SetTemperatureUnit(unit) => {
+                context.settings.set_temperature_unit(*unit);
+                temperature(context)
+            }
+            Command::SetShowTemperature(enabled) => {
+                context.settings.set_show_temperature(*enabled);
+                temperature(context)
+            }
+            Command::ShowSensor => sensor(context),
+            Command::ShowLocation => location(context),
+            Command::SetLocation(location_value) => {
+                context.settings.set_location(*location_value);
+                location(context)
+            }
+        }
+    }
+}
+
+/// Parses the arguments of `alarm add`, the words of the label are joined
+/// by single spaces.
+fn parse_alarm<'a>(time: &str, days: &str, label: impl Iterator<Item = &'a str>) -> Option<Alarm> {
+    let time = NaiveTime::parse_from_str(time, "%H:%M").ok()?;
+    let (days, recurring) = match days.strip_suffix("once") {
+        Some("") => (Weekdays::EVERY_DAY, false),
+        Some(days) => (days.strip_suffix(',')?.parse().ok()?, false),
+        None => (days.parse().ok()?, true),
+    };
+    if days.is_empty() {
+        return None;
+    }
+    let label = label.collect::<alloc::vec::Vec<_>>().join(" ");
+    Some(Alarm {
+        time,
+        weekdays: days,
+        recurring,
+        enabled: true,
+        label: label.as_str().try_into().ok()?,
+    })
+}
+
+/// Parses the number of an alarm as listed, starting at 1.
+fn parse_number(number: &str) -> Result<usize, CommandError> {
+    match number.parse() {
+        Ok(number) if number > 0 => Ok(number),
+        _ => Err(CommandError::InvalidArgument),
+    }
+}
+
+fn face(context: &CommandContext) -> String {
+    let mut reply = format!("face: {}", context.settings.active_face().name());
+    for (n, step) in context.settings.rotation().iter().enumerate() {
+        let separator = if n == 0 { ", rotating " } else { "," };
+        reply += &format!("{}{}:{}", separator, step.face.name(), step.seconds);
+        if let Some(transition) = step.transition {
+            reply += &format!(":{}", transition.name());
+        }
+    }
+    reply
+}
+
+fn scenery(context: &CommandContext) -> String {
+    format!("scenery: {}", context.settings.scenery().name())
+}
+
+fn weather(context: &CommandContext) -> String {
+    let mut reply = match context.weather.condition() {
+        Some(condition) => format!("weather: {}", condition.name()),
+        None => String::from("weather: off"),
+    };
+    if let Some(report) = context.weather.report() {
+        let unit = context.settings.temperature_unit();
+        reply += &format!(
+            ", last fetched {} at {:.1} {} {} minutes ago",
+            report.conditions.condition.name(),
+            unit.convert(report.conditions.temperature),
+            unit.symbol(),
+            report.age_minutes(context.time.millis())
+        );
+    }
+    reply
+}
+
+fn sensor(context: &CommandContext) -> String {
+    let (Some(name), Some(reading)) = (
+        context.sensor.sensor(),
+        context.sensor.latest(context.time.millis()),
+    ) else {
+        return String::from("sensor: no reading");
+    };
+    let unit = context.settings.temperature_unit();
+    let mut reply = format!(
+        "sensor: {}, {:.1} {}",
+        name,
+        unit.convert(reading.temperature),
+        unit.symbol()
+    );
+    if let Some(humidity) = reading.humidity {
+        reply += &format!(", {:.0} % humidity", humidity);
+    }
+    if let Some(pressure) = reading.pressure {
+        reply += &format!(", {:.1} hPa", pressure);
+    }
+    reply
+}
+
+fn temperature(context: &CommandContext) -> String {
+    let shown = if context.settings.show_temperature() {
+        "shown"
+    } else {
+        "hidden"
+    };
+    let unit = context.settings.temperature_unit();
+    let now = match context.weather.temperature(context.time.millis()) {
+        Some(celsius) => format!("{:.1} {}", unit.convert(celsius), unit.symbol()),
+        None => String::from("unknown"),
+    };
+    format!("temperature: {}, {} in {}", now, shown, unit.name())
+}
+
+fn location(context: &CommandContext) -> String {
+    let Some(location) = context.settings.location() else {
+        return String::from("location: off");
+    };
+    if !context.time.is_known() {
+        return format!("location: {}", location);
+    }
+    let time_zone = context.settings.time_zone();
+    let today = context.time.now().with_timezone(&time_zone).date_naive();
+    match sun::events(location, today) {
+        SunEvents::Daily { sunrise, sunset } => format!(
+            "location: {}, sunrise {}, sunset {}",
+            location,
+            hour_minute(sunrise, time_zone),
+            hour_minute(sunset, time_zone)
+        ),
+        SunEvents::PolarDay => format!("location: {}, sun up all day", location),
+        SunEvents::PolarNight => format!("location: {}, sun down all day", location),
+    }
+}
+
+/// `time` as `HH:MM` in `time_zone`.
+fn hour_minute(time: DateTime<Utc>, time_zone: Tz) -> String {
+    let time = time.with_timezone(&time_zone);
+    format!("{:02}:{:02}", time.hour(), time.minute())
+}
+
+fn transition(context: &CommandContext) -> String {
+    format!("transition: {}", context.settings.transition().name())
+}
+
+fn timer(context: &CommandContext) -> String {
+    match context.timer.reading(context.time.millis()) {
+        Some(reading) if context.timer.is_paused() => format!("{}, paused", reading),
+        Some(reading) => format!("{}", reading),
+        None => String::from("timer off, showing the clock"),
+    }
+}
+
+fn alarms(context: &CommandContext) -> String {
+    let alarms = context.settings.alarms();
+    if alarms.is_empty() {
+        return String::from("no alarms");
+    }
+    let mut list = String::from("alarms:");
+    for (index, alarm) in alarms.iter().enumerate() {
+        list += &format!("\n{}: {}", index + 1, alarm);
+    }
+    list
+}
+
+fn time_zone(context: &CommandContext) -> String {
+    format!("time zone: {}", context.settings.time_zone())
+}
+
+fn hours(context: &CommandContext) -> String {
+    let zero = if context.settings.leading_zero() {
+        "with"
+    } else {
+        "without"
+    };
+    format!(
+        "{} hour clock, {} leading zero",
+        context.settings.hour_format().hours(),
+        zero
+    )
+}
+
+fn sync_status(context: &CommandContext) -> String {
+    let interval = context.settings.sync_interval_minutes();
+    let slew = context.settings.slew_window_seconds();
+    let Some(status) = context.sync.status() else {
+        return format!(
+            "not synced yet, interval {} min, slew window {} s",
+            interval, slew
+        );
+    };
+    let drift = match status.drift_ppm {
+        Some(ppm) => format!("{:.1} ppm", ppm),
+        None => String::from("unknown"),
+    };
+    format!(
+        "last sync {} from {}, offset {} ms, drift {}, interval {} min, slew window {} s",
+        status.sample.time,
+        status.sample.kind.name(),
+        status.offset.num_milliseconds(),
+        drift,
+        interval,
+        slew
+    )
+}
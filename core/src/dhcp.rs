@@ -0,0 +1,374 @@
+use core::{future::Future, net::Ipv4Addr, time::Duration};
+
+use heapless::Vec;
+
+pub const DHCP_SERVER_PORT: u16 = 67;
+pub const DHCP_CLIENT_PORT: u16 = 68;
+/// Size of the sent messages, padded to the minimum BOOTP message size some
+/// servers insist on.
+pub const MESSAGE_SIZE: usize = 300;
+/// Largest reply, the client doesn't offer to take more (option 57).
+pub const MAX_REPLY_SIZE: usize = 576;
+/// Most NTP servers taken from a lease.
+pub const MAX_NTP_SERVERS: usize = 4;
+/// Most DNS servers taken from a lease, as many as embassy-net uses.
+pub const MAX_DNS_SERVERS: usize = 3;
+/// How long to wait for the server to answer a request, however many other
+/// replies arrive meanwhile.
+pub const REPLY_TIMEOUT: Duration = Duration::from_secs(4);
+/// Longest host name sent, the rest of the options have to fit.
+pub const MAX_HOSTNAME_LEN: usize = 32;
+
+const OP_REQUEST: u8 = 1;
+const OP_REPLY: u8 = 2;
+const HTYPE_ETHERNET: u8 = 1;
+/// Asks the server to broadcast its replies, the client can't receive
+/// unicast before it has an address.
+const FLAG_BROADCAST: u16 = 0x8000;
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+const OPTIONS_START: usize = 240;
+
+const OPTION_PAD: u8 = 0;
+const OPTION_SUBNET_MASK: u8 = 1;
+const OPTION_ROUTER: u8 = 3;
+const OPTION_DNS_SERVERS: u8 = 6;
+const OPTION_HOSTNAME: u8 = 12;
+const OPTION_NTP_SERVERS: u8 = 42;
+const OPTION_REQUESTED_ADDRESS: u8 = 50;
+const OPTION_LEASE_TIME: u8 = 51;
+const OPTION_MESSAGE_TYPE: u8 = 53;
+const OPTION_SERVER_ID: u8 = 54;
+const OPTION_PARAMETER_REQUEST_LIST: u8 = 55;
+const OPTION_RENEWAL_TIME: u8 = 58;
+const OPTION_END: u8 = 255;
+
+/// The options asked for in every request.
+const PARAMETER_REQUEST_LIST: [u8; 6] = [
+    OPTION_SUBNET_MASK,
+    OPTION_ROUTER,
+    OPTION_DNS_SERVERS,
+    OPTION_NTP_SERVERS,
+    OPTION_LEASE_TIME,
+    OPTION_RENEWAL_TIME,
+];
+
+const DHCPDISCOVER: u8 = 1;
+const DHCPOFFER: u8 = 2;
+const DHCPREQUEST: u8 = 3;
+const DHCPACK: u8 = 5;
+const DHCPNAK: u8 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DhcpError {
+    /// Sending the request or receiving the reply failed.
+    Network,
+    /// The server didn't reply in time.
+    Timeout,
+    /// The reply is malformed or doesn't answer our request.
+    InvalidReply,
+    /// The server declined the request with a DHCPNAK.
+    NotAcknowledged,
+}
+
+/// The network configuration handed out by the DHCP server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lease {
+    pub address: Ipv4Addr,
+    pub prefix_len: u8,
+    pub router: Option<Ipv4Addr>,
+    pub dns_servers: Vec<Ipv4Addr, MAX_DNS_SERVERS>,
+    /// Option 42, empty if the server doesn't know any.
+    pub ntp_servers: Vec<Ipv4Addr, MAX_NTP_SERVERS>,
+    /// The server identifier, where renewals go.
+    pub server: Ipv4Addr,
+    pub lease_time: Duration,
+    /// When to renew, half of the lease time unless the server says so.
+    pub renewal_time: Duration,
+}
+
+/// A reply of the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reply {
+    Offer(Lease),
+    Ack(Lease),
+}
+
+impl Reply {
+    pub fn offer(self) -> Option<Lease> {
+        match self {
+            Reply::Offer(lease) => Some(lease),
+            Reply::Ack(_) => None,
+        }
+    }
+
+    pub fn ack(self) -> Option<Lease> {
+        match self {
+            Reply::Ack(lease) => Some(lease),
+            Reply::Offer(_) => None,
+        }
+    }
+}
+
+/// Network access of the DHCP client, implemented on top of the network
+/// stack of the device and of the host in tests. Both are bound to the
+/// [`DHCP_CLIENT_PORT`].
+pub trait DhcpTransport {
+    /// Sends `request` to the [`DHCP_SERVER_PORT`] of `server`, the
+    /// broadcast address before there is a lease.
+    fn send(
+        &mut self,
+        server: Ipv4Addr,
+        request: &[u8],
+    ) -> impl Future<Output = Result<(), DhcpError>>;
+
+    /// Waits up to `timeout` for a datagram, returns its length.
+    fn receive(
+        &mut self,
+        reply: &mut [u8],
+        timeout: Duration,
+    ) -> impl Future<Output = Result<usize, DhcpError>>;
+
+    /// Uptime in milliseconds, to wait for the reply to a request until a
+    /// deadline.
+    fn millis(&self) -> u64;
+}
+
+/// The DHCP client of the clock, which asks for the NTP servers along with
+/// the address.
+#[derive(Debug, Clone, Copy)]
+pub struct DhcpClient<'a> {
+    pub mac: [u8; 6],
+    /// Sent as option 12, cut to [`MAX_HOSTNAME_LEN`].
+    pub hostname: &'a str,
+}
+
+impl DhcpClient<'_> {
+    /// Gets a new lease: broadcasts a DHCPDISCOVER, then requests the first
+    /// offer, see RFC 2131 3.1. The offers of further servers are ignored.
+    pub async fn acquire<T: DhcpTransport>(
+        &self,
+        transport: &mut T,
+        xid: u32,
+    ) -> Result<Lease, DhcpError> {
+        let discover = self.discover(xid);
+        let offer = self
+            .exchange(transport, Ipv4Addr::BROADCAST, &discover, xid, Reply::offer)
+            .await?;
+        let request = self.request(xid, &offer);
+        self.exchange(transport, Ipv4Addr::BROADCAST, &request, xid, Reply::ack)
+            .await
+    }
+
+    /// Extends `lease` with the server that handed it out, see RFC 2131
+    /// 4.3.2. The renewed lease may differ, e.g. in its NTP servers.
+    pub async fn renew<T: DhcpTransport>(
+        &self,
+        transport: &mut T,
+        xid: u32,
+        lease: &Lease,
+    ) -> Result<Lease, DhcpError> {
+        let request = self.renewal(xid, lease.address);
+        self.exchange(transport, lease.server, &request, xid, Reply::ack)
+            .await
+    }
+
+    /// A DHCPDISCOVER looking for a server.
+    pub fn discover(&self, xid: u32) -> [u8; MESSAGE_SIZE] {
+        let mut message = Message::new(xid, self.mac, Ipv4Addr::UNSPECIFIED);
+        message.option(OPTION_MESSAGE_TYPE, &[DHCPDISCOVER]);
+        self.common_options(&mut message);
+        message.finish()
+    }
+
+    /// A DHCPREQUEST taking `offer`.
+    pub fn request(&self, xid: u32, offer: &Lease) -> [u8; MESSAGE_SIZE] {
+        let mut message = Message::new(xid, self.mac, Ipv4Addr::UNSPECIFIED);
+        message.option(OPTION_MESSAGE_TYPE, &[DHCPREQUEST]);
+        message.option(OPTION_REQUESTED_ADDRESS, &offer.address.octets());
+        message.option(OPTION_SERVER_ID, &offer.server.octets());
+        self.common_options(&mut message);
+        message.finish()
+    }
+
+    /// A DHCPREQUEST extending the lease of `address`.
+    pub fn renewal(&self, xid: u32, address: Ipv4Addr) -> [u8; MESSAGE_SIZE] {
+        let mut message = Message::new(xid, self.mac, address);
+        message.option(OPTION_MESSAGE_TYPE, &[DHCPREQUEST]);
+        self.common_options(&mut message);
+        message.finish()
+    }
+
+    /// Sends `request` and waits up to [`REPLY_TIMEOUT`] for the reply to it
+    /// `wanted` takes, skipping replies to other clients and other replies to
+    /// this one, e.g. a second server's offer while waiting for the DHCPACK.
+    async fn exchange<T: DhcpTransport>(
+        &self,
+        transport: &mut T,
+        server: Ipv4Addr,
+        request: &[u8],
+        xid: u32,
+        wanted: fn(Reply) -> Option<Lease>,
+    ) -> Result<Lease, DhcpError> {
+        transport.send(server, request).await?;
+        let deadline = transport.millis() + REPLY_TIMEOUT.as_millis() as u64;
+        let mut reply = [0u8; MAX_REPLY_SIZE];
+        loop {
+            let remaining = deadline.saturating_sub(transport.millis());
+            if remaining == 0 {
+                return Err(DhcpError::Timeout);
+            }
+            let len = transport
+                .receive(&mut reply, Duration::from_millis(remaining))
+                .await?;
+            match parse_reply(&reply[..len], xid, self.mac) {
+                Ok(reply) => {
+                    if let Some(lease) = wanted(reply) {
+                        return Ok(lease);
+                    }
+                }
+                Err(DhcpError::InvalidReply) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn common_options(&self, message: &mut Message) {
+        message.option(OPTION_PARAMETER_REQUEST_LIST, &PARAMETER_REQUEST_LIST);
+        let hostname = self.hostname.as_bytes();
+        if !hostname.is_empty() {
+            message.option(
+                OPTION_HOSTNAME,
+                &hostname[..hostname.len().min(MAX_HOSTNAME_LEN)],
+            );
+        }
+    }
+}
+
+/// A message of the client under construction.
+struct Message {
+    packet: [u8; MESSAGE_SIZE],
+    len: usize,
+}
+
+impl Message {
+    /// The header of a message from `mac`, which has the address `ciaddr`
+    /// or none yet.
+    fn new(xid: u32, mac: [u8; 6], ciaddr: Ipv4Addr) -> Self {
+        let mut packet = [0u8; MESSAGE_SIZE];
+        packet[0] = OP_REQUEST;
+        packet[1] = HTYPE_ETHERNET;
+        packet[2] = mac.len() as u8;
+        packet[4..8].copy_from_slice(&xid.to_be_bytes());
+        if ciaddr.is_unspecified() {
+            packet[10..12].copy_from_slice(&FLAG_BROADCAST.to_be_bytes());
+        }
+        packet[12..16].copy_from_slice(&ciaddr.octets());
+        packet[28..34].copy_from_slice(&mac);
+        packet[236..OPTIONS_START].copy_from_slice(&MAGIC_COOKIE);
+        Message {
+            packet,
+            len: OPTIONS_START,
+        }
+    }
+
+    fn option(&mut self, code: u8, value: &[u8]) {
+        self.packet[self.len] = code;
+        self.packet[self.len + 1] = value.len() as u8;
+        self.packet[self.len + 2..self.len + 2 + value.len()].copy_from_slice(value);
+        self.len += 2 + value.len();
+    }
+
+    fn finish(mut self) -> [u8; MESSAGE_SIZE] {
+        self.packet[self.len] = OPTION_END;
+        self.packet
+    }
+}
+
+/// Parses the DHCPOFFER or DHCPACK answering the message with `xid` from
+/// the client with `mac`.
+pub fn parse_reply(reply: &[u8], xid: u32, mac: [u8; 6]) -> Result<Reply, DhcpError> {
+    if reply.len() < OPTIONS_START
+        || reply[0] != OP_REPLY
+        || reply[4..8] != xid.to_be_bytes()
+        || reply[28..34] != mac
+        || reply[236..OPTIONS_START] != MAGIC_COOKIE
+    {
+        return Err(DhcpError::InvalidReply);
+    }
+
+    let mut message_type = None;
+    let mut subnet_mask = None;
+    let mut router = None;
+    let mut dns_servers = Vec::new();
+    let mut ntp_servers = Vec::new();
+    let mut server = None;
+    let mut lease_time = None;
+    let mut renewal_time = None;
+    let mut options = &reply[OPTIONS_START..];
+    loop {
+        match options {
+            [] | [OPTION_END, ..] => break,
+            [OPTION_PAD, rest @ ..] => options = rest,
+            [code, len, rest @ ..] => {
+                let (value, rest) = rest
+                    .split_at_checked(*len as usize)
+                    .ok_or(DhcpError::InvalidReply)?;
+                match *code {
+                    OPTION_MESSAGE_TYPE => message_type = value.first().copied(),
+                    OPTION_SUBNET_MASK => subnet_mask = Some(address(value)?),
+                    OPTION_ROUTER => router = addresses::<1>(value)?.first().copied(),
+                    OPTION_DNS_SERVERS => dns_servers = addresses(value)?,
+                    OPTION_NTP_SERVERS => ntp_servers = addresses(value)?,
+                    OPTION_SERVER_ID => server = Some(address(value)?),
+                    OPTION_LEASE_TIME => lease_time = Some(seconds(value)?),
+                    OPTION_RENEWAL_TIME => renewal_time = Some(seconds(value)?),
+                    _ => {}
+                }
+                options = rest;
+            }
+            [_] => return Err(DhcpError::InvalidReply),
+        }
+    }
+
+    let lease = || {
+        let lease_time = lease_time.ok_or(DhcpError::InvalidReply)?;
+        Ok(Lease {
+            address: Ipv4Addr::new(reply[16], reply[17], reply[18], reply[19]),
+            prefix_len: u32::from(subnet_mask.ok_or(DhcpError::InvalidReply)?).leading_ones() as u8,
+            router,
+            dns_servers,
+            ntp_servers,
+            server: server.ok_or(DhcpError::InvalidReply)?,
+            lease_time,
+            renewal_time: renewal_time.unwrap_or(lease_time / 2),
+        })
+    };
+    match message_type {
+        Some(DHCPOFFER) => lease().map(Reply::Offer),
+        Some(DHCPACK) => lease().map(Reply::Ack),
+        Some(DHCPNAK) => Err(DhcpError::NotAcknowledged),
+        _ => Err(DhcpError::InvalidReply),
+    }
+}
+
+fn address(value: &[u8]) -> Result<Ipv4Addr, DhcpError> {
+    let octets: [u8; 4] = value.try_into().map_err(|_| DhcpError::InvalidReply)?;
+    Ok(Ipv4Addr::from(octets))
+}
+
+/// The first `N` addresses of a list option.
+fn addresses<const N: usize>(value: &[u8]) -> Result<Vec<Ipv4Addr, N>, DhcpError> {
+    if value.is_empty() || value.len() % 4 != 0 {
+        return Err(DhcpError::InvalidReply);
+    }
+    Ok(value
+        .chunks_exact(4)
+        .take(N)
+        .map(|addr| Ipv4Addr::new(addr[0], addr[1], addr[2], addr[3]))
+        .collect())
+}
+
+fn seconds(value: &[u8]) -> Result<Duration, DhcpError> {
+    let seconds: [u8; 4] = value.try_into().map_err(|_| DhcpError::InvalidReply)?;
+    Ok(Duration::from_secs(u32::from_be_bytes(seconds) as u64))
+}
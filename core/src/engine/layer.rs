@@ -0,0 +1,78 @@
+use embedded_graphics::{pixelcolor::Rgb888, prelude::DrawTarget};
+use heapless::Vec;
+
+use crate::COLS;
+
+use super::object::Object;
+
+/// One minute in milliseconds, the unit of the scroll speed.
+const MINUTE_MS: u64 = 60_000;
+
+/// A background layer of objects scrolling to the left at their own speed,
+/// e.g. slow clouds in front of the sky. Objects leaving the display on the
+/// left come back on the right, the whole layer repeats every [`COLS`]
+/// pixels.
+///
+/// The scroll position follows the elapsed milliseconds rather than the
+/// frames drawn, so the speed doesn't depend on the frame rate.
+pub struct Layer<const N: usize> {
+    objects: Vec<(Object, i32, i32), N>,
+    /// Pixels per minute, 0 keeps the layer still.
+    speed: u32,
+    /// Uptime of the last speed change.
+    since_ms: u64,
+    /// Scroll position at `since_ms`, in pixels times [`MINUTE_MS`].
+    scrolled: u64,
+}
+
+impl<const N: usize> Layer<N> {
+    /// Creates a still layer of `objects` at their position without
+    /// scrolling.
+    pub fn new(objects: [(Object, i32, i32); N]) -> Self {
+        Layer {
+            objects: Vec::from_iter(objects),
+            speed: 0,
+            since_ms: 0,
+            scrolled: 0,
+        }
+    }
+
+    /// Scrolls at `pixels_per_minute` from `millis` on, keeping the current
+    /// position.
+    pub fn set_speed(&mut self, pixels_per_minute: u32, millis: u64) {
+        if pixels_per_minute == self.speed {
+            return;
+        }
+        self.scrolled = self.scrolled(millis);
+        self.since_ms = millis;
+        self.speed = pixels_per_minute;
+    }
+
+    fn scrolled(&self, millis: u64) -> u64 {
+        let elapsed = millis.saturating_sub(self.since_ms);
+        // Wrapped so it never overflows, the layer repeats anyway
+        (self.scrolled + elapsed * self.speed as u64) % (COLS as u64 * MINUTE_MS)
+    }
+
+    /// Pixels the layer scrolled to the left at `millis`, below [`COLS`].
+    pub fn offset(&self, millis: u64) -> i32 {
+        (self.scrolled(millis) / MINUTE_MS) as i32
+    }
+
+    /// Draws the objects at `millis`, with their colors passed through `map`
+    /// like [`Object::draw_mapped`].
+    pub fn draw_mapped<D>(&self, millis: u64, fb: &mut D, map: impl Fn(u16) -> u16)
+    where
+        D: DrawTarget<Color = Rgb888>,
+    {
+        let offset = self.offset(millis);
+        for (object, x, y) in &self.objects {
+            let x = (x - offset).rem_euclid(COLS as i32);
+            object.draw_mapped(x, *y, fb, &map);
+            // The part past the right edge shows on the left
+            if x + object.width() > COLS as i32 {
+                object.draw_mapped(x - COLS as i32, *y, fb, &map);
+            }
+        }
+    }
+}
@@ -1,15 +1,15 @@
 use embassy_sync::{
     blocking_mutex::raw::CriticalSectionRawMutex,
-    pubsub::{Publisher, Subscriber},
+    pubsub::{PubSubChannel, Publisher, Subscriber},
 };
-use embassy_time::Instant;
 
+pub mod layer;
 pub mod object;
 pub mod tile;
 
 // Type definitions and basic structs
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub(crate) enum Direction {
+pub enum Direction {
     Up,
     Down,
     //Left,
@@ -17,7 +17,7 @@ pub(crate) enum Direction {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub(crate) struct SpriteInfo {
+pub struct SpriteInfo {
     pub name: &'static str,
     pub x: i8,
     pub y: i8,
@@ -26,18 +26,16 @@ pub(crate) struct SpriteInfo {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub(crate) enum Event {
+pub enum Event {
     Move(SpriteInfo),
     Collision(SpriteInfo),
 }
 
-// Utility functions
-pub(crate) fn millis() -> u64 {
-    Instant::now().as_millis()
-}
+/// Channel the sprites of a scene use to exchange [`Event`]s.
+pub type EventChannel = PubSubChannel<CriticalSectionRawMutex, Event, 3, 4, 4>;
 
 // Core sprite trait
-pub(crate) trait Sprite: Send + Sync {
+pub trait Sprite: Send + Sync {
     // Required properties
     fn x(&self) -> i8;
     fn y(&self) -> i8;
@@ -73,7 +71,7 @@ pub(crate) trait Sprite: Send + Sync {
 pub mod font {
     // Font related structs
     #[derive(Debug, Clone)]
-    pub(crate) struct GFXfont<'a> {
+    pub struct GFXfont<'a> {
         pub bitmap: &'a [u8],
         pub glyph: &'a [GFXglyph],
         pub first: u8,
@@ -81,7 +79,7 @@ pub mod font {
     }
 
     #[derive(Debug, Clone, Copy)]
-    pub(crate) struct GFXglyph {
+    pub struct GFXglyph {
         pub bitmap_offset: u16,
         pub width: u8,
         pub height: u8,
@@ -0,0 +1,40 @@
+use crate::display::{draw_rgb_bitmap, draw_rgb_bitmap_mapped};
+use embedded_graphics::{pixelcolor::Rgb888, prelude::DrawTarget};
+
+/// Objects are used to represent interactive elements within the
+/// game world, such as characters, items, or obstacles.
+pub struct Object {
+    image: &'static [u16],
+    width: i32,
+    height: i32,
+}
+
+impl Object {
+    pub fn new(image: &'static [u16], width: i32, height: i32) -> Self {
+        Object {
+            image,
+            width,
+            height,
+        }
+    }
+
+    pub fn draw<D>(&self, x: i32, y: i32, fb: &mut D)
+    where
+        D: DrawTarget<Color = Rgb888>,
+    {
+        draw_rgb_bitmap(fb, x, y, self.image, self.width, self.height);
+    }
+
+    /// Draws the object with its colors passed through `map`, see
+    /// [`draw_rgb_bitmap_mapped`].
+    pub fn draw_mapped<D>(&self, x: i32, y: i32, fb: &mut D, map: impl Fn(u16) -> u16)
+    where
+        D: DrawTarget<Color = Rgb888>,
+    {
+        draw_rgb_bitmap_mapped(fb, x, y, self.image, self.width, self.height, map);
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+}
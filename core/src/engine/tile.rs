@@ -0,0 +1,43 @@
+use crate::{display::draw_rgb_bitmap_mapped, ROWS};
+use embedded_graphics::{pixelcolor::Rgb888, prelude::DrawTarget};
+
+/// Tiles are used to represent static elements of the game world,
+/// such as the ground, walls, or other background elements.
+pub struct Tile {
+    image: &'static [u16],
+    width: i32,
+    height: i32,
+}
+
+impl Tile {
+    pub fn new(image: &'static [u16], width: i32, height: i32) -> Self {
+        Tile {
+            image,
+            width,
+            height,
+        }
+    }
+
+    pub fn fill_row<D>(&self, y: i32, fb: &mut D)
+    where
+        D: DrawTarget<Color = Rgb888>,
+    {
+        self.fill_row_mapped(y, 0, fb, |color| color);
+    }
+
+    /// Like [`Tile::fill_row`], scrolled `offset` pixels to the left and with
+    /// the colors passed through `map`.
+    pub fn fill_row_mapped<D>(&self, y: i32, offset: i32, fb: &mut D, map: impl Fn(u16) -> u16)
+    where
+        D: DrawTarget<Color = Rgb888>,
+    {
+        let start = -offset.rem_euclid(self.width);
+        for x in (start..ROWS as i32).step_by(self.width as usize) {
+            draw_rgb_bitmap_mapped(fb, x, y, self.image, self.width, self.height, &map);
+        }
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+}
@@ -0,0 +1,196 @@
+use chrono::{DateTime, Utc};
+use embedded_graphics::{pixelcolor::Rgb888, prelude::DrawTarget};
+use heapless::Vec;
+
+use crate::{
+    alarm::AlarmClock,
+    engine::EventChannel,
+    mario::{clockface::Clockface, dateface::DateFace},
+    sensor::SensorReadings,
+    settings::{FaceId, RotationStep, Settings, MAX_ROTATION_STEPS},
+    time::TimeSource,
+    timer::Timer,
+    transition::{Snapshot, Transition, TransitionKind},
+    weather::Weather,
+    ClockfaceTrait,
+};
+
+/// All clockfaces, created once and switched at runtime. The face shown is
+/// the active face in the settings, or the one due in the rotation. A face
+/// that needs attention, e.g. a ringing alarm, is shown regardless.
+///
+/// The faces keep their state while hidden, so switching doesn't set up the
+/// sprites and their [`EventChannel`] again. A switch plays the transition of
+/// the rotation step, or the one in the settings, given a snapshot buffer,
+/// see [`Faces::with_snapshot`].
+pub struct Faces<T: TimeSource + Clone> {
+    mario: Clockface<T>,
+    date: DateFace<T>,
+    time: T,
+    settings: &'static Settings,
+    /// Face drawn on the last frame, `None` before the first one.
+    shown: Option<FaceId>,
+    /// Running transition and the face it leaves.
+    transition: Option<(FaceId, Transition)>,
+    /// Where a transition records the face it leaves.
+    snapshot: Option<&'static mut Snapshot>,
+}
+
+impl<T: TimeSource + Clone> Faces<T> {
+    /// Creates every face, see [`Clockface::new`] for the arguments.
+    pub fn new(
+        channel: &'static EventChannel,
+        time: T,
+        settings: &'static Settings,
+        alarms: &'static AlarmClock,
+        timer: &'static Timer,
+        weather: &'static Weather,
+        sensor: &'static SensorReadings,
+    ) -> Self {
+        Faces {
+            mario: Clockface::new(
+                channel,
+                time.clone(),
+                settings,
+                alarms,
+                timer,
+                weather,
+                sensor,
+            ),
+            date: DateFace::new(time.clone(), settings, weather, sensor),
+            time,
+            settings,
+            shown: None,
+            transition: None,
+            snapshot: None,
+        }
+    }
+
+    /// Plays the transitions between the faces, recording the outgoing face
+    /// into `snapshot`. Without one every switch is a cut, so no frame is
+    /// allocated on a switch.
+    pub fn with_snapshot(mut self, snapshot: &'static mut Snapshot) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+
+    /// The face shown on the next frame.
+    pub fn active(&self) -> FaceId {
+        self.next().0
+    }
+
+    /// The face shown on the next frame and the transition to it, `None` for
+    /// the one in the settings.
+    fn next(&self) -> (FaceId, Option<TransitionKind>) {
+        if self.mario.needs_attention() {
+            return (FaceId::Mario, None);
+        }
+        match scheduled_step(&self.settings.rotation(), self.time.now()) {
+            Some(step) => (step.face, step.transition),
+            None => (self.settings.active_face(), None),
+        }
+    }
+}
+
+impl<T: TimeSource + Clone> ClockfaceTrait for Faces<T> {
+    async fn update<D>(&mut self, fb: &mut D)
+    where
+        D: DrawTarget<Color = Rgb888> + Send,
+    {
+        let (face, transition) = self.next();
+        let millis = self.time.millis();
+        if let Some(shown) = self.shown.filter(|shown| *shown != face) {
+            let kind = transition.unwrap_or_else(|| self.settings.transition());
+            self.transition = (kind != TransitionKind::Cut && self.snapshot.is_some())
+                .then(|| (shown, Transition::new(kind, millis)));
+        }
+        self.shown = Some(face);
+
+        let progress = self
+            .transition
+            .as_ref()
+            .and_then(|(_, transition)| transition.progress(millis));
+        match (self.transition, progress, &mut self.snapshot) {
+            (Some((from, transition)), Some(progress), Some(snapshot)) => {
+                draw(
+                    &mut self.mario,
+                    &mut self.date,
+                    from,
+                    &mut transition.record(fb, snapshot),
+                )
+                .await;
+                draw(
+                    &mut self.mario,
+                    &mut self.date,
+                    face,
+                    &mut transition.blend(fb, snapshot, progress),
+                )
+                .await;
+            }
+            _ => {
+                self.transition = None;
+                draw(&mut self.mario, &mut self.date, face, fb).await;
+            }
+        }
+    }
+}
+
+async fn draw<T, D>(mario: &mut Clockface<T>, date: &mut DateFace<T>, face: FaceId, fb: &mut D)
+where
+    T: TimeSource + Clone,
+    D: DrawTarget<Color = Rgb888> + Send,
+{
+    match face {
+        FaceId::Mario => mario.update(fb).await,
+        FaceId::Date => date.update(fb).await,
+    }
+}
+
+/// The face of `rotation` due at `now`, `None` without rotation. The
+/// schedule is aligned to the wall clock, so a rotation lasting a minute, e.g.
+/// Mario for 55 s and the date for 5 s, starts on the full minute and Mario
+/// still hits the blocks.
+pub fn scheduled_face(rotation: &[RotationStep], now: DateTime<Utc>) -> Option<FaceId> {
+    scheduled_step(rotation, now).map(|step| step.face)
+}
+
+/// The step of `rotation` due at `now`, see [`scheduled_face`].
+pub fn scheduled_step(rotation: &[RotationStep], now: DateTime<Utc>) -> Option<RotationStep> {
+    let total: i64 = rotation.iter().map(|step| step.seconds as i64).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut at = now.timestamp().rem_euclid(total);
+    for step in rotation {
+        if at < step.seconds as i64 {
+            return Some(*step);
+        }
+        at -= step.seconds as i64;
+    }
+    None
+}
+
+/// Parses a rotation like `mario:55,date:5:iris`, faces with the seconds they
+/// are shown and optionally the transition to them.
+pub fn parse_rotation(rotation: &str) -> Option<Vec<RotationStep, MAX_ROTATION_STEPS>> {
+    let mut steps = Vec::new();
+    for step in rotation.split(',') {
+        let mut parts = step.split(':');
+        let face = FaceId::from_name(parts.next()?)?;
+        let seconds = parts.next()?.parse().ok().filter(|seconds| *seconds > 0)?;
+        let transition = match parts.next() {
+            Some(name) => Some(TransitionKind::from_name(name)?),
+            None => None,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        let step = RotationStep {
+            face,
+            seconds,
+            transition,
+        };
+        steps.push(step).ok()?;
+    }
+    Some(steps)
+}
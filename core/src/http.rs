@@ -0,0 +1,146 @@
+use core::{
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use alloc::{format, string::String};
+
+pub const HTTP_PORT: u16 = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpError {
+    /// Only `http://` URLs are supported, there is no TLS.
+    InvalidUrl,
+    /// The server name couldn't be resolved.
+    Dns,
+    /// Connecting, sending the request or receiving the response failed.
+    Network,
+    /// The server didn't respond in time.
+    Timeout,
+    /// The response doesn't fit the buffer.
+    TooLarge,
+    /// The response isn't HTTP/1.x or is cut short.
+    InvalidResponse,
+    /// The server answered with a status other than 200.
+    Status(u16),
+}
+
+/// Network access of the HTTP client, implemented on top of the network
+/// stack of the device and of the host in tests.
+pub trait HttpTransport {
+    /// Resolves a host name or an IP address literal.
+    fn resolve(&mut self, host: &str) -> impl Future<Output = Result<IpAddr, HttpError>>;
+
+    /// Connects to `server`, sends `request` and reads the response until
+    /// the server closes the connection, all within `timeout`. Returns the
+    /// length of the response, [`HttpError::TooLarge`] if it doesn't fit.
+    fn exchange(
+        &mut self,
+        server: SocketAddr,
+        request: &[u8],
+        response: &mut [u8],
+        timeout: Duration,
+    ) -> impl Future<Output = Result<usize, HttpError>>;
+}
+
+/// Host, port and path of an `http://` URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Url<'a> {
+    pub host: &'a str,
+    pub port: u16,
+    /// The path with the query, `/` if the URL has none.
+    pub path: &'a str,
+}
+
+impl<'a> Url<'a> {
+    pub fn parse(url: &'a str) -> Result<Self, HttpError> {
+        let rest = url.strip_prefix("http://").ok_or(HttpError::InvalidUrl)?;
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| HttpError::InvalidUrl)?),
+            None => (authority, HTTP_PORT),
+        };
+        if host.is_empty() || !path.starts_with('/') {
+            return Err(HttpError::InvalidUrl);
+        }
+        Ok(Url { host, port, path })
+    }
+}
+
+/// A `GET` request of `url`. HTTP/1.0 keeps the response simple: no chunks
+/// and the connection closes after it.
+pub fn request(url: &Url) -> String {
+    format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: mario-clock-rs\r\nAccept: application/json\r\nConnection: close\r\n\r\n",
+        url.path, url.host
+    )
+}
+
+/// The body of a successful `response`, cut to its `Content-Length` if
+/// there is one.
+pub fn parse_response(response: &[u8]) -> Result<&[u8], HttpError> {
+    let end = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or(HttpError::InvalidResponse)?;
+    let head = core::str::from_utf8(&response[..end]).map_err(|_| HttpError::InvalidResponse)?;
+    let body = &response[end + 4..];
+
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| {
+            let mut parts = line.split(' ');
+            let version = parts.next()?;
+            version.starts_with("HTTP/1.").then_some(())?;
+            parts.next()?.parse::<u16>().ok()
+        })
+        .ok_or(HttpError::InvalidResponse)?;
+    if status != 200 {
+        return Err(HttpError::Status(status));
+    }
+
+    let mut length = None;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            length = Some(
+                value
+                    .parse::<usize>()
+                    .map_err(|_| HttpError::InvalidResponse)?,
+            );
+        } else if name.eq_ignore_ascii_case("transfer-encoding")
+            && !value.eq_ignore_ascii_case("identity")
+        {
+            // Not sent in answer to HTTP/1.0
+            return Err(HttpError::InvalidResponse);
+        }
+    }
+    match length {
+        Some(length) => body.get(..length).ok_or(HttpError::InvalidResponse),
+        None => Ok(body),
+    }
+}
+
+/// Fetches `url` into `buffer` and returns the body of the response.
+pub async fn get<'b, T: HttpTransport>(
+    transport: &mut T,
+    url: &str,
+    buffer: &'b mut [u8],
+    timeout: Duration,
+) -> Result<&'b [u8], HttpError> {
+    let url = Url::parse(url)?;
+    let server = SocketAddr::new(transport.resolve(url.host).await?, url.port);
+    let request = request(&url);
+    let len = transport
+        .exchange(server, request.as_bytes(), buffer, timeout)
+        .await?;
+    parse_response(&buffer[..len])
+}
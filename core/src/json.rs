@@ -0,0 +1,188 @@
+/// A JSON value borrowed from the document, parsed lazily: objects and arrays
+/// are only walked when looked into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value<'a> {
+    Null,
+    Bool(bool),
+    /// The number as written, see [`Value::as_f32`].
+    Number(&'a str),
+    /// The string between the quotes, escapes aren't decoded.
+    String(&'a str),
+    /// The object including its braces, see [`Value::get`].
+    Object(&'a str),
+    /// The array including its brackets.
+    Array(&'a str),
+}
+
+impl<'a> Value<'a> {
+    /// Parses a whole document, `None` if it isn't valid JSON.
+    pub fn parse(json: &'a str) -> Option<Self> {
+        let mut parser = Parser { json, pos: 0 };
+        let value = parser.value()?;
+        parser.whitespace();
+        (parser.pos == json.len()).then_some(value)
+    }
+
+    /// The member `key` of an object, `None` for other values or if the
+    /// object doesn't have it.
+    pub fn get(&self, key: &str) -> Option<Value<'a>> {
+        let Value::Object(json) = *self else {
+            return None;
+        };
+        let mut parser = Parser { json, pos: 1 };
+        loop {
+            parser.whitespace();
+            if parser.eat(b'}') {
+                return None;
+            }
+            let name = parser.string()?;
+            parser.whitespace();
+            parser.expect(b':')?;
+            let value = parser.value()?;
+            if name == key {
+                return Some(value);
+            }
+            parser.whitespace();
+            if !parser.eat(b',') {
+                return None;
+            }
+        }
+    }
+
+    /// The value at `path`, a member of a member and so on.
+    pub fn path(&self, path: &[&str]) -> Option<Value<'a>> {
+        path.iter().try_fold(*self, |value, key| value.get(key))
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            Value::Number(number) => number.parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self {
+            Value::String(string) => Some(string),
+            _ => None,
+        }
+    }
+}
+
+/// Deepest nesting of objects and arrays accepted, bounds the recursion.
+const MAX_DEPTH: u8 = 16;
+
+struct Parser<'a> {
+    json: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.json.as_bytes().get(self.pos).copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let matches = self.peek() == Some(byte);
+        if matches {
+            self.pos += 1;
+        }
+        matches
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        self.eat(byte).then_some(())
+    }
+
+    fn whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\r' | b'\n')) {
+            self.pos += 1;
+        }
+    }
+
+    fn value(&mut self) -> Option<Value<'a>> {
+        self.nested(0)
+    }
+
+    fn nested(&mut self, depth: u8) -> Option<Value<'a>> {
+        self.whitespace();
+        let start = self.pos;
+        match self.peek()? {
+            b'"' => self.string().map(Value::String),
+            b'{' => {
+                self.container(depth, b'}', true)?;
+                Some(Value::Object(&self.json[start..self.pos]))
+            }
+            b'[' => {
+                self.container(depth, b']', false)?;
+                Some(Value::Array(&self.json[start..self.pos]))
+            }
+            b'-' | b'0'..=b'9' => {
+                while matches!(
+                    self.peek(),
+                    Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+                ) {
+                    self.pos += 1;
+                }
+                Some(Value::Number(&self.json[start..self.pos]))
+            }
+            _ => {
+                for (literal, value) in [
+                    ("null", Value::Null),
+                    ("true", Value::Bool(true)),
+                    ("false", Value::Bool(false)),
+                ] {
+                    if self.json[start..].starts_with(literal) {
+                        self.pos += literal.len();
+                        return Some(value);
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    /// Skips an object or an array, with `members` the elements are `key:
+    /// value` pairs.
+    fn container(&mut self, depth: u8, close: u8, members: bool) -> Option<()> {
+        if depth >= MAX_DEPTH {
+            return None;
+        }
+        self.pos += 1;
+        self.whitespace();
+        if self.eat(close) {
+            return Some(());
+        }
+        loop {
+            if members {
+                self.whitespace();
+                self.string()?;
+                self.whitespace();
+                self.expect(b':')?;
+            }
+            self.nested(depth + 1)?;
+            self.whitespace();
+            if self.eat(close) {
+                return Some(());
+            }
+            self.expect(b',')?;
+        }
+    }
+
+    /// A string without its quotes.
+    fn string(&mut self) -> Option<&'a str> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        loop {
+            match self.peek()? {
+                b'"' => break,
+                // The escaped character can't end the string
+                b'\\' => self.pos += 2,
+                _ => self.pos += 1,
+            }
+        }
+        let string = self.json.get(start..self.pos)?;
+        self.pos += 1;
+        Some(string)
+    }
+}
@@ -0,0 +1,42 @@
+#![no_std]
+
+//! Hardware independent parts of the Super Mario clock: the sprite engine,
+//! the Mario assets, the rendering helpers and the time conversions.
+//! Everything draws into any embedded-graphics [`DrawTarget`], so the scene
+//! can be rendered and tested on the device as well as on a host.
+
+use core::future::Future;
+use embedded_graphics::{pixelcolor::Rgb888, prelude::DrawTarget};
+
+pub mod alarm;
+pub mod clock;
+pub mod command;
+pub mod dhcp;
+pub mod display;
+pub mod engine;
+pub mod faces;
+pub mod http;
+pub mod json;
+pub mod mario;
+pub mod nmea;
+pub mod ntp;
+pub mod open_meteo;
+pub mod sensor;
+pub mod settings;
+pub mod source;
+pub mod sun;
+pub mod time;
+pub mod timer;
+pub mod transition;
+pub mod weather;
+
+extern crate alloc;
+
+pub const ROWS: usize = 64;
+pub const COLS: usize = 64;
+
+pub trait ClockfaceTrait {
+    fn update<D>(&mut self, fb: &mut D) -> impl Future<Output = ()> + Send
+    where
+        D: DrawTarget<Color = Rgb888> + Send;
+}
@@ -0,0 +1,290 @@
+use alloc::format;
+use chrono::{DateTime, Timelike};
+use embedded_graphics::{pixelcolor::Rgb888, prelude::DrawTarget};
+
+use crate::{
+    alarm::AlarmClock,
+    display::{print_small_text, print_text, small_text_width},
+    engine::{layer::Layer, object::Object, tile::Tile, EventChannel, Sprite},
+    sensor::SensorReadings,
+    settings::{HourFormat, Settings, TemperatureUnit},
+    time::TimeSource,
+    timer::{Timer, TimerReading},
+    weather::Weather,
+    ClockfaceTrait, COLS,
+};
+
+use super::{
+    gfx::{
+        assets::{BUSH, CLOUD1, CLOUD2, GROUND, HILL},
+        block::Block,
+        mario::Mario,
+    },
+    layer_speeds,
+    sky::SkyPhase,
+    temperature_text, weather,
+};
+
+/// Shown on the blocks while the time is unknown.
+const UNKNOWN_TEXT: &str = "--";
+
+/// Where the AM/PM indicator of the 12 hour clock is drawn, in small letters
+/// in the top left corner like the score of the game, clear of the hour
+/// block and its bounce.
+const MERIDIEM_X: i32 = 1;
+const MERIDIEM_Y: i32 = 1;
+const MERIDIEM_COLOR: u16 = 0xFFFF;
+
+/// Where the temperature is shown, right aligned above the bush, clear of
+/// the blocks and of Mario's jump.
+const TEMPERATURE_RIGHT: i32 = 62;
+const TEMPERATURE_Y: i32 = 40;
+const TEMPERATURE_COLOR: u16 = 0xFFFF;
+
+/// Shown in the top row while an alarm without a label rings.
+const ALARM_TEXT: &str = "ALARM";
+/// Shown in the top row while counting down, measuring and when the
+/// countdown ran out.
+const COUNTDOWN_TEXT: &str = "TIMER";
+const STOPWATCH_TEXT: &str = "WATCH";
+const TIME_UP_TEXT: &str = "TIME UP";
+/// Characters that fit the width of the display in the top row.
+const BANNER_CHARS: usize = COLS / 8;
+const BANNER_Y: i32 = 8;
+const BANNER_COLOR: u16 = 0xFFE0;
+/// A flashing banner is shown for half of this period and hidden for the
+/// other.
+const FLASH_MS: u64 = 1000;
+
+pub struct Clockface<T: TimeSource + Clone> {
+    ground: Tile,
+    bushes: Layer<1>,
+    hills: Layer<1>,
+    clouds: Layer<2>,
+    mario: Mario<T>,
+    hour_block: Block<T>,
+    minute_block: Block<T>,
+    time: T,
+    settings: &'static Settings,
+    alarms: &'static AlarmClock,
+    timer: &'static Timer,
+    weather: &'static Weather,
+    sensor: &'static SensorReadings,
+    /// Whether the countdown had run out on the last frame.
+    countdown_done: bool,
+    /// [`TimeSource::steps`] on the last frame.
+    steps: u32,
+    /// Hour and minute on the last frame.
+    shown: Option<(u32, u32)>,
+    /// Hour and minute Mario last jumped for.
+    jumped_for: Option<(u32, u32)>,
+}
+
+impl<T: TimeSource + Clone> Clockface<T> {
+    /// Creates the clockface. The sprites talk to each other over `channel`,
+    /// `time` drives the animations and the displayed time, e.g. the RTC/NTP
+    /// backed clock on the device or a [`crate::time::ManualTime`] on the host.
+    /// The time zone is read from `settings` on every frame. While one of
+    /// `alarms` rings, Mario jumps continuously under its flashing label.
+    /// While `timer` runs the blocks show it instead of the time. The
+    /// condition of `weather` is drawn in front of the scenery, its
+    /// temperature above the bush if enabled in `settings`. Without one the
+    /// temperature of the local `sensor` is shown.
+    pub fn new(
+        channel: &'static EventChannel,
+        time: T,
+        settings: &'static Settings,
+        alarms: &'static AlarmClock,
+        timer: &'static Timer,
+        weather: &'static Weather,
+        sensor: &'static SensorReadings,
+    ) -> Self {
+        let mut mario = Mario::new(23, 40, time.clone());
+        mario.subscribe(channel.publisher().unwrap(), channel.subscriber().unwrap());
+
+        let mut hour_block = Block::new(13, 8, time.clone());
+        hour_block.subscribe(channel.publisher().unwrap(), channel.subscriber().unwrap());
+
+        let mut minute_block = Block::new(32, 8, time.clone());
+        minute_block.subscribe(channel.publisher().unwrap(), channel.subscriber().unwrap());
+
+        Self {
+            ground: Tile::new(GROUND, 8, 8),
+            bushes: Layer::new([(Object::new(BUSH, 21, 9), 43, 47)]),
+            hills: Layer::new([(Object::new(HILL, 20, 22), 0, 34)]),
+            clouds: Layer::new([
+                (Object::new(CLOUD1, 13, 12), 0, 21),
+                (Object::new(CLOUD2, 13, 12), 51, 7),
+            ]),
+            mario,
+            hour_block,
+            minute_block,
+            steps: time.steps(),
+            time,
+            settings,
+            alarms,
+            timer,
+            weather,
+            sensor,
+            // A countdown that ran out before the first frame doesn't jump
+            countdown_done: true,
+            shown: None,
+            jumped_for: None,
+        }
+    }
+
+    pub fn now(&self) -> DateTime<chrono_tz::Tz> {
+        self.time.now().with_timezone(&self.settings.time_zone())
+    }
+
+    /// Whether an alarm rings or a timer runs, which only this face shows.
+    pub fn needs_attention(&self) -> bool {
+        self.alarms.ringing().is_some() || self.timer.reading(self.time.millis()).is_some()
+    }
+}
+
+impl<T: TimeSource + Clone> ClockfaceTrait for Clockface<T> {
+    async fn update<D>(&mut self, fb: &mut D)
+    where
+        D: DrawTarget<Color = Rgb888> + Send,
+    {
+        let millis = self.time.millis();
+        let sky = SkyPhase::now(&self.time, self.settings);
+        sky.draw(fb, millis);
+
+        // Farthest first, the bushes stand on the ground and scroll with it
+        let (clouds, hills, ground) = layer_speeds(self.settings.scenery());
+        self.clouds.set_speed(clouds, millis);
+        self.hills.set_speed(hills, millis);
+        self.bushes.set_speed(ground, millis);
+        let tint = sky.tint();
+        self.ground.fill_row_mapped(
+            COLS as i32 - self.ground.height(),
+            self.bushes.offset(millis),
+            fb,
+            &tint,
+        );
+        self.hills.draw_mapped(millis, fb, &tint);
+        self.bushes.draw_mapped(millis, fb, &tint);
+        self.clouds.draw_mapped(millis, fb, &tint);
+        if let Some(condition) = self.weather.condition() {
+            weather::draw(condition, sky, fb, millis);
+        }
+        if self.settings.show_temperature() {
+            let temperature = self.weather.temperature(millis).or_else(|| {
+                self.sensor
+                    .latest(millis)
+                    .map(|reading| reading.temperature)
+            });
+            if let Some(celsius) = temperature {
+                print_temperature(fb, celsius, self.settings.temperature_unit());
+            }
+        }
+        let ringing = self.alarms.ringing();
+        if let Some(ringing) = &ringing {
+            let label = match ringing.alarm.label.as_str() {
+                "" => ALARM_TEXT,
+                label => label,
+            };
+            print_banner(fb, label, true, millis);
+        }
+
+        // The timer runs on the uptime, so it is shown even while the time
+        // is unknown
+        if let Some(reading) = self.timer.reading(millis) {
+            let done = reading.is_done();
+            let jump = done && !self.countdown_done;
+            self.countdown_done = done;
+            if ringing.is_none() {
+                let paused = self.timer.is_paused();
+                let (text, flash) = match reading {
+                    _ if done => (TIME_UP_TEXT, true),
+                    TimerReading::Countdown(_) => (COUNTDOWN_TEXT, paused),
+                    TimerReading::Stopwatch(_) => (STOPWATCH_TEXT, paused),
+                };
+                print_banner(fb, text, flash, millis);
+            }
+            let (high, low) = reading.blocks();
+            self.mario.update(fb, jump || ringing.is_some()).await;
+            self.hour_block.update(fb, &format!("{:02}", high)).await;
+            self.minute_block.update(fb, &format!("{:02}", low)).await;
+            return;
+        }
+        self.countdown_done = true;
+
+        // Until the clock is set there is nothing to show but dashes
+        if !self.time.is_known() {
+            self.mario.update(fb, false).await;
+            self.hour_block.update(fb, UNKNOWN_TEXT).await;
+            self.minute_block.update(fb, UNKNOWN_TEXT).await;
+            return;
+        }
+
+        let now = self.now();
+        let minute = (now.hour(), now.minute());
+
+        // Jump every minute. A step of the clock can skip the full minute or
+        // repeat it, so after a step Mario jumps right away if the minute
+        // changed, and never twice for the same minute. While he is still in
+        // the air, e.g. from a ringing alarm, the jump is tried again on the
+        // following frames of the full minute.
+        let steps = self.time.steps();
+        let jump = if steps != self.steps {
+            self.shown != Some(minute)
+        } else {
+            now.second() == 0
+        } && self.jumped_for != Some(minute);
+        self.steps = steps;
+        self.shown = Some(minute);
+
+        let format = self.settings.hour_format();
+        if format == HourFormat::H12 && ringing.is_none() {
+            let meridiem = if now.hour() < 12 { "AM" } else { "PM" };
+            print_small_text(fb, meridiem, MERIDIEM_X, MERIDIEM_Y, MERIDIEM_COLOR);
+        }
+        let hour = format.display_hour(now.hour());
+        let hour = if self.settings.leading_zero() {
+            format!("{:02}", hour)
+        } else {
+            format!("{}", hour)
+        };
+
+        // Update the hour and minute blocks
+        // A ringing alarm keeps Mario jumping until it is dismissed or snoozed
+        if self.mario.update(fb, jump || ringing.is_some()).await && jump {
+            self.jumped_for = Some(minute);
+        }
+        self.hour_block.update(fb, &hour).await;
+        self.minute_block
+            .update(fb, &format!("{:02}", now.minute()))
+            .await;
+    }
+}
+
+/// Prints the temperature of `celsius` degrees in whole degrees of `unit`,
+/// e.g. `-3°C`.
+fn print_temperature<D>(fb: &mut D, celsius: f32, unit: TemperatureUnit)
+where
+    D: DrawTarget<Color = Rgb888>,
+{
+    let text = temperature_text(celsius, unit);
+    let x = TEMPERATURE_RIGHT + 1 - small_text_width(&text);
+    print_small_text(fb, &text, x, TEMPERATURE_Y, TEMPERATURE_COLOR);
+}
+
+/// Prints `text` centered in the top row, cut to the width of the display.
+/// A flashing banner is hidden for half of every [`FLASH_MS`].
+fn print_banner<D>(fb: &mut D, text: &str, flash: bool, millis: u64)
+where
+    D: DrawTarget<Color = Rgb888>,
+{
+    if flash && millis % FLASH_MS >= FLASH_MS / 2 {
+        return;
+    }
+    let text = text
+        .char_indices()
+        .nth(BANNER_CHARS)
+        .map_or(text, |(end, _)| &text[..end]);
+    let x = (COLS as i32 - 8 * text.chars().count() as i32) / 2;
+    print_text(fb, text, x, BANNER_Y, BANNER_COLOR);
+}
@@ -0,0 +1,133 @@
+use alloc::format;
+use chrono::Datelike;
+use embedded_graphics::{pixelcolor::Rgb888, prelude::DrawTarget};
+
+use crate::{
+    display::{print_small_text, print_text, small_text_width},
+    engine::{layer::Layer, object::Object, tile::Tile},
+    sensor::SensorReadings,
+    settings::Settings,
+    time::TimeSource,
+    weather::Weather,
+    ClockfaceTrait, COLS,
+};
+
+use super::{
+    gfx::assets::{BUSH, CLOUD1, GROUND},
+    layer_speeds,
+    sky::SkyPhase,
+    temperature_text, weather,
+};
+
+const WEEKDAYS: [&str; 7] = ["MON", "TUE", "WED", "THU", "FRI", "SAT", "SUN"];
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+/// Baselines of the weekday, the day and month and the year.
+const WEEKDAY_Y: i32 = 16;
+const DAY_Y: i32 = 30;
+const YEAR_Y: i32 = 44;
+const WEEKDAY_COLOR: u16 = 0xFFE0;
+const DATE_COLOR: u16 = 0xFFFF;
+/// Where the local sensor reading is shown, right aligned above the ground.
+const INDOOR_RIGHT: i32 = 62;
+const INDOOR_Y: i32 = 49;
+const INDOOR_COLOR: u16 = 0xFFFF;
+
+/// Shown instead of the date while the time is unknown.
+const UNKNOWN_TEXT: &str = "--";
+
+/// The date in the Mario world, e.g. shown for a few seconds between the
+/// clock in a rotation. The temperature and humidity of the local sensor are
+/// shown below it.
+pub struct DateFace<T: TimeSource> {
+    ground: Tile,
+    bushes: Layer<1>,
+    clouds: Layer<1>,
+    time: T,
+    settings: &'static Settings,
+    weather: &'static Weather,
+    sensor: &'static SensorReadings,
+}
+
+impl<T: TimeSource> DateFace<T> {
+    pub fn new(
+        time: T,
+        settings: &'static Settings,
+        weather: &'static Weather,
+        sensor: &'static SensorReadings,
+    ) -> Self {
+        Self {
+            ground: Tile::new(GROUND, 8, 8),
+            bushes: Layer::new([(Object::new(BUSH, 21, 9), 3, 47)]),
+            clouds: Layer::new([(Object::new(CLOUD1, 13, 12), 48, 2)]),
+            time,
+            settings,
+            weather,
+            sensor,
+        }
+    }
+}
+
+impl<T: TimeSource> ClockfaceTrait for DateFace<T> {
+    async fn update<D>(&mut self, fb: &mut D)
+    where
+        D: DrawTarget<Color = Rgb888> + Send,
+    {
+        let millis = self.time.millis();
+        let sky = SkyPhase::now(&self.time, self.settings);
+        sky.draw(fb, millis);
+        let (clouds, _, ground) = layer_speeds(self.settings.scenery());
+        self.clouds.set_speed(clouds, millis);
+        self.bushes.set_speed(ground, millis);
+        let tint = sky.tint();
+        self.ground.fill_row_mapped(
+            COLS as i32 - self.ground.height(),
+            self.bushes.offset(millis),
+            fb,
+            &tint,
+        );
+        self.bushes.draw_mapped(millis, fb, &tint);
+        self.clouds.draw_mapped(millis, fb, &tint);
+        if let Some(condition) = self.weather.condition() {
+            weather::draw(condition, sky, fb, millis);
+        }
+        if self.settings.show_temperature() {
+            if let Some(reading) = self.sensor.latest(millis) {
+                let mut text =
+                    temperature_text(reading.temperature, self.settings.temperature_unit());
+                if let Some(humidity) = reading.humidity {
+                    text += &format!(" {}%", libm::roundf(humidity) as i32);
+                }
+                let x = INDOOR_RIGHT + 1 - small_text_width(&text);
+                print_small_text(fb, &text, x, INDOOR_Y, INDOOR_COLOR);
+            }
+        }
+
+        if !self.time.is_known() {
+            print_centered(fb, UNKNOWN_TEXT, DAY_Y, DATE_COLOR);
+            return;
+        }
+
+        let today = self
+            .time
+            .now()
+            .with_timezone(&self.settings.time_zone())
+            .date_naive();
+        let weekday = WEEKDAYS[today.weekday().num_days_from_monday() as usize];
+        let month = MONTHS[today.month0() as usize];
+        print_centered(fb, weekday, WEEKDAY_Y, WEEKDAY_COLOR);
+        print_centered(fb, &format!("{} {}", today.day(), month), DAY_Y, DATE_COLOR);
+        print_centered(fb, &format!("{}", today.year()), YEAR_Y, DATE_COLOR);
+    }
+}
+
+/// Prints `text` horizontally centered, the font advances 8 pixels.
+fn print_centered<D>(fb: &mut D, text: &str, y: i32, color565: u16)
+where
+    D: DrawTarget<Color = Rgb888>,
+{
+    let x = (COLS as i32 - 8 * text.chars().count() as i32) / 2;
+    print_text(fb, text, x, y, color565);
+}
@@ -1,14 +1,14 @@
 use super::assets::{BLACK, BLOCK};
 use crate::{
-    display::{draw_rgb_bitmap, print_text},
-    engine::{millis, Direction, Event, Sprite}, // Added SpriteInfo
-    FBType,
+    display::{draw_rgb_bitmap_mapped, print_text},
+    engine::{Direction, Event, Sprite}, // Added SpriteInfo
+    time::TimeSource,
 };
-use alloc::format;
 use embassy_sync::{
     blocking_mutex::raw::CriticalSectionRawMutex,
     pubsub::{Publisher, Subscriber},
 };
+use embedded_graphics::{pixelcolor::Rgb888, prelude::DrawTarget};
 use heapless::String;
 
 // --- Constants ---
@@ -24,7 +24,7 @@ enum State {
 }
 
 // --- Block Struct ---
-pub(crate) struct Block {
+pub struct Block<T: TimeSource> {
     // Position and Dimensions
     x: i32,
     y: i32,
@@ -43,11 +43,14 @@ pub(crate) struct Block {
     // Event Handling (Pub/Sub)
     rx: Option<Subscriber<'static, CriticalSectionRawMutex, Event, 3, 4, 4>>,
     tx: Option<Publisher<'static, CriticalSectionRawMutex, Event, 3, 4, 4>>,
+
+    // Time
+    time: T, // Source of the animation timestamps
 }
 
-impl Block {
+impl<T: TimeSource> Block<T> {
     /// Creates a new Block instance at the given coordinates.
-    pub fn new(x: i32, y: i32) -> Self {
+    pub fn new(x: i32, y: i32, time: T) -> Self {
         Block {
             x,
             y,
@@ -60,6 +63,7 @@ impl Block {
             text: String::new(), // Initialize empty text
             rx: None,
             tx: None,
+            time,
         }
     }
 
@@ -84,12 +88,15 @@ impl Block {
         if self.state != State::Hit {
             self.state = State::Hit;
             self.direction = Direction::Up; // Start moving up
-            self.last_animation_millis = millis(); // Reset animation timer
+            self.last_animation_millis = self.time.millis(); // Reset animation timer
         }
     }
 
     /// Helper function to draw the text centered on the block.
-    fn draw_text_on_block(&self, fb: &mut FBType) {
+    fn draw_text_on_block<D>(&self, fb: &mut D)
+    where
+        D: DrawTarget<Color = Rgb888>,
+    {
         // Basic centering logic (adjust offsets as needed for the font)
         let text_x = if self.text.len() == 1 {
             self.x + 6 // Approx center for 1 char
@@ -102,9 +109,12 @@ impl Block {
 
     /// Updates the block's state, position, and draws it.
     /// Handles collision detection and animation.
-    /// `current_value` is the number to display (e.g., current hour or minute).
-    pub async fn update(&mut self, fb: &mut FBType, current_value: u32) {
-        let current_millis = millis();
+    /// `text` is what to display (e.g., the formatted hour or minute).
+    pub async fn update<D>(&mut self, fb: &mut D, text: &str)
+    where
+        D: DrawTarget<Color = Rgb888>,
+    {
+        let current_millis = self.time.millis();
         let next_x = self.x; // X position doesn't change in this logic
         let mut next_y = self.y;
 
@@ -126,9 +136,7 @@ impl Block {
         }
 
         // --- 2. Update Displayed Text ---
-        // Format the current value (e.g., hour/minute) with leading zero
-        let formatted_text = format!("{:02}", current_value);
-        self.set_text(formatted_text.as_str());
+        self.set_text(text);
 
         // --- 3. Update State and Position (Animation Logic) ---
         match self.state {
@@ -176,7 +184,15 @@ impl Block {
 
         // --- 5. Draw Current Frame ---
         // Draw the block sprite at the current position
-        draw_rgb_bitmap(fb, self.x, self.y, BLOCK, self.width, self.height);
+        draw_rgb_bitmap_mapped(
+            fb,
+            self.x,
+            self.y,
+            BLOCK,
+            self.width,
+            self.height,
+            super::without_mask,
+        );
         // Draw the text on top of the block
         self.draw_text_on_block(fb);
 
@@ -191,7 +207,7 @@ impl Block {
 }
 
 // --- Sprite Trait Implementation ---
-impl Sprite for Block {
+impl<T: TimeSource> Sprite for Block<T> {
     fn x(&self) -> i8 {
         self.x as i8
     }
@@ -810,3 +810,35 @@ pub const SUPER_MARIO_BROS_24PT: GFXfont = GFXfont {
     first: 0x20, // First ASCII character (space)
     last: 0x7E,  // Last ASCII character (tilde)
 };
+
+/// Width and height of the glyphs of [`small_glyph`], they advance one more.
+pub const SMALL_GLYPH_WIDTH: i32 = 3;
+pub const SMALL_GLYPH_HEIGHT: i32 = 5;
+
+/// Rows of a 3x5 pixel glyph for small text like the temperature, the
+/// leftmost pixel in bit 2. `None` for characters without a glyph.
+pub fn small_glyph(c: char) -> Option<[u8; 5]> {
+    let rows = match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b011, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '°' => [0b010, 0b101, 0b010, 0b000, 0b000],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'C' => [0b111, 0b100, 0b100, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        _ => return None,
+    };
+    Some(rows)
+}
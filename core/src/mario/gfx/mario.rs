@@ -1,12 +1,13 @@
 use crate::{
-    display::draw_rgb_bitmap,
-    engine::{millis, Direction, Event, Sprite},
-    FBType,
+    display::draw_rgb_bitmap_mapped,
+    engine::{Direction, Event, Sprite},
+    time::TimeSource,
 };
 use embassy_sync::{
     blocking_mutex::raw::CriticalSectionRawMutex,
     pubsub::{Publisher, Subscriber},
 };
+use embedded_graphics::{pixelcolor::Rgb888, prelude::DrawTarget};
 
 use super::assets::{MARIO_IDLE, MARIO_IDLE_SIZE, MARIO_JUMP, MARIO_JUMP_SIZE};
 
@@ -25,7 +26,7 @@ enum State {
 }
 
 // --- Mario Struct ---
-pub(crate) struct Mario {
+pub struct Mario<T: TimeSource> {
     // Position and Dimensions
     x: i32,
     y: i32,
@@ -42,11 +43,14 @@ pub(crate) struct Mario {
     // Event Handling (Pub/Sub)
     rx: Option<Subscriber<'static, CriticalSectionRawMutex, Event, 3, 4, 4>>,
     tx: Option<Publisher<'static, CriticalSectionRawMutex, Event, 3, 4, 4>>,
+
+    // Time
+    time: T, // Source of the animation timestamps
 }
 
-impl Mario {
+impl<T: TimeSource> Mario<T> {
     /// Creates a new Mario instance at the given coordinates.
-    pub fn new(x: i32, y: i32) -> Self {
+    pub fn new(x: i32, y: i32, time: T) -> Self {
         Mario {
             x,
             y,
@@ -59,6 +63,7 @@ impl Mario {
             jump_start_y: y, // Initialize jump_start_y
             rx: None,
             tx: None,
+            time,
         }
     }
 
@@ -74,23 +79,32 @@ impl Mario {
         }
     }
 
-    /// Initiates the jump sequence.
-    fn start_jump(&mut self) {
-        if self.state == State::Idle && (millis() - self.last_animation_millis > JUMP_DEBOUNCE_MS) {
+    /// Initiates the jump sequence. Returns whether Mario took off, he
+    /// doesn't while in the air or right after landing.
+    fn start_jump(&mut self) -> bool {
+        if self.state == State::Idle
+            && (self.time.millis() - self.last_animation_millis > JUMP_DEBOUNCE_MS)
+        {
             self.state = State::Jumping;
             self.width = MARIO_JUMP_SIZE[0] as i32;
             self.height = MARIO_JUMP_SIZE[1] as i32;
             self.sprite = MARIO_JUMP;
             self.direction = Direction::Up; // Start jumping upwards
             self.jump_start_y = self.y; // Record starting Y for height check
-            self.last_animation_millis = millis(); // Reset timer for debounce and animation
+            self.last_animation_millis = self.time.millis(); // Reset timer for debounce and animation
+            return true;
         }
+        false
     }
 
     /// Updates Mario's state, position, and draws him on the framebuffer.
     /// `trigger_jump` indicates if a jump should be initiated this frame.
-    pub async fn update(&mut self, fb: &mut FBType, trigger_jump: bool) {
-        let current_millis = millis();
+    /// Returns whether a jump started.
+    pub async fn update<D>(&mut self, fb: &mut D, trigger_jump: bool) -> bool
+    where
+        D: DrawTarget<Color = Rgb888>,
+    {
+        let current_millis = self.time.millis();
         let next_x = self.x;
         let mut next_y = self.y;
         let mut position_changed = false; // Track if position changes this frame
@@ -112,10 +126,8 @@ impl Mario {
         }
 
         // --- 2. Handle Jump Initiation ---
-        if trigger_jump {
-            self.start_jump();
-            // If jump started, dimensions might change, force redraw logic later
-        }
+        // If jump started, dimensions might change, force redraw logic later
+        let jumped = trigger_jump && self.start_jump();
 
         // --- 3. Update State and Position ---
         match self.state {
@@ -164,7 +176,15 @@ impl Mario {
 
         // --- 5. Draw Current Frame ---
         // Always draw Mario at his current position
-        draw_rgb_bitmap(fb, self.x, self.y, self.sprite, self.width, self.height);
+        draw_rgb_bitmap_mapped(
+            fb,
+            self.x,
+            self.y,
+            self.sprite,
+            self.width,
+            self.height,
+            super::without_mask,
+        );
 
         // --- 6. Publish Move Event if Position Changed ---
         if position_changed {
@@ -173,11 +193,12 @@ impl Mario {
                 tx.publish_immediate(Event::Move(info));
             }
         }
+        jumped
     }
 }
 
 // --- Sprite Trait Implementation ---
-impl Sprite for Mario {
+impl<T: TimeSource> Sprite for Mario<T> {
     fn x(&self) -> i8 {
         self.x as i8
     }
@@ -0,0 +1,17 @@
+pub mod assets;
+pub mod block;
+pub mod font;
+pub mod mario;
+
+use assets::_MASK;
+
+/// Leaves out the background of the sprites, painted in the day sky color,
+/// so they don't show as boxes on any other sky. For
+/// [`crate::display::draw_rgb_bitmap_mapped`].
+pub(crate) fn without_mask(color: u16) -> u16 {
+    if color == _MASK {
+        0
+    } else {
+        color
+    }
+}
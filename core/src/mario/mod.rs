@@ -0,0 +1,33 @@
+pub mod clockface;
+pub mod dateface;
+pub mod gfx;
+pub mod sky;
+pub mod weather;
+
+use alloc::{format, string::String};
+
+use crate::settings::{Scenery, TemperatureUnit};
+
+/// Drift of the clouds, pixels per minute.
+const CLOUD_SPEED: u32 = 20;
+/// Speed of the hills and of the bushes with the ground while the scenery
+/// scrolls, the nearer the faster.
+const HILL_SPEED: u32 = 60;
+const GROUND_SPEED: u32 = 120;
+
+/// Scroll speeds of the clouds, the hills and the bushes with the ground for
+/// `scenery`, pixels per minute.
+pub(crate) fn layer_speeds(scenery: Scenery) -> (u32, u32, u32) {
+    match scenery {
+        Scenery::Still => (0, 0, 0),
+        Scenery::Clouds => (CLOUD_SPEED, 0, 0),
+        Scenery::Scroll => (CLOUD_SPEED, HILL_SPEED, GROUND_SPEED),
+    }
+}
+
+/// The temperature of `celsius` degrees in whole degrees of `unit`, e.g.
+/// `-3°C`.
+pub(crate) fn temperature_text(celsius: f32, unit: TemperatureUnit) -> String {
+    let degrees = libm::roundf(unit.convert(celsius)) as i32;
+    format!("{}°{}", degrees, unit.symbol())
+}
@@ -0,0 +1,160 @@
+use chrono::{DateTime, Utc};
+use embedded_graphics::{pixelcolor::Rgb888, prelude::DrawTarget};
+
+use crate::{
+    display::fill_rect,
+    settings::Settings,
+    sun::{self, Location, SunPosition},
+    time::TimeSource,
+    COLS, ROWS,
+};
+
+use super::gfx::{assets::SKY_COLOR, without_mask};
+
+/// Elevation of the sun between day and night, the end of the civil
+/// twilight. Dawn and dusk last while the sun is within this many degrees of
+/// the horizon, so they span the sunrise and sunset of [`sun::events`]
+/// rather than start there.
+const TWILIGHT: f32 = 6.0;
+
+const DAWN_COLOR: u16 = 0xC20C;
+const DUSK_COLOR: u16 = 0x808E;
+const NIGHT_COLOR: u16 = 0x0004;
+const STAR_COLOR: u16 = 0xFFFF;
+const MOON_COLOR: u16 = 0xFFEC;
+
+/// Stars of the night sky, above the hill and clear of the blocks.
+const STARS: [(i32, i32); 10] = [
+    (3, 2),
+    (10, 14),
+    (5, 27),
+    (24, 3),
+    (41, 2),
+    (30, 30),
+    (47, 26),
+    (58, 22),
+    (20, 41),
+    (61, 37),
+];
+/// A star blinks off for one of this many periods.
+const TWINKLE_PERIODS: u64 = 11;
+const TWINKLE_MS: u64 = 400;
+
+/// Rows of the crescent moon in the top right corner, a bit per pixel from
+/// the left.
+const MOON: [u8; 6] = [
+    0b0011_1000,
+    0b0110_0000,
+    0b1100_0000,
+    0b1100_0000,
+    0b0110_0000,
+    0b0011_1000,
+];
+const MOON_X: i32 = 54;
+const MOON_Y: i32 = 1;
+
+/// Time of day the sky is painted for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkyPhase {
+    Dawn,
+    Day,
+    Dusk,
+    Night,
+}
+
+impl SkyPhase {
+    /// The phase at `now` at `location`, always [`SkyPhase::Day`] without a
+    /// location.
+    pub fn at(location: Option<Location>, now: DateTime<Utc>) -> Self {
+        location.map_or(SkyPhase::Day, |location| {
+            Self::from_position(sun::position(location, now))
+        })
+    }
+
+    /// The phase shown now at the location in `settings`, the day while the
+    /// time is unknown.
+    pub fn now(time: &impl TimeSource, settings: &Settings) -> Self {
+        if !time.is_known() {
+            return SkyPhase::Day;
+        }
+        Self::at(settings.location(), time.now())
+    }
+
+    pub fn from_position(position: SunPosition) -> Self {
+        match position.elevation {
+            elevation if elevation >= TWILIGHT => SkyPhase::Day,
+            elevation if elevation < -TWILIGHT => SkyPhase::Night,
+            _ if position.rising => SkyPhase::Dawn,
+            _ => SkyPhase::Dusk,
+        }
+    }
+
+    fn color(&self) -> u16 {
+        match self {
+            SkyPhase::Dawn => DAWN_COLOR,
+            SkyPhase::Day => SKY_COLOR,
+            SkyPhase::Dusk => DUSK_COLOR,
+            SkyPhase::Night => NIGHT_COLOR,
+        }
+    }
+
+    /// Brightness of the scenery, out of 256.
+    fn brightness(&self) -> u16 {
+        match self {
+            SkyPhase::Dawn => 192,
+            SkyPhase::Day => 256,
+            SkyPhase::Dusk => 160,
+            SkyPhase::Night => 96,
+        }
+    }
+
+    /// Tint of the clouds, hills, bushes and the ground, to be passed to
+    /// [`crate::engine::object::Object::draw_mapped`]. The background the
+    /// sprites paint in the day sky color is left out, so they can scroll
+    /// over each other.
+    pub fn tint(&self) -> impl Fn(u16) -> u16 {
+        let phase = *self;
+        move |color| match phase {
+            SkyPhase::Day => without_mask(color),
+            _ => darken(without_mask(color), phase.brightness()),
+        }
+    }
+
+    /// Paints the whole sky, with the stars and the moon at night. `millis`
+    /// lets the stars twinkle.
+    pub fn draw<D>(&self, fb: &mut D, millis: u64)
+    where
+        D: DrawTarget<Color = Rgb888>,
+    {
+        fill_rect(fb, 0, 0, ROWS as u32, COLS as u32, self.color());
+        if *self != SkyPhase::Night {
+            return;
+        }
+
+        let period = millis / TWINKLE_MS;
+        for (n, &(x, y)) in STARS.iter().enumerate() {
+            if (period + 3 * n as u64) % TWINKLE_PERIODS != 0 {
+                fill_rect(fb, x, y, 1, 1, STAR_COLOR);
+            }
+        }
+        for (row, bits) in MOON.iter().enumerate() {
+            for col in (0..8).filter(|col| bits & (0x80 >> col) != 0) {
+                fill_rect(fb, MOON_X + col, MOON_Y + row as i32, 1, 1, MOON_COLOR);
+            }
+        }
+    }
+}
+
+/// Scales the channels of an RGB565 `color` by `brightness` out of 256. A
+/// dark color that would round down to black, which the bitmaps skip, stays
+/// the darkest blue instead.
+fn darken(color: u16, brightness: u16) -> u16 {
+    if color == 0 {
+        return 0;
+    }
+    let scale = |value: u16| (value as u32 * brightness as u32 / 256) as u16;
+    let red = scale(color >> 11);
+    let green = scale((color >> 5) & 0x3F);
+    let blue = scale(color & 0x1F);
+    ((red << 11) | (green << 5) | blue).max(1)
+}
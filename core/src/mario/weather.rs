@@ -0,0 +1,178 @@
+use embedded_graphics::{pixelcolor::Rgb888, prelude::DrawTarget};
+
+use crate::{display::fill_rect, weather::WeatherCondition, COLS, ROWS};
+
+use super::sky::SkyPhase;
+
+/// Most raindrops or snowflakes on the display. Every particle is a few
+/// pixels, so a frame stays well within the refresh of the panel on core 1.
+pub const MAX_PARTICLES: usize = 48;
+/// Fastest fall of a particle, pixels per second. Faster drops would jump
+/// several pixels between two frames.
+pub const MAX_SPEED: u32 = 48;
+
+/// Particles falling over the whole display.
+struct Shower {
+    count: usize,
+    /// Fall of the slowest particles, pixels per second. Some fall up to
+    /// half as fast again.
+    speed: u32,
+    /// Sideways drift, pixels per second, negative to the left.
+    drift: i32,
+    /// Pixels of a particle, a streak for rain.
+    length: i32,
+    /// Whether the particles sway from side to side.
+    sway: bool,
+    color: u16,
+}
+
+const RAIN: Shower = Shower {
+    count: 40,
+    speed: 32,
+    drift: -16,
+    length: 3,
+    sway: false,
+    color: 0x7D7F,
+};
+
+const SNOW: Shower = Shower {
+    count: 32,
+    speed: 6,
+    drift: 1,
+    length: 1,
+    sway: true,
+    color: 0xFFFF,
+};
+/// A swaying snowflake moves a pixel to the side and back within this
+/// period.
+const SWAY_MS: u64 = 1600;
+
+/// Rows, height and drift to the right in pixels per second of the banks of
+/// fog, in front of the hills and the ground.
+const FOG_BANKS: [(i32, i32, i32); 2] = [(30, 5, 2), (46, 6, 4)];
+const FOG_COLOR: u16 = 0xBDF7;
+
+/// Center and radius of the sun in the top right corner, clear of the blocks.
+const SUN_X: i32 = 57;
+const SUN_Y: i32 = 6;
+const SUN_RADIUS: i32 = 3;
+const SUN_COLOR: u16 = 0xFFE0;
+const RAY_COLOR: u16 = 0xFD20;
+/// The straight and the diagonal rays take turns being long.
+const RAY_MS: u64 = 500;
+const RAYS: [(i32, i32); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
+/// Draws the weather of `condition` at `millis` in front of the scenery,
+/// tinted for `sky` like it. Mario, the blocks and any text are drawn on top
+/// so they stay readable.
+pub fn draw<D>(condition: WeatherCondition, sky: SkyPhase, fb: &mut D, millis: u64)
+where
+    D: DrawTarget<Color = Rgb888>,
+{
+    let tint = sky.tint();
+    match condition {
+        // The sun doesn't shine at night
+        WeatherCondition::Clear if sky != SkyPhase::Night => draw_sun(fb, millis, &tint),
+        WeatherCondition::Clear | WeatherCondition::Cloudy => {}
+        WeatherCondition::Rain => draw_shower(&RAIN, fb, millis, &tint),
+        WeatherCondition::Snow => draw_shower(&SNOW, fb, millis, &tint),
+        WeatherCondition::Fog => draw_fog(fb, millis, &tint),
+    }
+}
+
+/// A fixed pseudo random value per particle, its start and speed.
+fn seed(n: usize) -> u32 {
+    let mut hash = (n as u32 + 1).wrapping_mul(0x9E37_79B9);
+    hash = (hash ^ (hash >> 15)).wrapping_mul(0x85EB_CA6B);
+    hash ^ (hash >> 13)
+}
+
+/// `pixels_per_second` times the seconds of `millis`, wrapped into `0..size`.
+fn travel(start: i64, pixels_per_second: i64, millis: u64, size: usize) -> i32 {
+    (start + pixels_per_second * millis as i64 / 1000).rem_euclid(size as i64) as i32
+}
+
+fn draw_shower<D>(shower: &Shower, fb: &mut D, millis: u64, tint: &impl Fn(u16) -> u16)
+where
+    D: DrawTarget<Color = Rgb888>,
+{
+    let color = tint(shower.color);
+    for n in 0..shower.count.min(MAX_PARTICLES) {
+        let seed = seed(n);
+        // Every particle falls at its own speed, so they don't move in rows
+        let speed = (shower.speed + shower.speed * ((seed >> 16) & 0x3) / 6).min(MAX_SPEED);
+        let y = travel(((seed >> 8) & 0xFF) as i64, speed as i64, millis, ROWS);
+        let drift = shower.drift as i64 * speed as i64 / shower.speed as i64;
+        let mut x = travel((seed & 0xFF) as i64, drift, millis, COLS);
+        if shower.sway && (millis + seed as u64 % SWAY_MS) % SWAY_MS >= SWAY_MS / 2 {
+            x += 1;
+        }
+        // A streak trails the particle against its direction
+        for trail in 0..shower.length {
+            let dx = -(drift as i32) * trail / speed as i32;
+            let x = (x + dx).rem_euclid(COLS as i32);
+            fill_rect(fb, x, y - trail, 1, 1, color);
+        }
+    }
+}
+
+fn draw_fog<D>(fb: &mut D, millis: u64, tint: &impl Fn(u16) -> u16)
+where
+    D: DrawTarget<Color = Rgb888>,
+{
+    let color = tint(FOG_COLOR);
+    for (top, height, drift) in FOG_BANKS {
+        let offset = travel(0, drift as i64, millis, COLS);
+        for row in 0..height {
+            // Thinner at the edges of the bank
+            let spacing = if row == 0 || row == height - 1 { 4 } else { 2 };
+            let y = top + row;
+            for x in 0..COLS as i32 {
+                if (x - offset + row).rem_euclid(spacing) == 0 {
+                    fill_rect(fb, x, y, 1, 1, color);
+                }
+            }
+        }
+    }
+}
+
+fn draw_sun<D>(fb: &mut D, millis: u64, tint: &impl Fn(u16) -> u16)
+where
+    D: DrawTarget<Color = Rgb888>,
+{
+    let color = tint(SUN_COLOR);
+    for dy in -SUN_RADIUS..=SUN_RADIUS {
+        for dx in -SUN_RADIUS..=SUN_RADIUS {
+            if dx * dx + dy * dy <= SUN_RADIUS * SUN_RADIUS + 1 {
+                fill_rect(fb, SUN_X + dx, SUN_Y + dy, 1, 1, color);
+            }
+        }
+    }
+
+    let color = tint(RAY_COLOR);
+    let long = (millis / RAY_MS % 2) as usize;
+    for (n, (dx, dy)) in RAYS.into_iter().enumerate() {
+        // The diagonal rays start closer, they are longer per step
+        let start = if dx != 0 && dy != 0 { 4 } else { 5 };
+        let length = if n % 2 == long { 2 } else { 1 };
+        for distance in start..start + length {
+            fill_rect(
+                fb,
+                SUN_X + dx * distance,
+                SUN_Y + dy * distance,
+                1,
+                1,
+                color,
+            );
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+
+use crate::clock::two_digit_year;
+
+/// Longest NMEA 0183 sentence including `$` and the checksum.
+pub const MAX_SENTENCE_LEN: usize = 82;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NmeaError {
+    /// The checksum is missing or doesn't match.
+    Checksum,
+    /// A field is missing or out of range.
+    Malformed,
+    /// The receiver has no fix, the time isn't valid yet.
+    NoFix,
+    /// Neither an RMC nor a ZDA sentence.
+    Unsupported,
+}
+
+/// Parses the UTC date and time of an RMC or a ZDA sentence from any talker,
+/// e.g. `$GPRMC` or `$GNZDA`. Trailing line endings are ignored.
+pub fn parse_time(sentence: &str) -> Result<DateTime<Utc>, NmeaError> {
+    let body = checked_body(sentence.trim_end())?;
+    let mut fields = body.split(',');
+    let kind = fields.next().ok_or(NmeaError::Malformed)?;
+    // Two talker characters, e.g. GP, GN, GL, then the sentence type
+    match kind.get(2..) {
+        Some("RMC") => parse_rmc(fields),
+        Some("ZDA") => parse_zda(fields),
+        _ => Err(NmeaError::Unsupported),
+    }
+}
+
+/// `hhmmss.ss,A,lat,N,lon,E,speed,course,ddmmyy,...`
+fn parse_rmc<'a>(mut fields: impl Iterator<Item = &'a str>) -> Result<DateTime<Utc>, NmeaError> {
+    let time = fields.next().ok_or(NmeaError::Malformed)?;
+    if fields.next() != Some("A") {
+        return Err(NmeaError::NoFix);
+    }
+    let date = fields.nth(6).ok_or(NmeaError::Malformed)?;
+    if date.len() != 6 || !date.is_ascii() {
+        return Err(NmeaError::Malformed);
+    }
+    let day = number(&date[0..2])?;
+    let month = number(&date[2..4])?;
+    let year = two_digit_year(number(&date[4..6])? as u8).ok_or(NmeaError::Malformed)?;
+    datetime(year, month, day, time)
+}
+
+/// `hhmmss.ss,dd,mm,yyyy,zone hours,zone minutes`
+fn parse_zda<'a>(mut fields: impl Iterator<Item = &'a str>) -> Result<DateTime<Utc>, NmeaError> {
+    let time = fields.next().ok_or(NmeaError::Malformed)?;
+    let mut next = || fields.next().ok_or(NmeaError::Malformed);
+    let (day, month, year) = (next()?, next()?, next()?);
+    // Receivers without a fix send empty fields
+    if time.is_empty() || year.is_empty() {
+        return Err(NmeaError::NoFix);
+    }
+    datetime(number(year)? as i32, number(month)?, number(day)?, time)
+}
+
+fn datetime(year: i32, month: u32, day: u32, time: &str) -> Result<DateTime<Utc>, NmeaError> {
+    let date = NaiveDate::from_ymd_opt(year, month, day).ok_or(NmeaError::Malformed)?;
+    Ok(date.and_time(parse_hhmmss(time)?).and_utc())
+}
+
+/// `hhmmss` with optional decimal places.
+fn parse_hhmmss(time: &str) -> Result<NaiveTime, NmeaError> {
+    let (whole, fraction) = time.split_once('.').unwrap_or((time, ""));
+    if whole.len() != 6 || !whole.is_ascii() || fraction.len() > 6 {
+        return Err(NmeaError::Malformed);
+    }
+    let micros = if fraction.is_empty() {
+        0
+    } else {
+        number(fraction)? * 10u32.pow(6 - fraction.len() as u32)
+    };
+    NaiveTime::from_hms_micro_opt(
+        number(&whole[0..2])?,
+        number(&whole[2..4])?,
+        number(&whole[4..6])?,
+        micros,
+    )
+    .ok_or(NmeaError::Malformed)
+}
+
+fn number(digits: &str) -> Result<u32, NmeaError> {
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(NmeaError::Malformed);
+    }
+    digits.parse().map_err(|_| NmeaError::Malformed)
+}
+
+/// Returns the part between `$` and `*` if the checksum, the XOR of its
+/// bytes, matches.
+fn checked_body(sentence: &str) -> Result<&str, NmeaError> {
+    let sentence = sentence.strip_prefix('$').ok_or(NmeaError::Malformed)?;
+    let (body, checksum) = sentence.rsplit_once('*').ok_or(NmeaError::Checksum)?;
+    if checksum.len() != 2 || !checksum.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(NmeaError::Checksum);
+    }
+    let expected = u8::from_str_radix(checksum, 16).map_err(|_| NmeaError::Checksum)?;
+    let actual = body.bytes().fold(0, |sum, b| sum ^ b);
+    if actual != expected {
+        return Err(NmeaError::Checksum);
+    }
+    Ok(body)
+}
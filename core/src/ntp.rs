@@ -0,0 +1,325 @@
+use core::{
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::{
+    clock::{ntp_fraction_to_micros, SyncStatus},
+    time::TimeSource,
+};
+
+pub const NTP_PORT: u16 = 123;
+/// Size of an NTP packet without extensions.
+pub const PACKET_SIZE: usize = 48;
+
+/// Seconds between the NTP epoch (1900) and the unix epoch (1970).
+const NTP_UNIX_DELTA: i64 = 2_208_988_800;
+/// Highest stratum of a synchronised server, 16 means unsynchronised.
+const MAX_STRATUM: u8 = 15;
+/// Stratum sent while the time is unknown.
+const UNSYNCHRONIZED_STRATUM: u8 = 16;
+/// Precision of the served timestamps as a power of two seconds, the clock
+/// counts microseconds.
+const PRECISION: i8 = -20;
+
+const LEAP_NONE: u8 = 0;
+const LEAP_ALARM: u8 = 3;
+const VERSION: u8 = 4;
+const MODE_CLIENT: u8 = 3;
+const MODE_SERVER: u8 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NtpError {
+    /// No servers are configured.
+    NoServers,
+    /// The server name couldn't be resolved.
+    Dns,
+    /// Sending the request or receiving the reply failed.
+    Network,
+    /// The server didn't reply in time.
+    Timeout,
+    /// The reply is malformed or doesn't answer our request.
+    InvalidReply,
+    /// The server asked us to back off or stop querying it, see RFC 5905 7.4.
+    KissOfDeath([u8; 4]),
+    /// The server isn't synchronised itself.
+    Unsynchronized,
+    /// The reply holds a zero timestamp.
+    ZeroTimestamp,
+}
+
+/// Result of a successful NTP query.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NtpReply {
+    pub server: SocketAddr,
+    /// Time to add to the local clock to match the server.
+    pub offset: TimeDelta,
+    /// Network delay of the request and the reply, without the time the
+    /// server took to answer.
+    pub round_trip: TimeDelta,
+    pub stratum: u8,
+    /// When the server sent the reply.
+    pub server_time: DateTime<Utc>,
+}
+
+/// Which servers to ask and how persistently.
+#[derive(Debug, Clone, Copy)]
+pub struct NtpConfig<'a> {
+    /// Host names or IPv4 addresses, optionally with a `:port`.
+    pub servers: &'a [&'a str],
+    /// How long to wait for each reply.
+    pub timeout: Duration,
+    /// How often to go through the server list before giving up.
+    pub rounds: u8,
+    /// Wait after the first round, doubled after every following round.
+    pub backoff: Duration,
+}
+
+impl<'a> NtpConfig<'a> {
+    pub const fn new(servers: &'a [&'a str]) -> Self {
+        NtpConfig {
+            servers,
+            timeout: Duration::from_secs(2),
+            rounds: 3,
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Network access of the NTP client, implemented on top of the network
+/// stack of the device and of the host in tests.
+pub trait NtpTransport {
+    /// Resolves a host name or an IP address literal.
+    fn resolve(&mut self, host: &str) -> impl Future<Output = Result<IpAddr, NtpError>>;
+
+    /// Sends `request` to `server` and waits up to `timeout` for a datagram
+    /// from it, returns the length of the reply.
+    fn exchange(
+        &mut self,
+        server: SocketAddr,
+        request: &[u8],
+        reply: &mut [u8],
+        timeout: Duration,
+    ) -> impl Future<Output = Result<usize, NtpError>>;
+
+    fn delay(&mut self, duration: Duration) -> impl Future<Output = ()>;
+}
+
+/// Asks the configured servers in turn until one gives a sane reply. After
+/// each round through the list the client backs off before trying again.
+/// Returns the error of the last query if all of them failed.
+pub async fn sync<T: NtpTransport>(
+    config: &NtpConfig<'_>,
+    transport: &mut T,
+    time: &impl TimeSource,
+) -> Result<NtpReply, NtpError> {
+    let mut error = NtpError::NoServers;
+    for round in 0..config.rounds {
+        if round > 0 && !config.servers.is_empty() {
+            let backoff = config.backoff.saturating_mul(1 << (round - 1).min(16));
+            transport.delay(backoff).await;
+        }
+        for server in config.servers {
+            match query(transport, time, server, config.timeout).await {
+                Ok(reply) => return Ok(reply),
+                Err(e) => error = e,
+            }
+        }
+    }
+    Err(error)
+}
+
+/// Sends a single request to `server`, see [`NtpConfig::servers`] for the format.
+pub async fn query<T: NtpTransport>(
+    transport: &mut T,
+    time: &impl TimeSource,
+    server: &str,
+    timeout: Duration,
+) -> Result<NtpReply, NtpError> {
+    let (host, port) = split_port(server);
+    let server = SocketAddr::new(transport.resolve(host).await?, port);
+
+    let originate = time.now();
+    let request = request(originate);
+    let mut reply = [0u8; PACKET_SIZE];
+    let len = transport
+        .exchange(server, &request, &mut reply, timeout)
+        .await?;
+    let destination = time.now();
+
+    parse_reply(server, &request, &reply[..len], destination)
+}
+
+/// Builds a client request sent at `transmit`.
+pub fn request(transmit: DateTime<Utc>) -> [u8; PACKET_SIZE] {
+    let mut packet = [0u8; PACKET_SIZE];
+    packet[0] = (VERSION << 3) | MODE_CLIENT;
+    packet[40..48].copy_from_slice(&to_ntp_timestamp(transmit).to_be_bytes());
+    packet
+}
+
+/// Checks a reply of `server` to `request` received at `destination` and
+/// computes the offset and round trip from its timestamps, see RFC 5905 8.
+pub fn parse_reply(
+    server: SocketAddr,
+    request: &[u8; PACKET_SIZE],
+    reply: &[u8],
+    destination: DateTime<Utc>,
+) -> Result<NtpReply, NtpError> {
+    let reply: &[u8; PACKET_SIZE] = reply
+        .get(..PACKET_SIZE)
+        .and_then(|reply| reply.try_into().ok())
+        .ok_or(NtpError::InvalidReply)?;
+
+    let leap = reply[0] >> 6;
+    let version = (reply[0] >> 3) & 0x07;
+    let mode = reply[0] & 0x07;
+    let stratum = reply[1];
+    if mode != MODE_SERVER || !(1..=VERSION).contains(&version) {
+        return Err(NtpError::InvalidReply);
+    }
+    // Our transmit timestamp has to come back as the originate timestamp,
+    // otherwise this is a stale or spoofed reply
+    if reply[24..32] != request[40..48] {
+        return Err(NtpError::InvalidReply);
+    }
+    if stratum == 0 {
+        let mut code = [0u8; 4];
+        code.copy_from_slice(&reply[12..16]);
+        return Err(NtpError::KissOfDeath(code));
+    }
+    if leap == LEAP_ALARM || stratum > MAX_STRATUM {
+        return Err(NtpError::Unsynchronized);
+    }
+
+    let timestamp = |range: core::ops::Range<usize>| {
+        let raw = u64::from_be_bytes(reply[range].try_into().unwrap());
+        match raw {
+            0 => Err(NtpError::ZeroTimestamp),
+            raw => from_ntp_timestamp(raw).ok_or(NtpError::InvalidReply),
+        }
+    };
+    let originate = timestamp(24..32)?;
+    let receive = timestamp(32..40)?;
+    let transmit = timestamp(40..48)?;
+
+    let offset = ((receive - originate) + (transmit - destination)) / 2;
+    let round_trip = (destination - originate) - (transmit - receive);
+
+    Ok(NtpReply {
+        server,
+        offset,
+        round_trip,
+        stratum,
+        server_time: transmit,
+    })
+}
+
+/// What an NTP server tells its clients about its own synchronisation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ServerStatus {
+    /// Stratum of the clock, 16 while the time is unknown.
+    pub stratum: u8,
+    pub reference_id: [u8; 4],
+    /// When the clock was last set, `None` if it never was.
+    pub reference_time: Option<DateTime<Utc>>,
+    /// How far the clock may be off, sent as the root dispersion.
+    pub dispersion: TimeDelta,
+}
+
+impl ServerStatus {
+    /// Status of a clock last set as described by `sync`, grown by the drift
+    /// since until `uptime_micros`.
+    pub fn new(sync: Option<&SyncStatus>, uptime_micros: u64) -> Self {
+        match sync {
+            Some(sync) => ServerStatus {
+                stratum: sync.sample.stratum,
+                reference_id: sync.sample.reference_id,
+                reference_time: Some(sync.sample.time),
+                dispersion: sync.sample.uncertainty_at(uptime_micros),
+            },
+            None => ServerStatus {
+                stratum: UNSYNCHRONIZED_STRATUM,
+                reference_id: [0; 4],
+                reference_time: None,
+                dispersion: TimeDelta::MAX,
+            },
+        }
+    }
+}
+
+/// Answers a client `request` received at `receive`, with the reply sent at
+/// `transmit`, see RFC 4330 5. Returns `None` for anything but a client
+/// request, e.g. replies and broadcasts, which aren't answered.
+pub fn serve(
+    request: &[u8],
+    status: &ServerStatus,
+    receive: DateTime<Utc>,
+    transmit: DateTime<Utc>,
+) -> Option<[u8; PACKET_SIZE]> {
+    let request = request.get(..PACKET_SIZE)?;
+    let version = (request[0] >> 3) & 0x07;
+    let mode = request[0] & 0x07;
+    if mode != MODE_CLIENT || !(1..=VERSION).contains(&version) {
+        return None;
+    }
+
+    let synchronized = status.stratum <= MAX_STRATUM && status.reference_time.is_some();
+    let leap = if synchronized { LEAP_NONE } else { LEAP_ALARM };
+    let mut reply = [0u8; PACKET_SIZE];
+    // Answer in the version of the request, as RFC 4330 asks
+    reply[0] = (leap << 6) | (version << 3) | MODE_SERVER;
+    reply[1] = status.stratum;
+    reply[2] = request[2];
+    reply[3] = PRECISION as u8;
+    // Root delay stays zero, the whole uncertainty goes into the dispersion
+    reply[8..12].copy_from_slice(&to_ntp_short(status.dispersion).to_be_bytes());
+    reply[12..16].copy_from_slice(&status.reference_id);
+    if let Some(reference_time) = status.reference_time {
+        reply[16..24].copy_from_slice(&to_ntp_timestamp(reference_time).to_be_bytes());
+    }
+    reply[24..32].copy_from_slice(&request[40..48]);
+    reply[32..40].copy_from_slice(&to_ntp_timestamp(receive).to_be_bytes());
+    reply[40..48].copy_from_slice(&to_ntp_timestamp(transmit).to_be_bytes());
+    Some(reply)
+}
+
+/// Converts to the 32 bit NTP short format, 16 bits of seconds and 16 of
+/// fraction. Saturates at about 18 hours.
+fn to_ntp_short(delta: TimeDelta) -> u32 {
+    let micros = delta.num_microseconds().unwrap_or(i64::MAX).max(0) as u64;
+    u32::try_from((micros << 16) / 1_000_000).unwrap_or(u32::MAX)
+}
+
+/// Converts to a 64 bit NTP timestamp, seconds since 1900 in the upper and the
+/// fraction in the lower half. The seconds wrap in 2036 as in the protocol.
+pub fn to_ntp_timestamp(time: DateTime<Utc>) -> u64 {
+    let seconds = (time.timestamp() + NTP_UNIX_DELTA) as u32;
+    // Rounded up, so converting back gives the same microseconds
+    let fraction = ((time.timestamp_subsec_micros() as u64) << 32).div_ceil(1_000_000);
+    ((seconds as u64) << 32) | fraction
+}
+
+/// Converts a 64 bit NTP timestamp. Seconds below 2^31 are taken to be in the
+/// era starting 2036, so this works from 1968 to 2104.
+pub fn from_ntp_timestamp(timestamp: u64) -> Option<DateTime<Utc>> {
+    let mut seconds = (timestamp >> 32) as i64;
+    if seconds < 1 << 31 {
+        seconds += 1 << 32;
+    }
+    let micros = ntp_fraction_to_micros(timestamp as u32);
+    DateTime::from_timestamp(seconds - NTP_UNIX_DELTA, micros * 1_000)
+}
+
+fn split_port(server: &str) -> (&str, u16) {
+    match server.rsplit_once(':') {
+        Some((host, port)) => match port.parse() {
+            Ok(port) => (host, port),
+            Err(_) => (server, NTP_PORT),
+        },
+        None => (server, NTP_PORT),
+    }
+}
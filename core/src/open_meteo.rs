@@ -0,0 +1,89 @@
+use core::time::Duration;
+
+use alloc::format;
+
+use crate::{
+    http::{self, HttpTransport},
+    json::Value,
+    sun::Location,
+    weather::{Conditions, WeatherCondition, WeatherError, WeatherProvider},
+};
+
+/// The forecast API of Open-Meteo, free without a key. Plain HTTP, the
+/// client has no TLS.
+pub const OPEN_METEO_URL: &str = "http://api.open-meteo.com/v1/forecast";
+/// Room for the headers and the body of a response, which is about 500
+/// bytes.
+const RESPONSE_SIZE: usize = 1536;
+
+/// Current conditions from the Open-Meteo forecast API, or any server
+/// answering in its format.
+pub struct OpenMeteo<'a, T: HttpTransport> {
+    /// The forecast endpoint, [`OPEN_METEO_URL`] or e.g. a local stand-in.
+    pub url: &'a str,
+    pub transport: T,
+    /// How long to wait for the whole response.
+    pub timeout: Duration,
+}
+
+impl<'a, T: HttpTransport> OpenMeteo<'a, T> {
+    pub fn new(transport: T) -> Self {
+        OpenMeteo {
+            url: OPEN_METEO_URL,
+            transport,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl<T: HttpTransport> WeatherProvider for OpenMeteo<'_, T> {
+    fn name(&self) -> &'static str {
+        "open-meteo"
+    }
+
+    async fn fetch(&mut self, location: Location) -> Result<Conditions, WeatherError> {
+        let url = format!(
+            "{}?latitude={:.2}&longitude={:.2}&current=temperature_2m,weather_code",
+            self.url, location.latitude, location.longitude
+        );
+        let mut buffer = [0u8; RESPONSE_SIZE];
+        let body = http::get(&mut self.transport, &url, &mut buffer, self.timeout).await?;
+        parse(body)
+    }
+}
+
+/// Reads the current conditions of a forecast response, e.g.
+/// `{"current":{"temperature_2m":3.4,"weather_code":61}}`.
+pub fn parse(body: &[u8]) -> Result<Conditions, WeatherError> {
+    let json = core::str::from_utf8(body).map_err(|_| WeatherError::InvalidData)?;
+    let current = Value::parse(json)
+        .and_then(|json| json.get("current"))
+        .ok_or(WeatherError::InvalidData)?;
+    let temperature = current
+        .get("temperature_2m")
+        .and_then(|value| value.as_f32())
+        .ok_or(WeatherError::InvalidData)?;
+    let condition = current
+        .get("weather_code")
+        .and_then(|value| value.as_f32())
+        .and_then(|code| condition(code as u8))
+        .ok_or(WeatherError::InvalidData)?;
+    Ok(Conditions {
+        condition,
+        temperature,
+    })
+}
+
+/// The condition of a WMO weather interpretation code, `None` for unknown
+/// codes.
+pub fn condition(code: u8) -> Option<WeatherCondition> {
+    match code {
+        0 | 1 => Some(WeatherCondition::Clear),
+        2 | 3 => Some(WeatherCondition::Cloudy),
+        45 | 48 => Some(WeatherCondition::Fog),
+        // Drizzle, rain, showers and thunderstorms
+        51..=67 | 80..=82 | 95..=99 => Some(WeatherCondition::Rain),
+        71..=77 | 85 | 86 => Some(WeatherCondition::Snow),
+        _ => None,
+    }
+}
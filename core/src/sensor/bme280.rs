@@ -0,0 +1,204 @@
+use core::time::Duration;
+
+use embedded_hal::i2c::I2c;
+
+use super::{Reading, Sensor, SensorError};
+
+/// The address with SDO low and high.
+pub const BME280_ADDRESSES: [u8; 2] = [0x76, 0x77];
+
+const REG_CHIP_ID: u8 = 0xD0;
+const CHIP_ID: u8 = 0x60;
+/// Temperature and pressure calibration, 0x88 to 0x9F, then H1 at 0xA1.
+const REG_CALIB_TP: u8 = 0x88;
+const REG_CALIB_H1: u8 = 0xA1;
+/// Humidity calibration H2 to H6, 0xE1 to 0xE7.
+const REG_CALIB_H: u8 = 0xE1;
+const REG_CTRL_HUM: u8 = 0xF2;
+const REG_CTRL_MEAS: u8 = 0xF4;
+/// Pressure, temperature and humidity, 0xF7 to 0xFE.
+const REG_DATA: u8 = 0xF7;
+/// Humidity oversampling x1, only applied by the next write of ctrl_meas.
+const CTRL_HUM: u8 = 0x01;
+/// Temperature and pressure oversampling x1 (0b001 each), forced mode (0b01):
+/// one measurement, then back to sleep.
+const CTRL_MEAS_FORCED: u8 = 0b0010_0101;
+/// 9.3 ms with every oversampling at x1.
+const MEASUREMENT_TIME: Duration = Duration::from_millis(10);
+
+/// Compensation parameters stored in the chip, named as in the datasheet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Calibration {
+    pub t1: u16,
+    pub t2: i16,
+    pub t3: i16,
+    pub p1: u16,
+    pub p2: i16,
+    pub p3: i16,
+    pub p4: i16,
+    pub p5: i16,
+    pub p6: i16,
+    pub p7: i16,
+    pub p8: i16,
+    pub p9: i16,
+    pub h1: u8,
+    pub h2: i16,
+    pub h3: u8,
+    pub h4: i16,
+    pub h5: i16,
+    pub h6: i8,
+}
+
+impl Calibration {
+    /// Decodes the registers 0x88 to 0x9F, 0xA1 and 0xE1 to 0xE7.
+    pub fn from_registers(tp: &[u8; 24], h1: u8, h: &[u8; 7]) -> Self {
+        let u16_at = |i: usize| u16::from_le_bytes([tp[i], tp[i + 1]]);
+        let i16_at = |i: usize| i16::from_le_bytes([tp[i], tp[i + 1]]);
+        Calibration {
+            t1: u16_at(0),
+            t2: i16_at(2),
+            t3: i16_at(4),
+            p1: u16_at(6),
+            p2: i16_at(8),
+            p3: i16_at(10),
+            p4: i16_at(12),
+            p5: i16_at(14),
+            p6: i16_at(16),
+            p7: i16_at(18),
+            p8: i16_at(20),
+            p9: i16_at(22),
+            h1,
+            h2: i16::from_le_bytes([h[0], h[1]]),
+            h3: h[2],
+            // 12 bit values sharing the nibbles of 0xE5
+            h4: ((h[3] as i8 as i16) << 4) | (h[4] & 0x0F) as i16,
+            h5: ((h[5] as i8 as i16) << 4) | (h[4] >> 4) as i16,
+            h6: h[6] as i8,
+        }
+    }
+
+    /// Degrees Celsius of the raw temperature, with the fine temperature
+    /// the pressure and the humidity are compensated with.
+    pub fn temperature(&self, adc_t: i32) -> (f32, i32) {
+        let (t1, t2, t3) = (self.t1 as i32, self.t2 as i32, self.t3 as i32);
+        let var1 = (((adc_t >> 3) - (t1 << 1)) * t2) >> 11;
+        let var2 = (((((adc_t >> 4) - t1) * ((adc_t >> 4) - t1)) >> 12) * t3) >> 14;
+        let t_fine = var1 + var2;
+        let centi_degrees = (t_fine * 5 + 128) >> 8;
+        (centi_degrees as f32 / 100.0, t_fine)
+    }
+
+    /// Hectopascal of the raw pressure, `None` before the chip is
+    /// calibrated.
+    pub fn pressure(&self, adc_p: i32, t_fine: i32) -> Option<f32> {
+        let mut var1 = t_fine as i64 - 128000;
+        let mut var2 = var1 * var1 * self.p6 as i64;
+        var2 += (var1 * self.p5 as i64) << 17;
+        var2 += (self.p4 as i64) << 35;
+        var1 = ((var1 * var1 * self.p3 as i64) >> 8) + ((var1 * self.p2 as i64) << 12);
+        var1 = (((1i64 << 47) + var1) * self.p1 as i64) >> 33;
+        if var1 == 0 {
+            return None;
+        }
+        let mut p = 1048576 - adc_p as i64;
+        p = (((p << 31) - var2) * 3125) / var1;
+        var1 = (self.p9 as i64 * (p >> 13) * (p >> 13)) >> 25;
+        var2 = (self.p8 as i64 * p) >> 19;
+        p = ((p + var1 + var2) >> 8) + ((self.p7 as i64) << 4);
+        // Pascal in 24.8 fixed point
+        Some(p as f32 / 256.0 / 100.0)
+    }
+
+    /// Relative humidity in percent of the raw humidity.
+    pub fn humidity(&self, adc_h: i32, t_fine: i32) -> f32 {
+        let (h1, h2, h3) = (self.h1 as i32, self.h2 as i32, self.h3 as i32);
+        let (h4, h5, h6) = (self.h4 as i32, self.h5 as i32, self.h6 as i32);
+        let mut v = t_fine - 76800;
+        v = (((adc_h << 14) - (h4 << 20) - (h5 * v)) + 16384) >> 15;
+        v *= (((((((t_fine - 76800) * h6) >> 10) * ((((t_fine - 76800) * h3) >> 11) + 32768))
+            >> 10)
+            + 2097152)
+            * h2
+            + 8192)
+            >> 14;
+        v -= ((((v >> 15) * (v >> 15)) >> 7) * h1) >> 4;
+        let v = v.clamp(0, 419430400);
+        // Percent in 22.10 fixed point
+        (v >> 12) as f32 / 1024.0
+    }
+}
+
+/// Bosch BME280 temperature, humidity and pressure sensor.
+pub struct Bme280<I2C: I2c> {
+    i2c: I2C,
+    address: u8,
+    calibration: Calibration,
+}
+
+impl<I2C: I2c> Bme280<I2C> {
+    pub fn new(i2c: I2C, address: u8) -> Self {
+        Bme280 {
+            i2c,
+            address,
+            calibration: Calibration::default(),
+        }
+    }
+
+    /// Gives the bus back.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    /// Checks the chip id and reads the calibration, needed before the
+    /// first measurement.
+    pub fn init(&mut self) -> Result<(), SensorError> {
+        let mut id = [0u8];
+        self.i2c.write_read(self.address, &[REG_CHIP_ID], &mut id)?;
+        if id[0] != CHIP_ID {
+            return Err(SensorError::UnknownChip(id[0]));
+        }
+        let mut tp = [0u8; 24];
+        let mut h1 = [0u8];
+        let mut h = [0u8; 7];
+        self.i2c
+            .write_read(self.address, &[REG_CALIB_TP], &mut tp)?;
+        self.i2c
+            .write_read(self.address, &[REG_CALIB_H1], &mut h1)?;
+        self.i2c.write_read(self.address, &[REG_CALIB_H], &mut h)?;
+        self.calibration = Calibration::from_registers(&tp, h1[0], &h);
+        self.i2c.write(self.address, &[REG_CTRL_HUM, CTRL_HUM])?;
+        Ok(())
+    }
+}
+
+impl<I2C: I2c> Sensor for Bme280<I2C> {
+    fn name(&self) -> &'static str {
+        "bme280"
+    }
+
+    fn start(&mut self) -> Result<(), SensorError> {
+        self.i2c
+            .write(self.address, &[REG_CTRL_MEAS, CTRL_MEAS_FORCED])?;
+        Ok(())
+    }
+
+    fn measurement_time(&self) -> Duration {
+        MEASUREMENT_TIME
+    }
+
+    fn read(&mut self) -> Result<Reading, SensorError> {
+        let mut data = [0u8; 8];
+        self.i2c.write_read(self.address, &[REG_DATA], &mut data)?;
+        let raw20 = |i: usize| {
+            ((data[i] as i32) << 12) | ((data[i + 1] as i32) << 4) | (data[i + 2] as i32 >> 4)
+        };
+        let (adc_p, adc_t) = (raw20(0), raw20(3));
+        let adc_h = ((data[6] as i32) << 8) | data[7] as i32;
+        let (temperature, t_fine) = self.calibration.temperature(adc_t);
+        Ok(Reading {
+            temperature,
+            humidity: Some(self.calibration.humidity(adc_h, t_fine)),
+            pressure: self.calibration.pressure(adc_p, t_fine),
+        })
+    }
+}
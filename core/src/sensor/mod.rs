@@ -0,0 +1,167 @@
+use core::time::Duration;
+
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, watch::Watch};
+use embedded_hal::i2c::{ErrorKind, I2c};
+
+pub mod bme280;
+pub mod sht3x;
+
+use bme280::{Bme280, BME280_ADDRESSES};
+use sht3x::{Sht3x, SHT3X_ADDRESSES};
+
+/// Time between two readings of the sensor.
+pub const SENSOR_INTERVAL: Duration = Duration::from_secs(30);
+/// A reading older than this isn't shown any more, e.g. after the sensor was
+/// unplugged.
+pub const READING_MAX_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// What a sensor measured.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading {
+    /// Degrees Celsius.
+    pub temperature: f32,
+    /// Relative humidity in percent.
+    pub humidity: Option<f32>,
+    /// Air pressure in hectopascal.
+    pub pressure: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorError {
+    /// The transfer failed, e.g. no device answered at the address.
+    Bus(ErrorKind),
+    /// A CRC of the data doesn't match.
+    Checksum,
+    /// The device at the address isn't the expected chip, with its id.
+    UnknownChip(u8),
+}
+
+impl<E: embedded_hal::i2c::Error> From<E> for SensorError {
+    fn from(error: E) -> Self {
+        SensorError::Bus(error.kind())
+    }
+}
+
+/// A temperature sensor on the I2C bus, measuring on request.
+pub trait Sensor {
+    /// Name on the console.
+    fn name(&self) -> &'static str;
+
+    /// Starts a measurement, read it with [`Sensor::read`] after
+    /// [`Sensor::measurement_time`].
+    fn start(&mut self) -> Result<(), SensorError>;
+
+    /// How long a measurement takes at most.
+    fn measurement_time(&self) -> Duration;
+
+    /// Reads the measurement started last.
+    fn read(&mut self) -> Result<Reading, SensorError>;
+}
+
+/// Any of the supported sensors, see [`detect`].
+pub enum AnySensor<I2C: I2c> {
+    Sht3x(Sht3x<I2C>),
+    Bme280(Bme280<I2C>),
+}
+
+impl<I2C: I2c> Sensor for AnySensor<I2C> {
+    fn name(&self) -> &'static str {
+        match self {
+            AnySensor::Sht3x(sensor) => sensor.name(),
+            AnySensor::Bme280(sensor) => sensor.name(),
+        }
+    }
+
+    fn start(&mut self) -> Result<(), SensorError> {
+        match self {
+            AnySensor::Sht3x(sensor) => sensor.start(),
+            AnySensor::Bme280(sensor) => sensor.start(),
+        }
+    }
+
+    fn measurement_time(&self) -> Duration {
+        match self {
+            AnySensor::Sht3x(sensor) => sensor.measurement_time(),
+            AnySensor::Bme280(sensor) => sensor.measurement_time(),
+        }
+    }
+
+    fn read(&mut self) -> Result<Reading, SensorError> {
+        match self {
+            AnySensor::Sht3x(sensor) => sensor.read(),
+            AnySensor::Bme280(sensor) => sensor.read(),
+        }
+    }
+}
+
+/// Looks for a sensor at the addresses of an SHT3x and then of a BME280.
+/// Returns the bus back if none answers.
+pub fn detect<I2C: I2c>(mut i2c: I2C) -> Result<AnySensor<I2C>, I2C> {
+    for address in SHT3X_ADDRESSES {
+        let mut sensor = Sht3x::new(i2c, address);
+        if sensor.probe().is_ok() {
+            return Ok(AnySensor::Sht3x(sensor));
+        }
+        i2c = sensor.release();
+    }
+    for address in BME280_ADDRESSES {
+        let mut sensor = Bme280::new(i2c, address);
+        if sensor.init().is_ok() {
+            return Ok(AnySensor::Bme280(sensor));
+        }
+        i2c = sensor.release();
+    }
+    Err(i2c)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Published {
+    sensor: &'static str,
+    reading: Reading,
+    /// Uptime of the reading in milliseconds.
+    measured_ms: u64,
+}
+
+/// The latest reading of the local sensor, published by the sensor task on
+/// a [`Watch`] channel and read by the clockfaces on every frame and by the
+/// console. They take the latest value without waiting for a new one, so
+/// the channel has no receivers.
+pub struct SensorReadings {
+    latest: Watch<CriticalSectionRawMutex, Published, 0>,
+}
+
+impl SensorReadings {
+    pub const fn new() -> Self {
+        SensorReadings {
+            latest: Watch::new(),
+        }
+    }
+
+    /// Publishes `reading` of `sensor`, measured at the uptime `millis`.
+    pub fn publish(&self, sensor: &'static str, reading: Reading, millis: u64) {
+        self.latest.sender().send(Published {
+            sensor,
+            reading,
+            measured_ms: millis,
+        });
+    }
+
+    /// The latest reading at `millis`, `None` without one or if it is older
+    /// than [`READING_MAX_AGE`].
+    pub fn latest(&self, millis: u64) -> Option<Reading> {
+        let published = self.latest.try_get()?;
+        (millis.saturating_sub(published.measured_ms) <= READING_MAX_AGE.as_millis() as u64)
+            .then_some(published.reading)
+    }
+
+    /// Name of the sensor that published last.
+    pub fn sensor(&self) -> Option<&'static str> {
+        self.latest.try_get().map(|published| published.sensor)
+    }
+}
+
+impl Default for SensorReadings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
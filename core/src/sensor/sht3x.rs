@@ -0,0 +1,91 @@
+use core::time::Duration;
+
+use embedded_hal::i2c::I2c;
+
+use super::{Reading, Sensor, SensorError};
+
+/// The address with ADDR low and high.
+pub const SHT3X_ADDRESSES: [u8; 2] = [0x44, 0x45];
+
+/// Single shot measurement, high repeatability, without clock stretching so
+/// the bus stays free while measuring.
+const MEASURE: [u8; 2] = [0x24, 0x00];
+const READ_STATUS: [u8; 2] = [0xF3, 0x2D];
+/// 15.5 ms at high repeatability.
+const MEASUREMENT_TIME: Duration = Duration::from_millis(16);
+
+/// Sensirion SHT30/31/35 temperature and humidity sensor.
+pub struct Sht3x<I2C: I2c> {
+    i2c: I2C,
+    address: u8,
+}
+
+impl<I2C: I2c> Sht3x<I2C> {
+    pub fn new(i2c: I2C, address: u8) -> Self {
+        Sht3x { i2c, address }
+    }
+
+    /// Gives the bus back.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    /// Checks that an SHT3x answers by reading its status register.
+    pub fn probe(&mut self) -> Result<(), SensorError> {
+        let mut status = [0u8; 3];
+        self.i2c.write(self.address, &READ_STATUS)?;
+        self.i2c.read(self.address, &mut status)?;
+        word(&status).map(|_| ())
+    }
+}
+
+impl<I2C: I2c> Sensor for Sht3x<I2C> {
+    fn name(&self) -> &'static str {
+        "sht3x"
+    }
+
+    fn start(&mut self) -> Result<(), SensorError> {
+        self.i2c.write(self.address, &MEASURE)?;
+        Ok(())
+    }
+
+    fn measurement_time(&self) -> Duration {
+        MEASUREMENT_TIME
+    }
+
+    fn read(&mut self) -> Result<Reading, SensorError> {
+        let mut data = [0u8; 6];
+        self.i2c.read(self.address, &mut data)?;
+        let temperature = word(&data[..3])?;
+        let humidity = word(&data[3..])?;
+        Ok(Reading {
+            temperature: -45.0 + 175.0 * temperature as f32 / 65535.0,
+            humidity: Some(100.0 * humidity as f32 / 65535.0),
+            pressure: None,
+        })
+    }
+}
+
+/// A big endian word followed by its CRC.
+fn word(data: &[u8]) -> Result<u16, SensorError> {
+    if crc8(&data[..2]) != data[2] {
+        return Err(SensorError::Checksum);
+    }
+    Ok(u16::from_be_bytes([data[0], data[1]]))
+}
+
+/// CRC-8 with the polynomial 0x31 and 0xFF as initial value.
+pub fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0xFFu8;
+    for byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x31
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
@@ -0,0 +1,694 @@
+use core::cell::RefCell;
+
+use chrono_tz::Tz;
+use embassy_sync::{
+    blocking_mutex::{raw::CriticalSectionRawMutex, Mutex},
+    signal::Signal,
+};
+use heapless::Vec;
+
+use crate::{
+    alarm::{Alarm, ALARM_SIZE, MAX_ALARMS},
+    sun::Location,
+    transition::TransitionKind,
+};
+
+/// Size of the buffer the settings are serialized into, see [`Settings::save`].
+/// All settings at their longest take about 250 bytes, the rest is left for
+/// settings to come. A quarter of a 4 KB flash sector.
+pub const SETTINGS_SIZE: usize = 1024;
+
+// Serialized layout: magic, payload length (u16 LE), payload, fletcher-16 of
+// the payload (u16 LE). The payload is a list of `key, length, value` records,
+// unknown keys are skipped so older firmware can read newer settings.
+const MAGIC: [u8; 4] = *b"MCS1";
+const HEADER_SIZE: usize = MAGIC.len() + 2;
+const CHECKSUM_SIZE: usize = 2;
+
+const KEY_TIME_ZONE: u8 = 1;
+const KEY_SYNC_INTERVAL: u8 = 2;
+const KEY_SLEW_WINDOW: u8 = 3;
+const KEY_NTP_SERVER: u8 = 4;
+const KEY_HOUR_FORMAT: u8 = 5;
+const KEY_LEADING_ZERO: u8 = 6;
+/// One record per alarm, in order.
+const KEY_ALARM: u8 = 7;
+const KEY_ACTIVE_FACE: u8 = 8;
+/// Face and seconds (u16 LE) of every step, empty without rotation.
+const KEY_ROTATION: u8 = 9;
+const KEY_TRANSITION: u8 = 10;
+/// Transition of every rotation step, [`DEFAULT_STEP_TRANSITION`] for the
+/// default one. Kept apart from [`KEY_ROTATION`] so older firmware still reads
+/// the rotation.
+const KEY_ROTATION_TRANSITIONS: u8 = 11;
+const DEFAULT_STEP_TRANSITION: u8 = 0xFF;
+/// Latitude and longitude (f32 LE), empty without location.
+const KEY_LOCATION: u8 = 12;
+const KEY_SCENERY: u8 = 13;
+const KEY_TEMPERATURE_UNIT: u8 = 14;
+const KEY_SHOW_TEMPERATURE: u8 = 15;
+
+/// Most faces in the rotation.
+pub const MAX_ROTATION_STEPS: usize = 4;
+
+/// Default time between two clock synchronisations.
+pub const DEFAULT_SYNC_INTERVAL_MINUTES: u16 = 6 * 60;
+
+/// Default time small clock corrections are spread over.
+pub const DEFAULT_SLEW_WINDOW_SECONDS: u16 = 10 * 60;
+
+/// How the hour is shown on the clockface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HourFormat {
+    /// 0 to 23.
+    H24,
+    /// 1 to 12 with an AM/PM indicator.
+    H12,
+}
+
+impl HourFormat {
+    /// The hour shown for `hour` of a day, 0 to 23.
+    pub fn display_hour(&self, hour: u32) -> u32 {
+        match self {
+            HourFormat::H24 => hour,
+            HourFormat::H12 => match hour % 12 {
+                0 => 12,
+                hour => hour,
+            },
+        }
+    }
+
+    /// Hours of the clock, 12 or 24.
+    pub fn hours(&self) -> u8 {
+        match self {
+            HourFormat::H24 => 24,
+            HourFormat::H12 => 12,
+        }
+    }
+
+    pub fn from_hours(hours: u8) -> Option<Self> {
+        match hours {
+            24 => Some(HourFormat::H24),
+            12 => Some(HourFormat::H12),
+            _ => None,
+        }
+    }
+}
+
+/// How much of the background moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scenery {
+    /// Nothing moves.
+    Still,
+    /// The clouds drift.
+    Clouds,
+    /// The clouds drift and the hills, the bushes and the ground scroll by
+    /// as if Mario walked.
+    Scroll,
+}
+
+impl Scenery {
+    pub const ALL: [Scenery; 3] = [Scenery::Still, Scenery::Clouds, Scenery::Scroll];
+
+    /// Name on the console.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scenery::Still => "still",
+            Scenery::Clouds => "clouds",
+            Scenery::Scroll => "scroll",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scenery| scenery.name() == name)
+    }
+
+    /// Id in the serialized settings.
+    fn id(&self) -> u8 {
+        match self {
+            Scenery::Still => 0,
+            Scenery::Clouds => 1,
+            Scenery::Scroll => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|scenery| scenery.id() == id)
+    }
+}
+
+/// Unit the temperature is shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    pub const ALL: [TemperatureUnit; 2] = [TemperatureUnit::Celsius, TemperatureUnit::Fahrenheit];
+
+    /// Name on the console.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "celsius",
+            TemperatureUnit::Fahrenheit => "fahrenheit",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|unit| unit.name() == name)
+    }
+
+    /// Letter after the degrees, e.g. `C` in 21°C.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "C",
+            TemperatureUnit::Fahrenheit => "F",
+        }
+    }
+
+    /// The temperature of `celsius` degrees Celsius in this unit.
+    pub fn convert(&self, celsius: f32) -> f32 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    /// Id in the serialized settings.
+    fn id(&self) -> u8 {
+        match self {
+            TemperatureUnit::Celsius => 0,
+            TemperatureUnit::Fahrenheit => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|unit| unit.id() == id)
+    }
+}
+
+/// A clockface the display can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaceId {
+    /// Mario hitting the blocks with the time.
+    Mario,
+    /// The date.
+    Date,
+}
+
+impl FaceId {
+    pub const ALL: [FaceId; 2] = [FaceId::Mario, FaceId::Date];
+
+    /// Name on the console.
+    pub fn name(&self) -> &'static str {
+        match self {
+            FaceId::Mario => "mario",
+            FaceId::Date => "date",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|face| face.name() == name)
+    }
+
+    /// Id in the serialized settings.
+    fn id(&self) -> u8 {
+        match self {
+            FaceId::Mario => 0,
+            FaceId::Date => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|face| face.id() == id)
+    }
+}
+
+/// A face of the rotation and how long it is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RotationStep {
+    pub face: FaceId,
+    pub seconds: u16,
+    /// Transition to this face, `None` for [`SettingsValues::transition`].
+    pub transition: Option<TransitionKind>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsError {
+    /// The settings don't fit into [`SETTINGS_SIZE`] bytes.
+    TooLarge,
+}
+
+/// Values of all user settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SettingsValues {
+    /// IANA time zone the clock displays.
+    pub time_zone: Tz,
+    /// Minutes between two clock synchronisations.
+    pub sync_interval_minutes: u16,
+    /// Seconds small clock corrections are slewed over, 0 to always step.
+    pub slew_window_seconds: u16,
+    /// Whether the time is served to the LAN over NTP.
+    pub ntp_server: bool,
+    pub hour_format: HourFormat,
+    /// Whether hours below 10 are shown with a leading zero, e.g. `09`.
+    pub leading_zero: bool,
+    pub alarms: Vec<Alarm, MAX_ALARMS>,
+    /// Face shown unless the faces rotate.
+    pub active_face: FaceId,
+    /// Faces shown in turn, empty to always show the active face.
+    pub rotation: Vec<RotationStep, MAX_ROTATION_STEPS>,
+    /// Transition shown when the face changes.
+    pub transition: TransitionKind,
+    /// Where the clock is, for the sunrise and sunset. Without it the sky is
+    /// always blue.
+    pub location: Option<Location>,
+    pub scenery: Scenery,
+    pub temperature_unit: TemperatureUnit,
+    /// Whether the Mario face shows the temperature once one is known.
+    pub show_temperature: bool,
+}
+
+/// User settings shared between the tasks. Every change signals [`Settings::changed`]
+/// so the new values can be persisted.
+pub struct Settings {
+    values: Mutex<CriticalSectionRawMutex, RefCell<SettingsValues>>,
+    pub changed: Signal<CriticalSectionRawMutex, ()>,
+}
+
+impl Settings {
+    pub const fn new(time_zone: Tz) -> Self {
+        Settings {
+            values: Mutex::new(RefCell::new(SettingsValues {
+                time_zone,
+                sync_interval_minutes: DEFAULT_SYNC_INTERVAL_MINUTES,
+                slew_window_seconds: DEFAULT_SLEW_WINDOW_SECONDS,
+                ntp_server: false,
+                hour_format: HourFormat::H24,
+                leading_zero: true,
+                alarms: Vec::new(),
+                active_face: FaceId::Mario,
+                rotation: Vec::new(),
+                transition: TransitionKind::Fade,
+                location: None,
+                scenery: Scenery::Clouds,
+                temperature_unit: TemperatureUnit::Celsius,
+                show_temperature: true,
+            })),
+            changed: Signal::new(),
+        }
+    }
+
+    pub fn values(&self) -> SettingsValues {
+        self.values.lock(|values| values.borrow().clone())
+    }
+
+    /// Replaces all values, e.g. to undo a change that can't be saved.
+    pub fn set_values(&self, values: SettingsValues) {
+        self.update(|current| *current = values);
+    }
+
+    pub fn time_zone(&self) -> Tz {
+        self.values.lock(|values| values.borrow().time_zone)
+    }
+
+    pub fn set_time_zone(&self, time_zone: Tz) {
+        self.update(|values| values.time_zone = time_zone);
+    }
+
+    pub fn sync_interval_minutes(&self) -> u16 {
+        self.values
+            .lock(|values| values.borrow().sync_interval_minutes)
+    }
+
+    pub fn set_sync_interval_minutes(&self, minutes: u16) {
+        self.update(|values| values.sync_interval_minutes = minutes);
+    }
+
+    pub fn slew_window_seconds(&self) -> u16 {
+        self.values
+            .lock(|values| values.borrow().slew_window_seconds)
+    }
+
+    pub fn set_slew_window_seconds(&self, seconds: u16) {
+        self.update(|values| values.slew_window_seconds = seconds);
+    }
+
+    pub fn ntp_server(&self) -> bool {
+        self.values.lock(|values| values.borrow().ntp_server)
+    }
+
+    pub fn set_ntp_server(&self, enabled: bool) {
+        self.update(|values| values.ntp_server = enabled);
+    }
+
+    pub fn hour_format(&self) -> HourFormat {
+        self.values.lock(|values| values.borrow().hour_format)
+    }
+
+    pub fn set_hour_format(&self, format: HourFormat) {
+        self.update(|values| values.hour_format = format);
+    }
+
+    pub fn leading_zero(&self) -> bool {
+        self.values.lock(|values| values.borrow().leading_zero)
+    }
+
+    pub fn set_leading_zero(&self, enabled: bool) {
+        self.update(|values| values.leading_zero = enabled);
+    }
+
+    pub fn active_face(&self) -> FaceId {
+        self.values.lock(|values| values.borrow().active_face)
+    }
+
+    pub fn set_active_face(&self, face: FaceId) {
+        self.update(|values| values.active_face = face);
+    }
+
+    pub fn rotation(&self) -> Vec<RotationStep, MAX_ROTATION_STEPS> {
+        self.values.lock(|values| values.borrow().rotation.clone())
+    }
+
+    /// Rotates through `rotation`, an empty one stops rotating.
+    pub fn set_rotation(&self, rotation: Vec<RotationStep, MAX_ROTATION_STEPS>) {
+        self.update(|values| values.rotation = rotation);
+    }
+
+    pub fn transition(&self) -> TransitionKind {
+        self.values.lock(|values| values.borrow().transition)
+    }
+
+    pub fn set_transition(&self, transition: TransitionKind) {
+        self.update(|values| values.transition = transition);
+    }
+
+    pub fn location(&self) -> Option<Location> {
+        self.values.lock(|values| values.borrow().location)
+    }
+
+    pub fn set_location(&self, location: Option<Location>) {
+        self.update(|values| values.location = location);
+    }
+
+    pub fn scenery(&self) -> Scenery {
+        self.values.lock(|values| values.borrow().scenery)
+    }
+
+    pub fn set_scenery(&self, scenery: Scenery) {
+        self.update(|values| values.scenery = scenery);
+    }
+
+    pub fn temperature_unit(&self) -> TemperatureUnit {
+        self.values.lock(|values| values.borrow().temperature_unit)
+    }
+
+    pub fn set_temperature_unit(&self, unit: TemperatureUnit) {
+        self.update(|values| values.temperature_unit = unit);
+    }
+
+    pub fn show_temperature(&self) -> bool {
+        self.values.lock(|values| values.borrow().show_temperature)
+    }
+
+    pub fn set_show_temperature(&self, enabled: bool) {
+        self.update(|values| values.show_temperature = enabled);
+    }
+
+    pub fn alarms(&self) -> Vec<Alarm, MAX_ALARMS> {
+        self.values.lock(|values| values.borrow().alarms.clone())
+    }
+
+    /// Adds an alarm, returns it back if [`MAX_ALARMS`] are already set.
+    pub fn add_alarm(&self, alarm: Alarm) -> Result<(), Alarm> {
+        let full = self.values.lock(|values| values.borrow().alarms.is_full());
+        if full {
+            return Err(alarm);
+        }
+        self.update(|values| {
+            // Can't fail, checked above and only the console adds alarms
+            let _ = values.alarms.push(alarm);
+        });
+        Ok(())
+    }
+
+    /// Removes the alarm at `index`, `None` if there is none.
+    pub fn remove_alarm(&self, index: usize) -> Option<Alarm> {
+        if index >= self.alarms().len() {
+            return None;
+        }
+        let mut removed = None;
+        self.update(|values| removed = Some(values.alarms.remove(index)));
+        removed
+    }
+
+    /// Changes the alarm at `index`, returns `false` if there is none.
+    pub fn update_alarm(&self, index: usize, f: impl FnOnce(&mut Alarm)) -> bool {
+        if index >= self.alarms().len() {
+            return false;
+        }
+        self.update(|values| f(&mut values.alarms[index]));
+        true
+    }
+
+    /// Disables the alarms that equal one of `rung` in time, weekdays and
+    /// label, in one change. Matching by value rather than by position
+    /// leaves the other alarms alone if one was removed meanwhile.
+    pub fn disable_alarms(&self, rung: &[Alarm]) {
+        self.update(|values| {
+            for alarm in &mut values.alarms {
+                if rung.iter().any(|rung| {
+                    rung.time == alarm.time
+                        && rung.weekdays == alarm.weekdays
+                        && rung.label == alarm.label
+                }) {
+                    alarm.enabled = false;
+                }
+            }
+        });
+    }
+
+    fn update(&self, f: impl FnOnce(&mut SettingsValues)) {
+        self.values.lock(|values| f(&mut values.borrow_mut()));
+        self.changed.signal(());
+    }
+
+    /// Serializes the current values into `buf`, returns the number of bytes
+    /// used. Fails if they don't fit, `buf` holds no valid settings then.
+    pub fn save(&self, buf: &mut [u8; SETTINGS_SIZE]) -> Result<usize, SettingsError> {
+        let values = self.values();
+        let mut writer = RecordWriter::new(buf);
+        writer.record(KEY_TIME_ZONE, values.time_zone.name().as_bytes())?;
+        writer.record(
+            KEY_SYNC_INTERVAL,
+            &values.sync_interval_minutes.to_le_bytes(),
+        )?;
+        writer.record(KEY_SLEW_WINDOW, &values.slew_window_seconds.to_le_bytes())?;
+        writer.record(KEY_NTP_SERVER, &[values.ntp_server as u8])?;
+        writer.record(KEY_HOUR_FORMAT, &[values.hour_format.hours()])?;
+        writer.record(KEY_LEADING_ZERO, &[values.leading_zero as u8])?;
+        for alarm in &values.alarms {
+            let mut bytes = [0u8; ALARM_SIZE];
+            let len = alarm.to_bytes(&mut bytes);
+            writer.record(KEY_ALARM, &bytes[..len])?;
+        }
+        writer.record(KEY_ACTIVE_FACE, &[values.active_face.id()])?;
+        let mut rotation = [0u8; MAX_ROTATION_STEPS * 3];
+        for (step, bytes) in values.rotation.iter().zip(rotation.chunks_mut(3)) {
+            bytes[0] = step.face.id();
+            bytes[1..].copy_from_slice(&step.seconds.to_le_bytes());
+        }
+        writer.record(KEY_ROTATION, &rotation[..values.rotation.len() * 3])?;
+        writer.record(KEY_TRANSITION, &[values.transition.id()])?;
+        let mut transitions = [0u8; MAX_ROTATION_STEPS];
+        for (step, byte) in values.rotation.iter().zip(&mut transitions) {
+            *byte = step
+                .transition
+                .map_or(DEFAULT_STEP_TRANSITION, |transition| transition.id());
+        }
+        writer.record(
+            KEY_ROTATION_TRANSITIONS,
+            &transitions[..values.rotation.len()],
+        )?;
+        let mut location = [0u8; 8];
+        let len = match values.location {
+            Some(Location {
+                latitude,
+                longitude,
+            }) => {
+                location[..4].copy_from_slice(&latitude.to_le_bytes());
+                location[4..].copy_from_slice(&longitude.to_le_bytes());
+                location.len()
+            }
+            None => 0,
+        };
+        writer.record(KEY_LOCATION, &location[..len])?;
+        writer.record(KEY_SCENERY, &[values.scenery.id()])?;
+        writer.record(KEY_TEMPERATURE_UNIT, &[values.temperature_unit.id()])?;
+        writer.record(KEY_SHOW_TEMPERATURE, &[values.show_temperature as u8])?;
+        Ok(writer.finish())
+    }
+
+    /// Restores values serialized by [`Settings::save`]. Settings missing in
+    /// `buf` keep their current value. Returns `false` and changes nothing if
+    /// `buf` doesn't hold valid settings, e.g. erased flash.
+    pub fn load(&self, buf: &[u8]) -> bool {
+        let Some(payload) = payload(buf) else {
+            return false;
+        };
+
+        let mut values = self.values();
+        let mut records = payload;
+        // Saved alarms replace the current ones as a whole
+        let mut alarms_seen = false;
+        while let [key, len, rest @ ..] = records {
+            let Some((value, rest)) = rest.split_at_checked(*len as usize) else {
+                return false;
+            };
+            match (*key, value) {
+                (KEY_TIME_ZONE, _) => {
+                    if let Some(time_zone) = core::str::from_utf8(value)
+                        .ok()
+                        .and_then(|name| name.parse().ok())
+                    {
+                        values.time_zone = time_zone;
+                    }
+                }
+                (KEY_SYNC_INTERVAL, &[low, high]) => {
+                    values.sync_interval_minutes = u16::from_le_bytes([low, high]);
+                }
+                (KEY_SLEW_WINDOW, &[low, high]) => {
+                    values.slew_window_seconds = u16::from_le_bytes([low, high]);
+                }
+                (KEY_NTP_SERVER, &[enabled]) => values.ntp_server = enabled != 0,
+                (KEY_HOUR_FORMAT, &[hours]) => {
+                    if let Some(format) = HourFormat::from_hours(hours) {
+                        values.hour_format = format;
+                    }
+                }
+                (KEY_LEADING_ZERO, &[enabled]) => values.leading_zero = enabled != 0,
+                (KEY_ALARM, _) => {
+                    if !alarms_seen {
+                        values.alarms.clear();
+                        alarms_seen = true;
+                    }
+                    if let Some(alarm) = Alarm::from_bytes(value) {
+                        // Extra alarms of a newer firmware are dropped
+                        let _ = values.alarms.push(alarm);
+                    }
+                }
+                (KEY_ACTIVE_FACE, &[id]) => {
+                    if let Some(face) = FaceId::from_id(id) {
+                        values.active_face = face;
+                    }
+                }
+                (KEY_ROTATION, _) => {
+                    values.rotation = value
+                        .chunks_exact(3)
+                        .filter_map(|step| {
+                            Some(RotationStep {
+                                face: FaceId::from_id(step[0])?,
+                                seconds: u16::from_le_bytes([step[1], step[2]]),
+                                transition: None,
+                            })
+                        })
+                        .take(MAX_ROTATION_STEPS)
+                        .collect();
+                }
+                (KEY_TRANSITION, &[id]) => {
+                    if let Some(transition) = TransitionKind::from_id(id) {
+                        values.transition = transition;
+                    }
+                }
+                // Saved after the rotation
+                (KEY_ROTATION_TRANSITIONS, _) => {
+                    for (step, &id) in values.rotation.iter_mut().zip(value) {
+                        step.transition = TransitionKind::from_id(id);
+                    }
+                }
+                (KEY_SCENERY, &[id]) => {
+                    if let Some(scenery) = Scenery::from_id(id) {
+                        values.scenery = scenery;
+                    }
+                }
+                (KEY_TEMPERATURE_UNIT, &[id]) => {
+                    if let Some(unit) = TemperatureUnit::from_id(id) {
+                        values.temperature_unit = unit;
+                    }
+                }
+                (KEY_SHOW_TEMPERATURE, &[enabled]) => values.show_temperature = enabled != 0,
+                (KEY_LOCATION, &[]) => values.location = None,
+                (KEY_LOCATION, &[a, b, c, d, e, f, g, h]) => {
+                    let latitude = f32::from_le_bytes([a, b, c, d]);
+                    let longitude = f32::from_le_bytes([e, f, g, h]);
+                    if let Some(location) = Location::new(latitude, longitude) {
+                        values.location = Some(location);
+                    }
+                }
+                _ => {}
+            }
+            records = rest;
+        }
+
+        self.values.lock(|v| *v.borrow_mut() = values);
+        true
+    }
+}
+
+/// Returns the records of serialized settings, `None` if the header or the
+/// checksum don't match.
+fn payload(buf: &[u8]) -> Option<&[u8]> {
+    let (header, rest) = buf.split_at_checked(HEADER_SIZE)?;
+    if header[..MAGIC.len()] != MAGIC {
+        return None;
+    }
+    let len = u16::from_le_bytes([header[MAGIC.len()], header[MAGIC.len() + 1]]) as usize;
+    let (payload, rest) = rest.split_at_checked(len)?;
+    let checksum = rest.get(..CHECKSUM_SIZE)?;
+    (fletcher16(payload).to_le_bytes() == checksum).then_some(payload)
+}
+
+fn fletcher16(data: &[u8]) -> u16 {
+    let (mut sum1, mut sum2) = (0u16, 0u16);
+    for byte in data {
+        sum1 = (sum1 + *byte as u16) % 255;
+        sum2 = (sum2 + sum1) % 255;
+    }
+    (sum2 << 8) | sum1
+}
+
+struct RecordWriter<'a> {
+    buf: &'a mut [u8; SETTINGS_SIZE],
+    pos: usize,
+}
+
+impl<'a> RecordWriter<'a> {
+    fn new(buf: &'a mut [u8; SETTINGS_SIZE]) -> Self {
+        buf[..MAGIC.len()].copy_from_slice(&MAGIC);
+        RecordWriter {
+            buf,
+            pos: HEADER_SIZE,
+        }
+    }
+
+    fn record(&mut self, key: u8, value: &[u8]) -> Result<(), SettingsError> {
+        let end = self.pos + 2 + value.len();
+        if value.len() > u8::MAX as usize || end + CHECKSUM_SIZE > SETTINGS_SIZE {
+            return Err(SettingsError::TooLarge);
+        }
+        self.buf[self.pos] = key;
+        self.buf[self.pos + 1] = value.len() as u8;
+        self.buf[self.pos + 2..end].copy_from_slice(value);
+        self.pos = end;
+        Ok(())
+    }
+
+    fn finish(self) -> usize {
+        let len = self.pos - HEADER_SIZE;
+        self.buf[MAGIC.len()..HEADER_SIZE].copy_from_slice(&(len as u16).to_le_bytes());
+        let checksum = fletcher16(&self.buf[HEADER_SIZE..self.pos]);
+        self.buf[self.pos..self.pos + CHECKSUM_SIZE].copy_from_slice(&checksum.to_le_bytes());
+        self.pos + CHECKSUM_SIZE
+    }
+}
@@ -0,0 +1,173 @@
+use core::{future::Future, net::IpAddr};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+
+use crate::{
+    clock::RtcError,
+    nmea::NmeaError,
+    ntp::{self, NtpConfig, NtpError, NtpTransport},
+    time::TimeSource,
+};
+
+/// Frequency error assumed for the local clock when a sample ages, the
+/// crystal of the ESP32-S3 is well within this.
+const LOCAL_DRIFT_PPM: i64 = 100;
+
+/// Uncertainty of a time typed in by hand.
+const MANUAL_UNCERTAINTY: TimeDelta = TimeDelta::seconds(1);
+
+/// NTP stratum of a clock set from a radio clock such as a GPS.
+pub const PRIMARY_STRATUM: u8 = 1;
+/// NTP stratum of a clock only set from the RTC or by hand, low enough to
+/// serve an isolated network, high enough for clients to prefer any other
+/// server.
+pub const LOCAL_STRATUM: u8 = 10;
+/// NTP reference ID of a GPS, see RFC 5905 7.3.
+pub const REFERENCE_GPS: [u8; 4] = *b"GPS\0";
+/// NTP reference ID of the undisciplined local clock.
+pub const REFERENCE_LOCAL: [u8; 4] = *b"LOCL";
+
+/// Where a reference time comes from. On equal uncertainty the later kinds
+/// are preferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SourceKind {
+    Rtc,
+    Manual,
+    Gps,
+    Ntp,
+}
+
+impl SourceKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SourceKind::Rtc => "rtc",
+            SourceKind::Manual => "manual",
+            SourceKind::Gps => "gps",
+            SourceKind::Ntp => "ntp",
+        }
+    }
+}
+
+/// A reading of a reference clock.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub kind: SourceKind,
+    /// The reference time at `uptime_micros`.
+    pub time: DateTime<Utc>,
+    /// Local uptime in microseconds at which `time` was valid.
+    pub uptime_micros: u64,
+    /// How far `time` may be off.
+    pub uncertainty: TimeDelta,
+    /// NTP stratum of the clock once set from this sample.
+    pub stratum: u8,
+    /// NTP reference ID of the source, the server address for NTP.
+    pub reference_id: [u8; 4],
+}
+
+impl Sample {
+    /// Uncertainty of the local clock set from this sample at `uptime_micros`,
+    /// grows with the drift of the local clock.
+    pub fn uncertainty_at(&self, uptime_micros: u64) -> TimeDelta {
+        let age =
+            i64::try_from(uptime_micros.saturating_sub(self.uptime_micros)).unwrap_or(i64::MAX);
+        let drift = TimeDelta::microseconds(age / 1_000_000 * LOCAL_DRIFT_PPM);
+        self.uncertainty
+            .checked_add(&drift)
+            .unwrap_or(TimeDelta::MAX)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceError {
+    Ntp(NtpError),
+    Rtc(RtcError),
+    Nmea(NmeaError),
+    /// The source has no time to offer, e.g. a GPS without a fix.
+    Unavailable,
+}
+
+impl From<NtpError> for SourceError {
+    fn from(error: NtpError) -> Self {
+        SourceError::Ntp(error)
+    }
+}
+
+impl From<RtcError> for SourceError {
+    fn from(error: RtcError) -> Self {
+        SourceError::Rtc(error)
+    }
+}
+
+impl From<NmeaError> for SourceError {
+    fn from(error: NmeaError) -> Self {
+        SourceError::Nmea(error)
+    }
+}
+
+/// A reference clock the local clock can be set from, see
+/// [`crate::clock::SyncTracker::offer`] for how the samples are ranked.
+pub trait Reference {
+    fn kind(&self) -> SourceKind;
+
+    /// Waits for the next reading of the reference clock.
+    fn sample(&mut self) -> impl Future<Output = Result<Sample, SourceError>>;
+}
+
+/// Time set by hand, e.g. on the console. Each [`ManualSource::sample`] waits
+/// for the next time signaled on `requested`.
+pub struct ManualSource<'a, T: TimeSource> {
+    pub requested: &'a Signal<CriticalSectionRawMutex, DateTime<Utc>>,
+    pub time: T,
+}
+
+impl<T: TimeSource> Reference for ManualSource<'_, T> {
+    fn kind(&self) -> SourceKind {
+        SourceKind::Manual
+    }
+
+    async fn sample(&mut self) -> Result<Sample, SourceError> {
+        let time = self.requested.wait().await;
+        Ok(Sample {
+            kind: SourceKind::Manual,
+            time,
+            uptime_micros: self.time.micros(),
+            uncertainty: MANUAL_UNCERTAINTY,
+            stratum: LOCAL_STRATUM,
+            reference_id: REFERENCE_LOCAL,
+        })
+    }
+}
+
+/// NTP servers, queried on every [`NtpSource::sample`].
+pub struct NtpSource<'a, N: NtpTransport, T: TimeSource> {
+    pub config: NtpConfig<'a>,
+    pub transport: N,
+    pub time: T,
+}
+
+impl<N: NtpTransport, T: TimeSource> Reference for NtpSource<'_, N, T> {
+    fn kind(&self) -> SourceKind {
+        SourceKind::Ntp
+    }
+
+    async fn sample(&mut self) -> Result<Sample, SourceError> {
+        let reply = ntp::sync(&self.config, &mut self.transport, &self.time).await?;
+        let uptime_micros = self.time.micros();
+        let local = self.time.now();
+        Ok(Sample {
+            kind: SourceKind::Ntp,
+            time: local.checked_add_signed(reply.offset).unwrap_or(local),
+            uptime_micros,
+            // The offset is exact for symmetric paths and off by at most half
+            // the round trip otherwise
+            uncertainty: reply.round_trip.abs() / 2,
+            stratum: reply.stratum.saturating_add(1),
+            reference_id: match reply.server.ip() {
+                IpAddr::V4(addr) => addr.octets(),
+                // Unused, the servers are only looked up as IPv4
+                IpAddr::V6(_) => [0; 4],
+            },
+        })
+    }
+}
@@ -0,0 +1,145 @@
+use core::{f64::consts::PI, fmt};
+
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeDelta, Utc};
+use libm::{acos, asin, atan2, cos, floor, sin};
+
+/// Julian day of 2000-01-01 12:00 UTC, the epoch of the formulas below.
+const J2000: f64 = 2_451_545.0;
+/// Julian day of the Unix epoch.
+const UNIX_EPOCH: f64 = 2_440_587.5;
+/// Tilt of the earth's axis, degrees.
+const OBLIQUITY: f64 = 23.4397;
+/// Elevation of the sun's center at sunrise and sunset, lowered by the
+/// refraction of the atmosphere and the radius of the sun, degrees.
+const HORIZON: f64 = -0.833;
+
+/// Where the clock is, in degrees. North and east are positive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    pub latitude: f32,
+    pub longitude: f32,
+}
+
+impl Location {
+    /// Returns `None` for latitudes beyond ±90 or longitudes beyond ±180.
+    pub fn new(latitude: f32, longitude: f32) -> Option<Self> {
+        ((-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude)).then_some(
+            Location {
+                latitude,
+                longitude,
+            },
+        )
+    }
+}
+
+impl fmt::Display for Location {
+    /// e.g. `47.37,8.54`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2},{:.2}", self.latitude, self.longitude)
+    }
+}
+
+/// Sunrise and sunset of a day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SunEvents {
+    Daily {
+        sunrise: DateTime<Utc>,
+        sunset: DateTime<Utc>,
+    },
+    /// The sun doesn't set, e.g. north of the arctic circle in June.
+    PolarDay,
+    /// The sun doesn't rise.
+    PolarNight,
+}
+
+/// Position of the sun in the sky.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SunPosition {
+    /// Degrees above the horizon, negative at night.
+    pub elevation: f32,
+    /// Whether the sun is before its highest point of the day, so rising.
+    pub rising: bool,
+}
+
+fn to_radians(degrees: f64) -> f64 {
+    degrees * PI / 180.0
+}
+
+fn to_degrees(radians: f64) -> f64 {
+    radians * 180.0 / PI
+}
+
+/// `value` wrapped into `0..period`, `rem_euclid` needs std for floats.
+fn wrap(value: f64, period: f64) -> f64 {
+    value - period * floor(value / period)
+}
+
+/// Days since [`J2000`], with fractions.
+fn days_since_j2000(time: DateTime<Utc>) -> f64 {
+    time.timestamp_millis() as f64 / 86_400_000.0 + UNIX_EPOCH - J2000
+}
+
+fn from_days_since_j2000(days: f64) -> DateTime<Utc> {
+    let millis = (days + J2000 - UNIX_EPOCH) * 86_400_000.0;
+    DateTime::from_timestamp_millis(millis as i64).unwrap_or_default()
+}
+
+/// Mean anomaly and ecliptic longitude of the sun `days` after J2000, both in
+/// radians.
+fn ecliptic(days: f64) -> (f64, f64) {
+    let anomaly = to_radians(wrap(357.5291 + 0.985_600_28 * days, 360.0));
+    let center = 1.9148 * sin(anomaly) + 0.02 * sin(2.0 * anomaly) + 0.0003 * sin(3.0 * anomaly);
+    let longitude = wrap(to_degrees(anomaly) + center + 180.0 + 102.9372, 360.0);
+    (anomaly, to_radians(longitude))
+}
+
+/// Declination of the sun for its ecliptic `longitude`, radians.
+fn declination(longitude: f64) -> f64 {
+    asin(sin(longitude) * sin(to_radians(OBLIQUITY)))
+}
+
+/// Where the sun is at `time` seen from `location`.
+pub fn position(location: Location, time: DateTime<Utc>) -> SunPosition {
+    let days = days_since_j2000(time);
+    let (_, longitude) = ecliptic(days);
+    let declination = declination(longitude);
+    let right_ascension = atan2(cos(to_radians(OBLIQUITY)) * sin(longitude), cos(longitude));
+    let sidereal = to_radians(280.460_618_37 + 360.985_647_366_29 * days);
+    let hour_angle = wrap(
+        sidereal + to_radians(location.longitude as f64) - right_ascension + PI,
+        2.0 * PI,
+    ) - PI;
+    let latitude = to_radians(location.latitude as f64);
+    let elevation =
+        asin(sin(latitude) * sin(declination) + cos(latitude) * cos(declination) * cos(hour_angle));
+    SunPosition {
+        elevation: to_degrees(elevation) as f32,
+        rising: hour_angle < 0.0,
+    }
+}
+
+/// Sunrise and sunset on `date` at `location`, accurate to about a minute.
+/// The times belong to the solar day around noon at `location`, so `date` is
+/// the local date.
+pub fn events(location: Location, date: NaiveDate) -> SunEvents {
+    let noon = date.and_time(NaiveTime::MIN).and_utc() + TimeDelta::hours(12);
+    // Mean solar noon at the longitude
+    let days = days_since_j2000(noon) - location.longitude as f64 / 360.0;
+    let (anomaly, longitude) = ecliptic(days);
+    let transit = days + 0.0053 * sin(anomaly) - 0.0069 * sin(2.0 * longitude);
+    let declination = declination(longitude);
+    let latitude = to_radians(location.latitude as f64);
+    let cos_hour_angle = (sin(to_radians(HORIZON)) - sin(latitude) * sin(declination))
+        / (cos(latitude) * cos(declination));
+    if cos_hour_angle < -1.0 {
+        return SunEvents::PolarDay;
+    }
+    if cos_hour_angle > 1.0 {
+        return SunEvents::PolarNight;
+    }
+    let half_day = to_degrees(acos(cos_hour_angle)) / 360.0;
+    SunEvents::Daily {
+        sunrise: from_days_since_j2000(transit - half_day),
+        sunset: from_days_since_j2000(transit + half_day),
+    }
+}
@@ -0,0 +1,133 @@
+use core::{cell::Cell, time::Duration};
+
+use chrono::{DateTime, Utc};
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+
+/// Source of time for the engine, the sprites and the clockfaces.
+///
+/// On the device this is backed by the embassy time driver and the RTC/NTP
+/// synchronised clock, in tests and in the simulator by [`ManualTime`].
+pub trait TimeSource: Send + Sync {
+    /// Monotonic milliseconds since boot, drives the sprite animations.
+    fn millis(&self) -> u64;
+
+    /// Monotonic microseconds since boot.
+    fn micros(&self) -> u64 {
+        self.millis() * 1_000
+    }
+
+    /// Current wall clock time.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Whether [`TimeSource::now`] can be trusted, `false` e.g. after the RTC
+    /// lost power and before the clock was synchronised.
+    fn is_known(&self) -> bool {
+        true
+    }
+
+    /// Number of times the wall clock stepped, i.e. jumped to a new time
+    /// instead of being slewed. Clockfaces compare it between frames to react
+    /// to a jump of the displayed time.
+    fn steps(&self) -> u32 {
+        0
+    }
+}
+
+impl<T: TimeSource> TimeSource for &T {
+    fn millis(&self) -> u64 {
+        (**self).millis()
+    }
+
+    fn micros(&self) -> u64 {
+        (**self).micros()
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        (**self).now()
+    }
+
+    fn is_known(&self) -> bool {
+        (**self).is_known()
+    }
+
+    fn steps(&self) -> u32 {
+        (**self).steps()
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ManualState {
+    millis: u64,
+    now: DateTime<Utc>,
+    known: bool,
+    steps: u32,
+}
+
+/// A clock that only moves when told to, for deterministic tests and
+/// simulations. Uptime starts at zero like on a freshly booted device.
+pub struct ManualTime {
+    state: Mutex<CriticalSectionRawMutex, Cell<ManualState>>,
+}
+
+impl ManualTime {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        ManualTime {
+            state: Mutex::new(Cell::new(ManualState {
+                millis: 0,
+                now,
+                known: true,
+                steps: 0,
+            })),
+        }
+    }
+
+    /// Like [`ManualTime::new`] but the time is unknown until [`ManualTime::set`].
+    pub fn unknown(now: DateTime<Utc>) -> Self {
+        let time = Self::new(now);
+        time.update(|state| state.known = false);
+        time
+    }
+
+    /// Steps the wall clock without touching the uptime, like a clock sync.
+    pub fn set(&self, now: DateTime<Utc>) {
+        self.update(|state| {
+            state.now = now;
+            state.known = true;
+            state.steps += 1;
+        });
+    }
+
+    /// Moves the uptime and the wall clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        self.update(|state| {
+            state.millis += duration.as_millis() as u64;
+            state.now += chrono::TimeDelta::from_std(duration).expect("duration out of range");
+        });
+    }
+
+    fn update(&self, f: impl FnOnce(&mut ManualState)) {
+        self.state.lock(|cell| {
+            let mut state = cell.get();
+            f(&mut state);
+            cell.set(state);
+        });
+    }
+}
+
+impl TimeSource for ManualTime {
+    fn millis(&self) -> u64 {
+        self.state.lock(|cell| cell.get().millis)
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        self.state.lock(|cell| cell.get().now)
+    }
+
+    fn is_known(&self) -> bool {
+        self.state.lock(|cell| cell.get().known)
+    }
+
+    fn steps(&self) -> u32 {
+        self.state.lock(|cell| cell.get().steps)
+    }
+}
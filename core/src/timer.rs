@@ -0,0 +1,216 @@
+use core::{cell::Cell, fmt, time::Duration};
+
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+
+/// Longest countdown, the blocks show at most 99 hours.
+pub const MAX_COUNTDOWN: Duration = Duration::from_secs(99 * 3600 + 59 * 60 + 59);
+
+/// Parses a countdown of `mm:ss` or `hh:mm:ss`, up to [`MAX_COUNTDOWN`].
+pub fn parse_duration(duration: &str) -> Option<Duration> {
+    let mut seconds = 0u64;
+    let mut parts = 0;
+    for part in duration.split(':') {
+        let value: u64 = part.parse().ok()?;
+        // Only the leading part may exceed 59, e.g. `90:00`
+        if parts > 0 && value > 59 {
+            return None;
+        }
+        seconds = seconds.checked_mul(60)?.checked_add(value)?;
+        parts += 1;
+    }
+    let duration = Duration::from_secs(seconds);
+    ((2..=3).contains(&parts) && !duration.is_zero() && duration <= MAX_COUNTDOWN)
+        .then_some(duration)
+}
+
+/// Time measured in uptime milliseconds, so steps of the wall clock don't
+/// affect it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Watch {
+    /// Milliseconds measured before the last pause.
+    elapsed_ms: u64,
+    /// Uptime of the last start, `None` while paused.
+    running_since: Option<u64>,
+}
+
+impl Watch {
+    fn started(millis: u64) -> Self {
+        Watch {
+            elapsed_ms: 0,
+            running_since: Some(millis),
+        }
+    }
+
+    fn elapsed(&self, millis: u64) -> u64 {
+        let running = self
+            .running_since
+            .map_or(0, |since| millis.saturating_sub(since));
+        self.elapsed_ms + running
+    }
+
+    fn pause(&mut self, millis: u64) {
+        self.elapsed_ms = self.elapsed(millis);
+        self.running_since = None;
+    }
+
+    fn resume(&mut self, millis: u64) {
+        if self.running_since.is_none() {
+            self.running_since = Some(millis);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Clock,
+    Countdown { duration_ms: u64, watch: Watch },
+    Stopwatch(Watch),
+}
+
+/// What the blocks show instead of the time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerReading {
+    /// Time left, zero once the countdown ran out.
+    Countdown(Duration),
+    /// Time measured so far.
+    Stopwatch(Duration),
+}
+
+impl TimerReading {
+    /// The two numbers shown on the blocks: minutes and seconds, or hours and
+    /// minutes from an hour on. A countdown is rounded up so it reaches
+    /// `00:00` exactly when it runs out, a stopwatch is rounded down.
+    pub fn blocks(&self) -> (u32, u32) {
+        let seconds = self.seconds();
+        if seconds < 3600 {
+            ((seconds / 60) as u32, (seconds % 60) as u32)
+        } else {
+            (
+                ((seconds / 3600).min(99)) as u32,
+                ((seconds / 60) % 60) as u32,
+            )
+        }
+    }
+
+    /// Whole seconds shown, rounded as described in [`TimerReading::blocks`].
+    fn seconds(&self) -> u64 {
+        match self {
+            TimerReading::Countdown(left) => left.as_millis().div_ceil(1000) as u64,
+            TimerReading::Stopwatch(elapsed) => elapsed.as_secs(),
+        }
+    }
+
+    /// Whether the countdown ran out.
+    pub fn is_done(&self) -> bool {
+        matches!(self, TimerReading::Countdown(left) if left.is_zero())
+    }
+}
+
+/// State of the countdown and the stopwatch, read by the clockface on every
+/// frame. It lives outside of the display task, so a restart of the task
+/// keeps a running timer. All methods take the uptime in milliseconds, see
+/// [`crate::time::TimeSource::millis`].
+pub struct Timer {
+    mode: Mutex<CriticalSectionRawMutex, Cell<Mode>>,
+}
+
+impl Timer {
+    pub const fn new() -> Self {
+        Timer {
+            mode: Mutex::new(Cell::new(Mode::Clock)),
+        }
+    }
+
+    /// Starts counting `duration` down, replacing any running timer.
+    pub fn start_countdown(&self, duration: Duration, millis: u64) {
+        let duration = duration.min(MAX_COUNTDOWN);
+        self.mode.lock(|mode| {
+            mode.set(Mode::Countdown {
+                duration_ms: duration.as_millis() as u64,
+                watch: Watch::started(millis),
+            })
+        });
+    }
+
+    /// Starts the stopwatch from zero, replacing any running timer.
+    pub fn start_stopwatch(&self, millis: u64) {
+        self.mode
+            .lock(|mode| mode.set(Mode::Stopwatch(Watch::started(millis))));
+    }
+
+    /// Pauses the countdown or the stopwatch, returns `false` if neither runs.
+    pub fn pause(&self, millis: u64) -> bool {
+        self.update(|watch| watch.pause(millis))
+    }
+
+    /// Continues after [`Timer::pause`], returns `false` if there is no timer.
+    pub fn resume(&self, millis: u64) -> bool {
+        self.update(|watch| watch.resume(millis))
+    }
+
+    /// Back to the clock.
+    pub fn stop(&self) {
+        self.mode.lock(|mode| mode.set(Mode::Clock));
+    }
+
+    /// The current reading, `None` while the clock is shown.
+    pub fn reading(&self, millis: u64) -> Option<TimerReading> {
+        match self.mode.lock(Cell::get) {
+            Mode::Clock => None,
+            Mode::Countdown { duration_ms, watch } => {
+                let left = duration_ms.saturating_sub(watch.elapsed(millis));
+                Some(TimerReading::Countdown(Duration::from_millis(left)))
+            }
+            Mode::Stopwatch(watch) => Some(TimerReading::Stopwatch(Duration::from_millis(
+                watch.elapsed(millis),
+            ))),
+        }
+    }
+
+    /// Whether the countdown or the stopwatch is paused.
+    pub fn is_paused(&self) -> bool {
+        match self.mode.lock(Cell::get) {
+            Mode::Clock => false,
+            Mode::Countdown { watch, .. } | Mode::Stopwatch(watch) => watch.running_since.is_none(),
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Watch)) -> bool {
+        self.mode.lock(|cell| {
+            let mut mode = cell.get();
+            let watch = match &mut mode {
+                Mode::Clock => return false,
+                Mode::Countdown { watch, .. } | Mode::Stopwatch(watch) => watch,
+            };
+            f(watch);
+            cell.set(mode);
+            true
+        })
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for TimerReading {
+    /// e.g. `countdown 00:04:59 left` or `stopwatch 01:02:03`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, suffix) = match self {
+            TimerReading::Countdown(_) => ("countdown", " left"),
+            TimerReading::Stopwatch(_) => ("stopwatch", ""),
+        };
+        let seconds = self.seconds();
+        write!(
+            f,
+            "{} {:02}:{:02}:{:02}{}",
+            name,
+            seconds / 3600,
+            (seconds / 60) % 60,
+            seconds % 60,
+            suffix
+        )
+    }
+}
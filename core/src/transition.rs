@@ -0,0 +1,269 @@
+use embedded_graphics::{
+    pixelcolor::Rgb888,
+    prelude::{Dimensions, DrawTarget, Point, RgbColor, Size},
+    primitives::Rectangle,
+    Pixel,
+};
+
+use crate::{COLS, ROWS};
+
+/// How long a transition between two faces takes.
+pub const TRANSITION_MS: u64 = 800;
+
+/// Effect shown when the display switches from one face to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionKind {
+    /// Hard cut, the new face is shown right away.
+    Cut,
+    /// The faces are blended into each other.
+    Fade,
+    /// The new face slides in from the left.
+    Wipe,
+    /// The new face replaces the old one pixel by pixel in random order.
+    Dissolve,
+    /// A circle closes to black on the old face and opens on the new one,
+    /// like between the levels of the game.
+    Iris,
+}
+
+impl TransitionKind {
+    pub const ALL: [TransitionKind; 5] = [
+        TransitionKind::Cut,
+        TransitionKind::Fade,
+        TransitionKind::Wipe,
+        TransitionKind::Dissolve,
+        TransitionKind::Iris,
+    ];
+
+    /// Name on the console.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransitionKind::Cut => "cut",
+            TransitionKind::Fade => "fade",
+            TransitionKind::Wipe => "wipe",
+            TransitionKind::Dissolve => "dissolve",
+            TransitionKind::Iris => "iris",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    /// Id in the serialized settings.
+    pub(crate) fn id(&self) -> u8 {
+        match self {
+            TransitionKind::Cut => 0,
+            TransitionKind::Fade => 1,
+            TransitionKind::Wipe => 2,
+            TransitionKind::Dissolve => 3,
+            TransitionKind::Iris => 4,
+        }
+    }
+
+    pub(crate) fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.id() == id)
+    }
+}
+
+/// The pixel at `x`, `y` of a transition at `progress`, 0 showing only the
+/// outgoing face `from` and 255 only the incoming face `to`.
+pub fn blend(
+    kind: TransitionKind,
+    x: i32,
+    y: i32,
+    progress: u8,
+    from: Rgb888,
+    to: Rgb888,
+) -> Rgb888 {
+    let incoming = match kind {
+        TransitionKind::Cut => true,
+        TransitionKind::Fade => return mix(from, to, progress),
+        TransitionKind::Wipe => x < (COLS as i32 * progress as i32) / 255,
+        TransitionKind::Dissolve => noise(x, y) < progress,
+        TransitionKind::Iris => {
+            // Closes on the old face in the first half and opens on the new
+            // one in the second
+            let (radius, face) = if progress < 128 {
+                (127 - progress as i32, from)
+            } else {
+                (progress as i32 - 128, to)
+            };
+            let max = IRIS_RADIUS * radius / 127;
+            let (dx, dy) = (x - COLS as i32 / 2, y - ROWS as i32 / 2);
+            return if dx * dx + dy * dy <= max * max {
+                face
+            } else {
+                Rgb888::BLACK
+            };
+        }
+    };
+    if incoming {
+        to
+    } else {
+        from
+    }
+}
+
+/// Radius of a fully open iris, the distance from the center to a corner.
+const IRIS_RADIUS: i32 = 46;
+
+fn mix(from: Rgb888, to: Rgb888, weight: u8) -> Rgb888 {
+    let channel = |from: u8, to: u8| {
+        ((from as u32 * (255 - weight as u32) + to as u32 * weight as u32) / 255) as u8
+    };
+    Rgb888::new(
+        channel(from.r(), to.r()),
+        channel(from.g(), to.g()),
+        channel(from.b(), to.b()),
+    )
+}
+
+/// A fixed pseudo random value per pixel below 255, the order of the
+/// dissolve.
+fn noise(x: i32, y: i32) -> u8 {
+    let mut hash = (x as u32)
+        .wrapping_mul(374_761_393)
+        .wrapping_add((y as u32).wrapping_mul(668_265_263));
+    hash = (hash ^ (hash >> 13)).wrapping_mul(1_274_126_177);
+    ((hash >> 24) % 255) as u8
+}
+
+/// The outgoing face recorded during a transition, allocated once by the
+/// owner of the faces, e.g. in a static. The frame buffers of the panel
+/// can't serve as the outgoing face: they hold bit planes for the DMA
+/// without reading pixels back, and the front buffer is being sent to the
+/// panel while the back buffer is drawn.
+pub type Snapshot = [Rgb888; ROWS * COLS];
+
+/// A black [`Snapshot`] to initialize one with.
+pub const EMPTY_SNAPSHOT: Snapshot = [Rgb888::BLACK; ROWS * COLS];
+
+/// A running transition. The outgoing face is drawn first and recorded into
+/// a [`Snapshot`], then the incoming face is drawn on top of it through
+/// [`Transition::blend`] into the same frame buffer. The snapshot takes
+/// 12 KB next to the two frame buffers.
+#[derive(Debug, Clone, Copy)]
+pub struct Transition {
+    kind: TransitionKind,
+    started_ms: u64,
+}
+
+impl Transition {
+    pub fn new(kind: TransitionKind, started_ms: u64) -> Self {
+        Transition { kind, started_ms }
+    }
+
+    /// Progress at `millis` from 0 to 255, `None` once done.
+    pub fn progress(&self, millis: u64) -> Option<u8> {
+        if self.kind == TransitionKind::Cut {
+            return None;
+        }
+        let elapsed = millis.saturating_sub(self.started_ms);
+        (elapsed < TRANSITION_MS).then(|| (elapsed * 255 / TRANSITION_MS) as u8)
+    }
+
+    /// Draw target for the outgoing face, passes everything on to `fb` and
+    /// records it into `snapshot`.
+    pub fn record<'a, D>(
+        &self,
+        fb: &'a mut D,
+        snapshot: &'a mut Snapshot,
+    ) -> impl DrawTarget<Color = Rgb888, Error = D::Error> + Send + 'a
+    where
+        D: DrawTarget<Color = Rgb888> + Send,
+    {
+        Recorder {
+            fb,
+            frame: snapshot,
+        }
+    }
+
+    /// Draw target for the incoming face, blends it with the outgoing face
+    /// recorded in `snapshot` at `progress`.
+    pub fn blend<'a, D>(
+        &self,
+        fb: &'a mut D,
+        snapshot: &'a Snapshot,
+        progress: u8,
+    ) -> impl DrawTarget<Color = Rgb888, Error = D::Error> + Send + 'a
+    where
+        D: DrawTarget<Color = Rgb888> + Send,
+    {
+        Blender {
+            fb,
+            from: snapshot,
+            kind: self.kind,
+            progress,
+        }
+    }
+}
+
+fn index(point: Point) -> Option<usize> {
+    let in_bounds = (0..COLS as i32).contains(&point.x) && (0..ROWS as i32).contains(&point.y);
+    in_bounds.then(|| point.y as usize * COLS + point.x as usize)
+}
+
+fn bounding_box() -> Rectangle {
+    Rectangle::new(Point::zero(), Size::new(COLS as u32, ROWS as u32))
+}
+
+struct Recorder<'a, D> {
+    fb: &'a mut D,
+    frame: &'a mut [Rgb888],
+}
+
+impl<D: DrawTarget<Color = Rgb888>> Dimensions for Recorder<'_, D> {
+    fn bounding_box(&self) -> Rectangle {
+        bounding_box()
+    }
+}
+
+impl<D: DrawTarget<Color = Rgb888>> DrawTarget for Recorder<'_, D> {
+    type Color = Rgb888;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let frame = &mut *self.frame;
+        self.fb
+            .draw_iter(pixels.into_iter().inspect(|Pixel(point, color)| {
+                if let Some(index) = index(*point) {
+                    frame[index] = *color;
+                }
+            }))
+    }
+}
+
+struct Blender<'a, D> {
+    fb: &'a mut D,
+    from: &'a [Rgb888],
+    kind: TransitionKind,
+    progress: u8,
+}
+
+impl<D: DrawTarget<Color = Rgb888>> Dimensions for Blender<'_, D> {
+    fn bounding_box(&self) -> Rectangle {
+        bounding_box()
+    }
+}
+
+impl<D: DrawTarget<Color = Rgb888>> DrawTarget for Blender<'_, D> {
+    type Color = Rgb888;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let (from, kind, progress) = (self.from, self.kind, self.progress);
+        self.fb
+            .draw_iter(pixels.into_iter().filter_map(|Pixel(point, to)| {
+                let from = from[index(point)?];
+                let color = blend(kind, point.x, point.y, progress, from, to);
+                Some(Pixel(point, color))
+            }))
+    }
+}
@@ -0,0 +1,185 @@
+use core::{cell::Cell, future::Future};
+
+use embassy_sync::{
+    blocking_mutex::{raw::CriticalSectionRawMutex, Mutex},
+    signal::Signal,
+};
+
+use crate::{http::HttpError, sun::Location, time::TimeSource};
+
+/// Time between two fetches of the weather.
+pub const WEATHER_INTERVAL_MINUTES: u64 = 15;
+/// A temperature older than this isn't shown any more, e.g. while the
+/// weather service can't be reached.
+pub const TEMPERATURE_MAX_AGE_MINUTES: u64 = 60;
+
+/// The weather outside, as far as the scene shows it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeatherCondition {
+    /// The sun shines with rays, at night the sky stays clear.
+    Clear,
+    /// Clouds only, the scenery has them anyway.
+    Cloudy,
+    Rain,
+    Snow,
+    Fog,
+}
+
+impl WeatherCondition {
+    pub const ALL: [WeatherCondition; 5] = [
+        WeatherCondition::Clear,
+        WeatherCondition::Cloudy,
+        WeatherCondition::Rain,
+        WeatherCondition::Snow,
+        WeatherCondition::Fog,
+    ];
+
+    /// Name on the console.
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeatherCondition::Clear => "clear",
+            WeatherCondition::Cloudy => "cloudy",
+            WeatherCondition::Rain => "rain",
+            WeatherCondition::Snow => "snow",
+            WeatherCondition::Fog => "fog",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|condition| condition.name() == name)
+    }
+}
+
+/// The weather at a location as told by a [`WeatherProvider`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Conditions {
+    pub condition: WeatherCondition,
+    /// Air temperature in degrees Celsius.
+    pub temperature: f32,
+}
+
+/// The last conditions fetched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Report {
+    pub conditions: Conditions,
+    /// Uptime of the fetch in milliseconds, see [`TimeSource::millis`].
+    pub fetched_ms: u64,
+}
+
+impl Report {
+    /// Whole minutes since the fetch at `millis`.
+    pub fn age_minutes(&self, millis: u64) -> u64 {
+        millis.saturating_sub(self.fetched_ms) / 60_000
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeatherError {
+    Http(HttpError),
+    /// The response lacks the current conditions.
+    InvalidData,
+}
+
+impl From<HttpError> for WeatherError {
+    fn from(error: HttpError) -> Self {
+        WeatherError::Http(error)
+    }
+}
+
+/// A weather service, e.g. [`crate::open_meteo::OpenMeteo`].
+pub trait WeatherProvider {
+    /// Name on the console.
+    fn name(&self) -> &'static str;
+
+    /// Asks for the current conditions at `location`.
+    fn fetch(
+        &mut self,
+        location: Location,
+    ) -> impl Future<Output = Result<Conditions, WeatherError>>;
+}
+
+/// The current weather condition shown by the clockfaces. Any source may set
+/// it, e.g. the console or a weather service, the faces read it on every
+/// frame. It starts unknown, which shows no weather at all. The last report
+/// of a weather service is kept when a later fetch fails. The temperature
+/// comes with the report or from any other source.
+pub struct Weather {
+    condition: Mutex<CriticalSectionRawMutex, Cell<Option<WeatherCondition>>>,
+    report: Mutex<CriticalSectionRawMutex, Cell<Option<Report>>>,
+    /// Degrees Celsius and the uptime they were measured at in milliseconds.
+    temperature: Mutex<CriticalSectionRawMutex, Cell<Option<(f32, u64)>>>,
+    /// Signaled to fetch the weather right away.
+    pub requested: Signal<CriticalSectionRawMutex, ()>,
+}
+
+impl Weather {
+    pub const fn new() -> Self {
+        Weather {
+            condition: Mutex::new(Cell::new(None)),
+            report: Mutex::new(Cell::new(None)),
+            temperature: Mutex::new(Cell::new(None)),
+            requested: Signal::new(),
+        }
+    }
+
+    /// Replaces the condition, `None` clears the weather from the scene.
+    pub fn set_condition(&self, condition: Option<WeatherCondition>) {
+        self.condition.lock(|cell| cell.set(condition));
+    }
+
+    pub fn condition(&self) -> Option<WeatherCondition> {
+        self.condition.lock(Cell::get)
+    }
+
+    /// Keeps `report` and shows its condition and temperature.
+    pub fn set_report(&self, report: Report) {
+        self.report.lock(|cell| cell.set(Some(report)));
+        self.set_condition(Some(report.conditions.condition));
+        self.set_temperature(report.conditions.temperature, report.fetched_ms);
+    }
+
+    /// The last report fetched, however old.
+    pub fn report(&self) -> Option<Report> {
+        self.report.lock(Cell::get)
+    }
+
+    /// Sets the temperature to `celsius` degrees, measured at the uptime
+    /// `millis`.
+    pub fn set_temperature(&self, celsius: f32, millis: u64) {
+        self.temperature
+            .lock(|cell| cell.set(Some((celsius, millis))));
+    }
+
+    /// The temperature in degrees Celsius at `millis`, `None` if unknown or
+    /// older than [`TEMPERATURE_MAX_AGE_MINUTES`].
+    pub fn temperature(&self, millis: u64) -> Option<f32> {
+        let (celsius, measured_ms) = self.temperature.lock(Cell::get)?;
+        (millis.saturating_sub(measured_ms) <= TEMPERATURE_MAX_AGE_MINUTES * 60_000)
+            .then_some(celsius)
+    }
+
+    /// Fetches the conditions at `location` from `provider` and keeps them
+    /// as the report of now. On an error the last report stays.
+    pub async fn update<P: WeatherProvider>(
+        &self,
+        provider: &mut P,
+        location: Location,
+        time: &impl TimeSource,
+    ) -> Result<Report, WeatherError> {
+        let conditions = provider.fetch(location).await?;
+        let report = Report {
+            conditions,
+            fetched_ms: time.millis(),
+        };
+        self.set_report(report);
+        Ok(report)
+    }
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        Self::new()
+    }
+}
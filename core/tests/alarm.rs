@@ -0,0 +1,299 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::{Europe, Tz};
+use mario_clock_core::{
+    alarm::{Alarm, AlarmClock, Weekdays, SNOOZE},
+    settings::Settings,
+};
+
+fn alarm(hour: u32, minute: u32, weekdays: &str, recurring: bool) -> Alarm {
+    Alarm {
+        time: NaiveTime::from_hms_opt(hour, minute, 0).unwrap(),
+        weekdays: weekdays.parse().unwrap(),
+        recurring,
+        enabled: true,
+        label: "Wake up".try_into().unwrap(),
+    }
+}
+
+fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+}
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+/// The zone-aware time the device passes in, see `Clock::get_time_in_zone`.
+fn in_zurich(time: DateTime<Utc>) -> DateTime<Tz> {
+    time.with_timezone(&Europe::Zurich)
+}
+
+/// Checks every minute from `from` to `to` like the alarm task, dismissing
+/// each alarm right away. Returns when the alarms rang.
+fn run(
+    clock: &AlarmClock,
+    settings: &Settings,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Vec<DateTime<Utc>> {
+    let mut rang = Vec::new();
+    let mut now = from;
+    while now <= to {
+        if clock.check(settings, in_zurich(now)).is_some() {
+            rang.push(clock.dismiss().unwrap().since);
+        }
+        now += TimeDelta::minutes(1);
+    }
+    rang
+}
+
+#[test]
+fn weekdays_parse_and_display() {
+    let weekdays: Weekdays = "mon-fri,sun".parse().unwrap();
+    assert_eq!(weekdays.bits(), 0b101_1111);
+    assert_eq!(weekdays.to_string(), "mon-fri,sun");
+    assert_eq!(
+        "sat,sun".parse::<Weekdays>().unwrap().to_string(),
+        "sat,sun"
+    );
+    assert_eq!("mon-sun".parse::<Weekdays>(), Ok(Weekdays::EVERY_DAY));
+    assert_eq!(Weekdays::EVERY_DAY.to_string(), "daily");
+    assert!("fri-mon".parse::<Weekdays>().is_err());
+    assert!("monday".parse::<Weekdays>().is_err());
+}
+
+#[test]
+fn alarm_bytes_round_trip() {
+    let mut alarm = alarm(6, 45, "tue,thu", false);
+    alarm.enabled = false;
+    let mut buf = [0u8; mario_clock_core::alarm::ALARM_SIZE];
+    let len = alarm.to_bytes(&mut buf);
+    assert_eq!(Alarm::from_bytes(&buf[..len]), Some(alarm));
+    assert_eq!(Alarm::from_bytes(&[24, 0, 0x7F, 0]), None);
+    assert_eq!(Alarm::from_bytes(&[7, 0]), None);
+}
+
+#[test]
+fn wall_clock_time_kept_across_dst() {
+    let alarm = alarm(7, 0, "daily", true);
+    // 07:00 CET before the switch to summer time, 07:00 CEST after it
+    assert_eq!(
+        alarm.on_date(date(2025, 3, 29), Europe::Zurich),
+        Some(utc(2025, 3, 29, 6, 0))
+    );
+    assert_eq!(
+        alarm.on_date(date(2025, 3, 31), Europe::Zurich),
+        Some(utc(2025, 3, 31, 5, 0))
+    );
+    assert_eq!(
+        alarm.on_date(date(2025, 10, 27), Europe::Zurich),
+        Some(utc(2025, 10, 27, 6, 0))
+    );
+}
+
+// 02:30 doesn't exist on the day the clocks go from 02:00 to 03:00, the alarm
+// rings at 03:30 as if the clock hadn't switched yet.
+#[test]
+fn skipped_time_rings_after_the_switch() {
+    let settings = Settings::new(Europe::Zurich);
+    settings.add_alarm(alarm(2, 30, "daily", true)).unwrap();
+    let clock = AlarmClock::new();
+    let rang = run(
+        &clock,
+        &settings,
+        utc(2025, 3, 29, 23, 0),
+        utc(2025, 3, 30, 3, 0),
+    );
+    assert_eq!(rang, [utc(2025, 3, 30, 1, 30)]);
+    assert_eq!(
+        in_zurich(rang[0]).time(),
+        NaiveTime::from_hms_opt(3, 30, 0).unwrap()
+    );
+}
+
+// 02:30 happens twice on the day the clocks go from 03:00 back to 02:00, the
+// alarm rings the first time only.
+#[test]
+fn repeated_time_rings_once() {
+    let settings = Settings::new(Europe::Zurich);
+    settings.add_alarm(alarm(2, 30, "daily", true)).unwrap();
+    let clock = AlarmClock::new();
+    let rang = run(
+        &clock,
+        &settings,
+        utc(2025, 10, 25, 23, 0),
+        utc(2025, 10, 26, 3, 0),
+    );
+    assert_eq!(rang, [utc(2025, 10, 26, 0, 30)]);
+}
+
+#[test]
+fn rings_on_the_selected_weekdays() {
+    let settings = Settings::new(Europe::Zurich);
+    settings.add_alarm(alarm(7, 30, "mon-fri", true)).unwrap();
+    let clock = AlarmClock::new();
+    // Friday 2025-06-13 to Tuesday 2025-06-17
+    let rang = run(
+        &clock,
+        &settings,
+        utc(2025, 6, 13, 0, 0),
+        utc(2025, 6, 17, 23, 0),
+    );
+    assert_eq!(
+        rang,
+        [
+            utc(2025, 6, 13, 5, 30),
+            utc(2025, 6, 16, 5, 30),
+            utc(2025, 6, 17, 5, 30)
+        ]
+    );
+}
+
+#[test]
+fn one_shot_alarm_is_disabled_after_ringing() {
+    let settings = Settings::new(Europe::Zurich);
+    settings.add_alarm(alarm(7, 30, "daily", false)).unwrap();
+    settings.changed.reset();
+    let clock = AlarmClock::new();
+    let rang = run(
+        &clock,
+        &settings,
+        utc(2025, 6, 13, 0, 0),
+        utc(2025, 6, 15, 0, 0),
+    );
+    assert_eq!(rang, [utc(2025, 6, 13, 5, 30)]);
+    assert!(!settings.alarms()[0].enabled);
+    // Persisted like any other change
+    assert!(settings.changed.signaled());
+}
+
+#[test]
+fn one_shot_alarms_ringing_together_are_all_disabled() {
+    let settings = Settings::new(Europe::Zurich);
+    settings.add_alarm(alarm(7, 30, "daily", false)).unwrap();
+    let mut other = alarm(7, 30, "daily", false);
+    other.label = "Coffee".try_into().unwrap();
+    settings.add_alarm(other).unwrap();
+    let clock = AlarmClock::new();
+    run(
+        &clock,
+        &settings,
+        utc(2025, 6, 13, 0, 0),
+        utc(2025, 6, 14, 0, 0),
+    );
+    assert!(settings.alarms().iter().all(|alarm| !alarm.enabled));
+}
+
+// A rung alarm is found by what it is, the alarm removed on the console
+// meanwhile doesn't make another one disabled.
+#[test]
+fn rung_alarms_are_disabled_by_value() {
+    let settings = Settings::new(Europe::Zurich);
+    settings.add_alarm(alarm(6, 0, "daily", true)).unwrap();
+    settings.add_alarm(alarm(7, 30, "daily", false)).unwrap();
+    settings.add_alarm(alarm(8, 0, "daily", true)).unwrap();
+    let rung = settings.alarms()[1].clone();
+    settings.remove_alarm(0);
+    settings.disable_alarms(&[rung]);
+    let enabled: Vec<bool> = settings
+        .alarms()
+        .iter()
+        .map(|alarm| alarm.enabled)
+        .collect();
+    assert_eq!(enabled, [false, true]);
+}
+
+#[test]
+fn disabled_alarm_does_not_ring() {
+    let settings = Settings::new(Europe::Zurich);
+    settings.add_alarm(alarm(7, 30, "daily", true)).unwrap();
+    settings.update_alarm(0, |alarm| alarm.enabled = false);
+    let clock = AlarmClock::new();
+    let rang = run(
+        &clock,
+        &settings,
+        utc(2025, 6, 13, 0, 0),
+        utc(2025, 6, 14, 0, 0),
+    );
+    assert!(rang.is_empty());
+}
+
+#[test]
+fn missed_alarms_are_not_rung_late() {
+    let settings = Settings::new(Europe::Zurich);
+    settings.add_alarm(alarm(7, 30, "daily", true)).unwrap();
+    let clock = AlarmClock::new();
+
+    // Due before the first check, e.g. before the clock was set
+    assert_eq!(
+        clock.check(&settings, in_zurich(utc(2025, 6, 13, 5, 31))),
+        None
+    );
+
+    // Stepped far past the alarm by a sync
+    assert_eq!(
+        clock.check(&settings, in_zurich(utc(2025, 6, 14, 5, 0))),
+        None
+    );
+    assert_eq!(
+        clock.check(&settings, in_zurich(utc(2025, 6, 14, 5, 40))),
+        None
+    );
+
+    // A few minutes late still rings
+    assert_eq!(
+        clock.check(&settings, in_zurich(utc(2025, 6, 15, 5, 28))),
+        None
+    );
+    let ringing = clock.check(&settings, in_zurich(utc(2025, 6, 15, 5, 33)));
+    assert_eq!(ringing.unwrap().since, utc(2025, 6, 15, 5, 30));
+}
+
+#[test]
+fn rings_until_dismissed() {
+    let settings = Settings::new(Europe::Zurich);
+    settings.add_alarm(alarm(7, 30, "daily", true)).unwrap();
+    let clock = AlarmClock::new();
+    clock.check(&settings, in_zurich(utc(2025, 6, 13, 5, 29)));
+    clock.check(&settings, in_zurich(utc(2025, 6, 13, 5, 30)));
+    let ringing = clock.check(&settings, in_zurich(utc(2025, 6, 13, 5, 45)));
+    assert_eq!(ringing.unwrap().alarm.label, "Wake up");
+    assert!(clock.ringing().is_some());
+
+    assert!(clock.dismiss().is_some());
+    assert_eq!(clock.ringing(), None);
+    assert_eq!(
+        clock.check(&settings, in_zurich(utc(2025, 6, 13, 5, 46))),
+        None
+    );
+    assert_eq!(clock.dismiss(), None);
+}
+
+#[test]
+fn snoozed_alarm_rings_again() {
+    let settings = Settings::new(Europe::Zurich);
+    settings.add_alarm(alarm(7, 30, "daily", true)).unwrap();
+    let clock = AlarmClock::new();
+    clock.check(&settings, in_zurich(utc(2025, 6, 13, 5, 29)));
+    clock.check(&settings, in_zurich(utc(2025, 6, 13, 5, 30)));
+
+    let snoozed_at = utc(2025, 6, 13, 5, 31);
+    assert_eq!(clock.snooze(snoozed_at), Some(snoozed_at + SNOOZE));
+    assert_eq!(clock.ringing(), None);
+    assert_eq!(
+        clock.check(&settings, in_zurich(utc(2025, 6, 13, 5, 39))),
+        None
+    );
+
+    let ringing = clock.check(&settings, in_zurich(utc(2025, 6, 13, 5, 40)));
+    assert_eq!(ringing.unwrap().since, utc(2025, 6, 13, 5, 30));
+
+    // Dismissing a snoozed alarm stops it for good
+    clock.snooze(utc(2025, 6, 13, 5, 41));
+    assert!(clock.dismiss().is_some());
+    assert_eq!(
+        clock.check(&settings, in_zurich(utc(2025, 6, 13, 6, 0))),
+        None
+    );
+    assert_eq!(clock.snooze(utc(2025, 6, 13, 6, 0)), None);
+}
@@ -0,0 +1,107 @@
+//! Drives Mario and the blocks frame by frame with a [`ManualTime`] and checks
+//! the jump: start at second 0, hit the blocks, bounce and land again.
+
+use core::{convert::Infallible, time::Duration};
+
+use chrono::{TimeZone, Utc};
+use embassy_futures::block_on;
+use embedded_graphics::{
+    pixelcolor::Rgb888,
+    prelude::{DrawTarget, OriginDimensions, Size},
+    Pixel,
+};
+use mario_clock_core::{
+    engine::{EventChannel, Sprite},
+    mario::gfx::{block::Block, mario::Mario},
+    time::{ManualTime, TimeSource},
+};
+
+/// Discards everything, only the sprite positions are of interest.
+struct NullDisplay;
+
+impl OriginDimensions for NullDisplay {
+    fn size(&self) -> Size {
+        Size::new(64, 64)
+    }
+}
+
+impl DrawTarget for NullDisplay {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, _pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        Ok(())
+    }
+}
+
+struct Scene<'a> {
+    time: &'a ManualTime,
+    mario: Mario<&'a ManualTime>,
+    block: Block<&'a ManualTime>,
+}
+
+impl<'a> Scene<'a> {
+    fn new(time: &'a ManualTime) -> Self {
+        let channel = Box::leak(Box::new(EventChannel::new()));
+
+        // Same layout as the clockface, with only the hour block
+        let mut mario = Mario::new(23, 40, time);
+        mario.subscribe(channel.publisher().unwrap(), channel.subscriber().unwrap());
+        let mut block = Block::new(13, 8, time);
+        block.subscribe(channel.publisher().unwrap(), channel.subscriber().unwrap());
+
+        Scene { time, mario, block }
+    }
+
+    /// Renders one frame and returns the `y` of Mario and the block.
+    fn frame(&mut self) -> (i8, i8) {
+        let jump = self.time.now().timestamp() % 60 == 0;
+        block_on(self.mario.update(&mut NullDisplay, jump));
+        block_on(self.block.update(&mut NullDisplay, "12"));
+        self.time.advance(Duration::from_millis(50));
+        (self.mario.y(), self.block.y())
+    }
+}
+
+#[test]
+fn jump_hits_block_and_lands() {
+    // One second before the full minute, one second after boot
+    let time = ManualTime::new(Utc.with_ymd_and_hms(2025, 1, 1, 12, 59, 59).unwrap());
+    let mut scene = Scene::new(&time);
+
+    let frames: Vec<_> = (0..40).map(|_| scene.frame()).collect();
+
+    // Idle until the minute changes at frame 20
+    assert!(frames[..20].iter().all(|&f| f == (40, 8)));
+    // Jump starts, Mario moves 3px up every 50ms
+    assert_eq!(frames[20], (40, 8));
+    assert_eq!(frames[21].0, 37);
+    assert_eq!(frames[24].0, 28);
+    // Mario hits the block on frame 25 and falls back down
+    assert_eq!(frames[25].0, 25);
+    assert_eq!(frames[26].0, 28);
+    // The block bounces 2px every 60ms, up to 4px above its position
+    assert_eq!(frames[27].1, 6);
+    assert_eq!(frames[29].1, 4);
+    assert_eq!(frames[31].1, 6);
+    assert_eq!(frames[33].1, 8);
+    // Mario is back on the ground while the block is still at the top
+    assert_eq!(frames[30], (40, 4));
+    assert!(frames[33..].iter().all(|&f| f == (40, 8)));
+}
+
+#[test]
+fn jump_is_debounced_after_boot() {
+    // The minute changes right at boot, before the debounce time has passed
+    let time = ManualTime::new(Utc.with_ymd_and_hms(2025, 1, 1, 13, 0, 0).unwrap());
+    let mut scene = Scene::new(&time);
+
+    let frames: Vec<_> = (0..20).map(|_| scene.frame()).collect();
+
+    // No jump within the first 500ms, then it starts as the second is still 0
+    assert!(frames[..11].iter().all(|&f| f == (40, 8)));
+    assert_eq!(frames[12].0, 37);
+}
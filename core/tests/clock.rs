@@ -0,0 +1,280 @@
+use core::time::Duration;
+
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+use mario_clock_core::clock::{
+    ntp_fraction_to_micros, offset_from_time, rtc_datetime_to_timestamp,
+    rtc_registers_to_timestamp, time_from_offset, timestamp_to_rtc_datetime, until_next_second,
+    Correction, RtcDateTime, RtcError, SlewedOffset, SLEW_THRESHOLD,
+};
+
+fn rtc(year: u8, month: u8, day: u8, hours: u8, minutes: u8, seconds: u8) -> RtcDateTime {
+    RtcDateTime {
+        year,
+        month,
+        day,
+        weekday: 0,
+        hours,
+        minutes,
+        seconds,
+    }
+}
+
+#[test]
+fn rtc_datetime_to_timestamp_converts_valid_dates() {
+    assert_eq!(
+        rtc_datetime_to_timestamp(&rtc(25, 1, 1, 0, 0, 0)),
+        Some(1_735_689_600)
+    );
+    assert_eq!(
+        rtc_datetime_to_timestamp(&rtc(25, 6, 15, 12, 34, 56)),
+        Some(1_749_990_896)
+    );
+    // Leap day
+    assert_eq!(
+        rtc_datetime_to_timestamp(&rtc(28, 2, 29, 23, 59, 59)),
+        Some(1_835_481_599)
+    );
+}
+
+#[test]
+fn rtc_years_wrap_into_the_next_century() {
+    assert_eq!(
+        rtc_datetime_to_timestamp(&rtc(99, 12, 31, 23, 59, 59)),
+        Some(4_102_444_799)
+    );
+    // 2100 isn't a leap year
+    assert_eq!(
+        rtc_datetime_to_timestamp(&rtc(0, 1, 1, 0, 0, 0)),
+        Some(4_102_444_800)
+    );
+    assert_eq!(rtc_datetime_to_timestamp(&rtc(0, 2, 29, 0, 0, 0)), None);
+    // Past the u32 epoch in 2106
+    assert_eq!(
+        rtc_datetime_to_timestamp(&rtc(24, 12, 31, 23, 59, 59)),
+        Some(4_891_363_199)
+    );
+}
+
+#[test]
+fn rtc_datetime_to_timestamp_rejects_invalid_fields() {
+    assert_eq!(rtc_datetime_to_timestamp(&rtc(25, 0, 1, 0, 0, 0)), None);
+    assert_eq!(rtc_datetime_to_timestamp(&rtc(25, 13, 1, 0, 0, 0)), None);
+    assert_eq!(rtc_datetime_to_timestamp(&rtc(25, 2, 29, 0, 0, 0)), None);
+    assert_eq!(rtc_datetime_to_timestamp(&rtc(25, 1, 1, 24, 0, 0)), None);
+    assert_eq!(rtc_datetime_to_timestamp(&rtc(25, 1, 1, 0, 60, 0)), None);
+    assert_eq!(rtc_datetime_to_timestamp(&rtc(25, 1, 1, 0, 0, 60)), None);
+    assert_eq!(rtc_datetime_to_timestamp(&rtc(100, 1, 1, 0, 0, 0)), None);
+}
+
+#[test]
+fn timestamp_to_rtc_datetime_round_trips() {
+    let datetime = timestamp_to_rtc_datetime(1_749_990_896).unwrap();
+    assert_eq!(
+        datetime,
+        RtcDateTime {
+            weekday: 6, // Sunday, counted from Monday
+            ..rtc(25, 6, 15, 12, 34, 56)
+        }
+    );
+    assert_eq!(rtc_datetime_to_timestamp(&datetime), Some(1_749_990_896));
+
+    let datetime = timestamp_to_rtc_datetime(4_891_363_199).unwrap();
+    assert_eq!(datetime.year, 24);
+    assert_eq!(rtc_datetime_to_timestamp(&datetime), Some(4_891_363_199));
+}
+
+#[test]
+fn timestamp_to_rtc_datetime_rejects_dates_out_of_range() {
+    assert_eq!(timestamp_to_rtc_datetime(1_735_689_599), None);
+    assert!(timestamp_to_rtc_datetime(1_735_689_600).is_some());
+    assert!(timestamp_to_rtc_datetime(4_891_363_199).is_some());
+    assert_eq!(timestamp_to_rtc_datetime(4_891_363_200), None);
+    assert_eq!(timestamp_to_rtc_datetime(i64::MIN), None);
+    assert_eq!(timestamp_to_rtc_datetime(i64::MAX), None);
+}
+
+#[test]
+fn time_from_offset_adds_uptime() {
+    assert_eq!(
+        time_from_offset(1_749_990_896_000_000, 4_000_000),
+        Utc.with_ymd_and_hms(2025, 6, 15, 12, 35, 0).unwrap()
+    );
+    assert_eq!(
+        time_from_offset(0, 0),
+        Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 0).unwrap()
+    );
+    // Beyond 2106, where a u32 of seconds ends
+    assert_eq!(
+        time_from_offset(4_294_967_296_000_000, 1_000_000),
+        Utc.with_ymd_and_hms(2106, 2, 7, 6, 28, 17).unwrap()
+    );
+}
+
+#[test]
+fn time_from_offset_keeps_microseconds() {
+    let time = time_from_offset(1_749_990_896_250_000, 500_001);
+    assert_eq!(time.timestamp(), 1_749_990_896);
+    assert_eq!(time.timestamp_subsec_micros(), 750_001);
+}
+
+#[test]
+fn time_from_offset_saturates() {
+    assert_eq!(time_from_offset(i64::MAX, 1), DateTime::<Utc>::MAX_UTC);
+    assert_eq!(
+        time_from_offset(i64::MAX, u64::MAX),
+        DateTime::<Utc>::MAX_UTC
+    );
+    assert_eq!(time_from_offset(i64::MIN, 0), DateTime::<Utc>::MIN_UTC);
+    // Negative offsets are before 1970
+    assert_eq!(
+        time_from_offset(-1_000_000, 500_000),
+        DateTime::from_timestamp_micros(-500_000).unwrap()
+    );
+}
+
+#[test]
+fn offset_from_time_inverts_time_from_offset() {
+    let time = Utc.with_ymd_and_hms(2125, 6, 15, 12, 0, 0).unwrap() + TimeDelta::microseconds(7);
+    let offset = offset_from_time(time, 123_456_789);
+    assert_eq!(time_from_offset(offset, 123_456_789), time);
+    assert_eq!(offset_from_time(DateTime::UNIX_EPOCH, 0), 0);
+    assert_eq!(offset_from_time(DateTime::UNIX_EPOCH, u64::MAX), -i64::MAX);
+}
+
+#[test]
+fn ntp_fraction_converts_to_micros() {
+    assert_eq!(ntp_fraction_to_micros(0), 0);
+    assert_eq!(ntp_fraction_to_micros(1 << 31), 500_000);
+    assert_eq!(ntp_fraction_to_micros(u32::MAX), 999_999);
+}
+
+#[test]
+fn until_next_second_rounds_up() {
+    let time = Utc.with_ymd_and_hms(2025, 6, 15, 12, 34, 56).unwrap();
+    assert_eq!(until_next_second(time), TimeDelta::seconds(1));
+    assert_eq!(
+        until_next_second(time + TimeDelta::milliseconds(250)),
+        TimeDelta::milliseconds(750)
+    );
+}
+
+#[test]
+fn rtc_registers_are_decoded() {
+    // 2025-06-15 12:34:56, a Sunday, BCD encoded
+    let registers = [0x56, 0x34, 0x12, 0x15, 0x00, 0x06, 0x25];
+    assert_eq!(
+        RtcDateTime::from_registers(&registers),
+        Ok(rtc(25, 6, 15, 12, 34, 56))
+    );
+    assert_eq!(rtc_registers_to_timestamp(&registers), Ok(1_749_990_896));
+
+    // Unused bits and the century flag are ignored
+    let registers = [0x56, 0xB4, 0xD2, 0xD5, 0xF8, 0x86, 0x25];
+    assert_eq!(rtc_registers_to_timestamp(&registers), Ok(1_749_990_896));
+}
+
+#[test]
+fn rtc_power_loss_is_detected() {
+    let registers = [0x80 | 0x56, 0x34, 0x12, 0x15, 0x00, 0x06, 0x25];
+    assert_eq!(
+        rtc_registers_to_timestamp(&registers),
+        Err(RtcError::VoltageLow)
+    );
+}
+
+#[test]
+fn invalid_rtc_registers_are_rejected() {
+    let valid = [0x56, 0x34, 0x12, 0x15, 0x00, 0x06, 0x25];
+    let invalid = |index: usize, value: u8| {
+        let mut registers = valid;
+        registers[index] = value;
+        rtc_registers_to_timestamp(&registers)
+    };
+
+    // Not BCD
+    assert_eq!(invalid(0, 0x5A), Err(RtcError::InvalidData));
+    assert_eq!(invalid(6, 0x2F), Err(RtcError::InvalidData));
+    // Out of range
+    assert_eq!(invalid(0, 0x60), Err(RtcError::InvalidData));
+    assert_eq!(invalid(2, 0x24), Err(RtcError::InvalidData));
+    assert_eq!(invalid(3, 0x31), Err(RtcError::InvalidData)); // June 31st
+    assert_eq!(invalid(4, 0x07), Err(RtcError::InvalidData));
+    assert_eq!(invalid(5, 0x13), Err(RtcError::InvalidData));
+    assert_eq!(invalid(6, 0xA0), Err(RtcError::InvalidData));
+    // Erased registers
+    assert_eq!(
+        rtc_registers_to_timestamp(&[0; 7]),
+        Err(RtcError::InvalidData)
+    );
+}
+
+#[test]
+fn rtc_registers_round_trip() {
+    let datetime = timestamp_to_rtc_datetime(4_891_363_199).unwrap();
+    let registers = datetime.to_registers();
+    assert_eq!(registers, [0x59, 0x59, 0x23, 0x31, 0x06, 0x12, 0x24]);
+    assert_eq!(RtcDateTime::from_registers(&registers), Ok(datetime));
+}
+
+const WINDOW: Duration = Duration::from_secs(600);
+
+#[test]
+fn small_corrections_are_slewed() {
+    let mut offset = SlewedOffset::new(1_000_000);
+    assert_eq!(
+        offset.correct(1_600_000, 5_000_000, WINDOW, SLEW_THRESHOLD),
+        Correction::Slewed(TimeDelta::milliseconds(600))
+    );
+    assert_eq!(offset.target(), 1_600_000);
+    assert_eq!(offset.at(5_000_000), 1_000_000);
+    assert_eq!(offset.at(305_000_000), 1_300_000);
+    assert!(offset.is_slewing(305_000_000));
+    assert_eq!(offset.at(605_000_000), 1_600_000);
+    assert!(!offset.is_slewing(605_000_000));
+
+    // A correction while slewing starts from where the slew got to
+    assert_eq!(
+        offset.correct(1_000_000, 305_000_000, WINDOW, SLEW_THRESHOLD),
+        Correction::Slewed(TimeDelta::milliseconds(-300))
+    );
+    assert_eq!(offset.at(305_000_000), 1_300_000);
+    assert_eq!(offset.at(905_000_000), 1_000_000);
+}
+
+#[test]
+fn slewed_time_is_monotonic() {
+    let mut offset = SlewedOffset::new(0);
+    offset.correct(-2_000_000, 0, Duration::from_secs(3), SLEW_THRESHOLD);
+    let mut last = time_from_offset(offset.at(0), 0);
+    for uptime in (0..5_000_000).step_by(10_000) {
+        let now = time_from_offset(offset.at(uptime), uptime);
+        assert!(now >= last, "{now} before {last}");
+        last = now;
+    }
+}
+
+#[test]
+fn large_corrections_step() {
+    let mut offset = SlewedOffset::new(0);
+    assert_eq!(
+        offset.correct(-3_000_000, 1_000_000, WINDOW, SLEW_THRESHOLD),
+        Correction::Stepped(TimeDelta::seconds(-3))
+    );
+    assert_eq!(offset.at(1_000_000), -3_000_000);
+
+    // Too large for the window to keep the clock running forward
+    assert_eq!(
+        offset.correct(
+            -4_500_000,
+            1_000_000,
+            Duration::from_secs(1),
+            SLEW_THRESHOLD
+        ),
+        Correction::Stepped(TimeDelta::milliseconds(-1_500))
+    );
+    assert_eq!(
+        offset.correct(-4_400_000, 1_000_000, Duration::ZERO, SLEW_THRESHOLD),
+        Correction::Stepped(TimeDelta::milliseconds(100))
+    );
+    assert!(!offset.is_slewing(1_000_000));
+}
@@ -0,0 +1,49 @@
+//! The device state the console command tests run against.
+
+use chrono::{TimeZone, Utc};
+use chrono_tz::Europe;
+use mario_clock_core::{
+    alarm::AlarmClock, clock::SyncTracker, command::CommandContext, sensor::SensorReadings,
+    settings::Settings, time::ManualTime, timer::Timer, weather::Weather,
+};
+
+/// Everything a [`CommandContext`] borrows, as on a fresh clock in Zurich
+/// with the time at noon UTC on 15 June 2025. Tests replace single fields
+/// with struct update syntax.
+pub struct Fixture {
+    pub settings: Settings,
+    pub sync: SyncTracker,
+    pub time: ManualTime,
+    pub alarms: AlarmClock,
+    pub timer: Timer,
+    pub weather: Weather,
+    pub sensor: SensorReadings,
+}
+
+impl Default for Fixture {
+    fn default() -> Self {
+        Fixture {
+            settings: Settings::new(Europe::Zurich),
+            sync: SyncTracker::new(),
+            time: ManualTime::new(Utc.with_ymd_and_hms(2025, 6, 15, 12, 0, 0).unwrap()),
+            alarms: AlarmClock::new(),
+            timer: Timer::new(),
+            weather: Weather::new(),
+            sensor: SensorReadings::new(),
+        }
+    }
+}
+
+impl Fixture {
+    pub fn context(&self) -> CommandContext<'_> {
+        CommandContext {
+            settings: &self.settings,
+            sync: &self.sync,
+            time: &self.time,
+            alarms: &self.alarms,
+            timer: &self.timer,
+            weather: &self.weather,
+            sensor: &self.sensor,
+        }
+    }
+}
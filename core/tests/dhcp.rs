@@ -0,0 +1,311 @@
+use std::{collections::VecDeque, net::Ipv4Addr, time::Duration};
+
+use embassy_futures::block_on;
+use mario_clock_core::dhcp::{
+    parse_reply, DhcpClient, DhcpError, DhcpTransport, Lease, Reply, MESSAGE_SIZE, REPLY_TIMEOUT,
+};
+
+const XID: u32 = 0x1234_5678;
+const MAC: [u8; 6] = [0x02, 0x00, 0x00, 0xaa, 0xbb, 0xcc];
+const CLIENT: DhcpClient = DhcpClient {
+    mac: MAC,
+    hostname: "mario-clock-rs",
+};
+const ADDRESS: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 42);
+const SERVER: Ipv4Addr = Ipv4Addr::new(192, 168, 1, 1);
+
+/// DHCP reply to [`XID`] offering [`ADDRESS`] with the given options, the end
+/// option is appended.
+fn reply(options: &[u8]) -> Vec<u8> {
+    let mut reply = vec![0u8; 240];
+    reply[0] = 2;
+    reply[1] = 1;
+    reply[2] = 6;
+    reply[4..8].copy_from_slice(&XID.to_be_bytes());
+    reply[16..20].copy_from_slice(&ADDRESS.octets());
+    reply[28..34].copy_from_slice(&MAC);
+    reply[236..240].copy_from_slice(&[99, 130, 83, 99]);
+    reply.extend_from_slice(options);
+    reply.push(255);
+    reply
+}
+
+/// The options of a lease of [`SERVER`] for an hour, after the message type.
+const LEASE_OPTIONS: [u8; 18] = [
+    1, 4, 255, 255, 255, 0, // subnet mask
+    54, 4, 192, 168, 1, 1, // server identifier
+    51, 4, 0, 0, 0x0e, 0x10, // lease time
+];
+
+fn lease_reply(message_type: u8, more: &[u8]) -> Vec<u8> {
+    let mut options = vec![53, 1, message_type];
+    options.extend_from_slice(&LEASE_OPTIONS);
+    options.extend_from_slice(more);
+    reply(&options)
+}
+
+/// The options of a message, without the end option.
+fn options(message: &[u8]) -> &[u8] {
+    let end = message[240..].iter().position(|&b| b == 255).unwrap();
+    &message[240..240 + end]
+}
+
+/// How long the [`FakeServer`] takes for every reply.
+const REPLY_DELAY: Duration = Duration::from_secs(1);
+
+/// A DHCP server answering with scripted replies, one every [`REPLY_DELAY`].
+/// The requests are recorded.
+struct FakeServer {
+    replies: VecDeque<Vec<u8>>,
+    sent: Vec<(Ipv4Addr, Vec<u8>)>,
+    millis: u64,
+}
+
+impl FakeServer {
+    fn new(replies: Vec<Vec<u8>>) -> Self {
+        FakeServer {
+            replies: replies.into(),
+            sent: Vec::new(),
+            millis: 0,
+        }
+    }
+}
+
+impl DhcpTransport for FakeServer {
+    async fn send(&mut self, server: Ipv4Addr, request: &[u8]) -> Result<(), DhcpError> {
+        self.sent.push((server, request.to_vec()));
+        Ok(())
+    }
+
+    async fn receive(&mut self, reply: &mut [u8], timeout: Duration) -> Result<usize, DhcpError> {
+        if timeout < REPLY_DELAY || self.replies.is_empty() {
+            self.millis += timeout.as_millis() as u64;
+            return Err(DhcpError::Timeout);
+        }
+        self.millis += REPLY_DELAY.as_millis() as u64;
+        let next = self.replies.pop_front().unwrap();
+        reply[..next.len()].copy_from_slice(&next);
+        Ok(next.len())
+    }
+
+    fn millis(&self) -> u64 {
+        self.millis
+    }
+}
+
+#[test]
+fn discover_asks_for_ntp_servers() {
+    let packet = CLIENT.discover(XID);
+    assert_eq!(packet.len(), MESSAGE_SIZE);
+    assert_eq!(packet[..4], [1, 1, 6, 0]);
+    assert_eq!(packet[4..8], XID.to_be_bytes());
+    // Without an address the replies have to be broadcast
+    assert_eq!(packet[10..12], [0x80, 0]);
+    assert_eq!(packet[12..16], [0; 4]);
+    assert_eq!(packet[28..34], MAC);
+    assert_eq!(packet[236..240], [99, 130, 83, 99]);
+    let mut expected = vec![53, 1, 1, 55, 6, 1, 3, 6, 42, 51, 58, 12, 14];
+    expected.extend_from_slice(b"mario-clock-rs");
+    assert_eq!(options(&packet), expected);
+}
+
+#[test]
+fn request_takes_the_offer() {
+    let Reply::Offer(offer) = parse_reply(&lease_reply(2, &[]), XID, MAC).unwrap() else {
+        panic!("not an offer");
+    };
+    let packet = CLIENT.request(XID, &offer);
+    assert_eq!(packet[10..12], [0x80, 0]);
+    assert_eq!(
+        options(&packet)[..15],
+        [53, 1, 3, 50, 4, 192, 168, 1, 42, 54, 4, 192, 168, 1, 1]
+    );
+    assert_eq!(options(&packet)[15..21], [55, 6, 1, 3, 6, 42]);
+}
+
+#[test]
+fn renewal_uses_the_address() {
+    let packet = CLIENT.renewal(XID, ADDRESS);
+    assert_eq!(packet[10..12], [0, 0]);
+    assert_eq!(packet[12..16], ADDRESS.octets());
+    assert_eq!(options(&packet)[..5], [53, 1, 3, 55, 6]);
+}
+
+#[test]
+fn long_hostnames_are_cut() {
+    let client = DhcpClient {
+        hostname: "a-very-long-hostname-for-a-mario-clock",
+        ..CLIENT
+    };
+    let packet = client.discover(XID);
+    assert_eq!(options(&packet)[11..13], [12, 32]);
+}
+
+#[test]
+fn leases_are_parsed() {
+    let reply = lease_reply(
+        5,
+        &[
+            3, 8, 192, 168, 1, 254, 192, 168, 1, 253, // routers
+            6, 4, 9, 9, 9, 9, // DNS
+            42, 8, 192, 168, 1, 1, 10, 0, 0, 1, // NTP
+        ],
+    );
+    let Reply::Ack(lease) = parse_reply(&reply, XID, MAC).unwrap() else {
+        panic!("not an ack");
+    };
+    assert_eq!(
+        lease,
+        Lease {
+            address: ADDRESS,
+            prefix_len: 24,
+            router: Some(Ipv4Addr::new(192, 168, 1, 254)),
+            dns_servers: [Ipv4Addr::new(9, 9, 9, 9)].into_iter().collect(),
+            ntp_servers: [Ipv4Addr::new(192, 168, 1, 1), Ipv4Addr::new(10, 0, 0, 1)]
+                .into_iter()
+                .collect(),
+            server: SERVER,
+            lease_time: Duration::from_secs(3600),
+            renewal_time: Duration::from_secs(1800),
+        }
+    );
+
+    // No option 42, no servers
+    let Reply::Ack(lease) = parse_reply(&lease_reply(5, &[58, 4, 0, 0, 0, 60]), XID, MAC).unwrap()
+    else {
+        panic!("not an ack");
+    };
+    assert!(lease.ntp_servers.is_empty());
+    assert_eq!(lease.router, None);
+    assert_eq!(lease.renewal_time, Duration::from_secs(60));
+}
+
+#[test]
+fn ntp_servers_are_limited() {
+    let mut options = vec![42, 24];
+    for n in 1..=6 {
+        options.extend_from_slice(&[10, 0, 0, n]);
+    }
+    let Reply::Ack(lease) = parse_reply(&lease_reply(5, &options), XID, MAC).unwrap() else {
+        panic!("not an ack");
+    };
+    assert_eq!(lease.ntp_servers.len(), 4);
+    assert_eq!(lease.ntp_servers[3], Ipv4Addr::new(10, 0, 0, 4));
+}
+
+#[test]
+fn bad_replies_are_rejected() {
+    assert_eq!(
+        parse_reply(&lease_reply(5, &[]), XID + 1, MAC),
+        Err(DhcpError::InvalidReply)
+    );
+    assert_eq!(
+        parse_reply(&reply(&[53, 1, 6]), XID, MAC),
+        Err(DhcpError::NotAcknowledged)
+    );
+    // No message type, or a lease without a server or a lease time
+    assert_eq!(
+        parse_reply(&reply(&LEASE_OPTIONS), XID, MAC),
+        Err(DhcpError::InvalidReply)
+    );
+    assert_eq!(
+        parse_reply(&reply(&[53, 1, 5, 1, 4, 255, 255, 255, 0]), XID, MAC),
+        Err(DhcpError::InvalidReply)
+    );
+    assert_eq!(
+        parse_reply(&lease_reply(5, &[42, 3, 10, 0, 0]), XID, MAC),
+        Err(DhcpError::InvalidReply)
+    );
+    // Option length past the end of the packet
+    let mut truncated = lease_reply(5, &[42, 8, 10, 0, 0, 1]);
+    truncated.pop();
+    assert_eq!(
+        parse_reply(&truncated, XID, MAC),
+        Err(DhcpError::InvalidReply)
+    );
+    let mut request = lease_reply(5, &[]);
+    request[0] = 1;
+    assert_eq!(
+        parse_reply(&request, XID, MAC),
+        Err(DhcpError::InvalidReply)
+    );
+    assert_eq!(
+        parse_reply(&[2; 100], XID, MAC),
+        Err(DhcpError::InvalidReply)
+    );
+    // The reply to another client that happened to pick the same xid
+    let mut foreign = lease_reply(5, &[]);
+    foreign[33] ^= 0x01;
+    assert_eq!(
+        parse_reply(&foreign, XID, MAC),
+        Err(DhcpError::InvalidReply)
+    );
+}
+
+#[test]
+fn acquire_gets_the_ntp_servers() {
+    let mut other_offer = lease_reply(2, &[]);
+    other_offer[4..8].copy_from_slice(&(XID + 1).to_be_bytes());
+    let mut second_offer = lease_reply(2, &[]);
+    second_offer[16..20].copy_from_slice(&[192, 168, 1, 43]);
+    let mut server = FakeServer::new(vec![
+        // Another client's offer on the broadcast address
+        other_offer,
+        lease_reply(2, &[]),
+        // A second server's offer arriving after the request
+        second_offer,
+        lease_reply(5, &[42, 4, 192, 168, 1, 1]),
+    ]);
+    let lease = block_on(CLIENT.acquire(&mut server, XID)).unwrap();
+    assert_eq!(lease.address, ADDRESS);
+    assert_eq!(lease.ntp_servers.as_slice(), [SERVER]);
+
+    assert_eq!(server.sent.len(), 2);
+    let (to, discover) = &server.sent[0];
+    assert_eq!(*to, Ipv4Addr::BROADCAST);
+    assert_eq!(options(discover)[..3], [53, 1, 1]);
+    let (to, request) = &server.sent[1];
+    assert_eq!(*to, Ipv4Addr::BROADCAST);
+    assert_eq!(options(request)[..3], [53, 1, 3]);
+}
+
+#[test]
+fn acquire_fails() {
+    let mut silent = FakeServer::new(Vec::new());
+    assert_eq!(
+        block_on(CLIENT.acquire(&mut silent, XID)),
+        Err(DhcpError::Timeout)
+    );
+    let mut declining = FakeServer::new(vec![lease_reply(2, &[]), reply(&[53, 1, 6])]);
+    assert_eq!(
+        block_on(CLIENT.acquire(&mut declining, XID)),
+        Err(DhcpError::NotAcknowledged)
+    );
+}
+
+#[test]
+fn renew_asks_the_server() {
+    let Reply::Ack(lease) = parse_reply(&lease_reply(5, &[]), XID, MAC).unwrap() else {
+        panic!("not an ack");
+    };
+    let mut server = FakeServer::new(vec![lease_reply(5, &[42, 4, 10, 0, 0, 1])]);
+    let renewed = block_on(CLIENT.renew(&mut server, XID, &lease)).unwrap();
+    assert_eq!(renewed.ntp_servers.as_slice(), [Ipv4Addr::new(10, 0, 0, 1)]);
+    assert_eq!(server.sent[0].0, SERVER);
+    assert_eq!(server.sent[0].1[12..16], ADDRESS.octets());
+}
+
+#[test]
+fn replies_are_awaited_until_one_deadline() {
+    let mut other_offer = lease_reply(2, &[]);
+    other_offer[4..8].copy_from_slice(&(XID + 1).to_be_bytes());
+    // A busy network keeps the client waiting no longer than a quiet one
+    let mut replies = vec![other_offer; 8];
+    replies.push(lease_reply(2, &[]));
+    let mut server = FakeServer::new(replies);
+    assert_eq!(
+        block_on(CLIENT.acquire(&mut server, XID)),
+        Err(DhcpError::Timeout)
+    );
+    assert_eq!(server.millis, REPLY_TIMEOUT.as_millis() as u64);
+}
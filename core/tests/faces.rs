@@ -0,0 +1,141 @@
+mod common;
+
+use chrono::{DateTime, TimeZone, Utc};
+use common::Fixture;
+use mario_clock_core::{
+    command::{Command, CommandError},
+    faces::{parse_rotation, scheduled_face},
+    settings::{FaceId, RotationStep},
+    time::ManualTime,
+    transition::TransitionKind,
+};
+
+fn at(second: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 6, 15, 12, 0, second).unwrap()
+}
+
+#[test]
+fn rotation_is_aligned_to_the_minute() {
+    let rotation = parse_rotation("mario:55,date:5").unwrap();
+    assert_eq!(
+        rotation,
+        [
+            RotationStep {
+                face: FaceId::Mario,
+                seconds: 55,
+                transition: None
+            },
+            RotationStep {
+                face: FaceId::Date,
+                seconds: 5,
+                transition: None
+            }
+        ]
+    );
+    // Mario hits the blocks on the full minute
+    assert_eq!(scheduled_face(&rotation, at(0)), Some(FaceId::Mario));
+    assert_eq!(scheduled_face(&rotation, at(54)), Some(FaceId::Mario));
+    assert_eq!(scheduled_face(&rotation, at(55)), Some(FaceId::Date));
+    assert_eq!(scheduled_face(&rotation, at(59)), Some(FaceId::Date));
+    assert_eq!(scheduled_face(&[], at(0)), None);
+
+    // Before 1970 works the same
+    let before_epoch = Utc.with_ymd_and_hms(1969, 12, 31, 23, 59, 58).unwrap();
+    assert_eq!(scheduled_face(&rotation, before_epoch), Some(FaceId::Date));
+}
+
+#[test]
+fn rotation_steps_pick_their_transition() {
+    let rotation = parse_rotation("mario:55,date:5:iris").unwrap();
+    assert_eq!(rotation[0].transition, None);
+    assert_eq!(rotation[1].transition, Some(TransitionKind::Iris));
+}
+
+#[test]
+fn invalid_rotations() {
+    for invalid in [
+        "",
+        "mario",
+        "mario:0",
+        "mario:-5",
+        "luigi:5",
+        "mario:70000",
+        "mario:1,date:1,mario:1,date:1,mario:1",
+        "date:5:zoom",
+        "date:5:iris:1",
+    ] {
+        assert_eq!(parse_rotation(invalid), None, "{invalid}");
+    }
+}
+
+#[test]
+fn face_commands() {
+    let fixture = Fixture {
+        time: ManualTime::new(at(0)),
+        ..Fixture::default()
+    };
+    let Fixture { settings, .. } = &fixture;
+    let context = fixture.context();
+
+    assert_eq!(Command::parse("face"), Ok(Command::ShowFace));
+    assert_eq!(
+        Command::parse("face date"),
+        Ok(Command::SetFace(FaceId::Date))
+    );
+    assert_eq!(
+        Command::parse("face luigi"),
+        Err(CommandError::InvalidArgument)
+    );
+    assert_eq!(
+        Command::parse("face rotate mario"),
+        Err(CommandError::InvalidArgument)
+    );
+
+    assert_eq!(Command::ShowFace.execute(&context), "face: mario");
+    let reply = Command::parse("face rotate mario:55,date:5")
+        .unwrap()
+        .execute(&context);
+    assert_eq!(reply, "face: mario, rotating mario:55,date:5");
+    assert_eq!(settings.rotation().len(), 2);
+
+    // Picking a face stops the rotation
+    let reply = Command::parse("face date").unwrap().execute(&context);
+    assert_eq!(reply, "face: date");
+    assert!(settings.rotation().is_empty());
+    assert_eq!(settings.active_face(), FaceId::Date);
+
+    Command::parse("face rotate date:1,mario:1")
+        .unwrap()
+        .execute(&context);
+    let reply = Command::parse("face rotate off").unwrap().execute(&context);
+    assert_eq!(reply, "face: date");
+
+    let reply = Command::parse("face rotate mario:55:wipe,date:5")
+        .unwrap()
+        .execute(&context);
+    assert_eq!(reply, "face: date, rotating mario:55:wipe,date:5");
+}
+
+#[test]
+fn transition_commands() {
+    let fixture = Fixture {
+        time: ManualTime::new(at(0)),
+        ..Fixture::default()
+    };
+    let Fixture { settings, .. } = &fixture;
+    let context = fixture.context();
+
+    assert_eq!(
+        Command::ShowTransition.execute(&context),
+        "transition: fade"
+    );
+    let reply = Command::parse("face transition iris")
+        .unwrap()
+        .execute(&context);
+    assert_eq!(reply, "transition: iris");
+    assert_eq!(settings.transition(), TransitionKind::Iris);
+    assert_eq!(
+        Command::parse("face transition zoom"),
+        Err(CommandError::InvalidArgument)
+    );
+}
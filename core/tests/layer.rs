@@ -0,0 +1,84 @@
+//! Scrolls background layers and checks where their objects are drawn.
+
+use core::convert::Infallible;
+use std::collections::BTreeSet;
+
+use embedded_graphics::{
+    pixelcolor::Rgb888,
+    prelude::{DrawTarget, OriginDimensions, Size},
+    Pixel,
+};
+use mario_clock_core::engine::{layer::Layer, object::Object};
+
+/// A white object of 4 by 1 pixels.
+const BAR: &[u16] = &[0xFFFF; 4];
+
+/// Records the columns drawn to.
+#[derive(Default)]
+struct Columns(BTreeSet<i32>);
+
+impl OriginDimensions for Columns {
+    fn size(&self) -> Size {
+        Size::new(64, 64)
+    }
+}
+
+impl DrawTarget for Columns {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.0
+            .extend(pixels.into_iter().map(|Pixel(point, _)| point.x));
+        Ok(())
+    }
+}
+
+fn columns<const N: usize>(layer: &Layer<N>, millis: u64) -> Vec<i32> {
+    let mut fb = Columns::default();
+    layer.draw_mapped(millis, &mut fb, |color| color);
+    fb.0.into_iter().collect()
+}
+
+#[test]
+fn still_layer_keeps_its_place() {
+    let layer = Layer::new([(Object::new(BAR, 4, 1), 10, 5)]);
+    assert_eq!(columns(&layer, 0), [10, 11, 12, 13]);
+    assert_eq!(columns(&layer, 600_000), [10, 11, 12, 13]);
+}
+
+#[test]
+fn scrolls_with_the_elapsed_time() {
+    let mut layer = Layer::new([(Object::new(BAR, 4, 1), 10, 5)]);
+    // 60 pixels per minute, one per second
+    layer.set_speed(60, 1_000);
+    assert_eq!(layer.offset(1_000), 0);
+    assert_eq!(layer.offset(1_999), 0);
+    assert_eq!(layer.offset(4_000), 3);
+    assert_eq!(columns(&layer, 4_000), [7, 8, 9, 10]);
+}
+
+#[test]
+fn speed_change_keeps_the_position() {
+    let mut layer = Layer::new([(Object::new(BAR, 4, 1), 10, 5)]);
+    layer.set_speed(60, 0);
+    layer.set_speed(120, 5_000);
+    assert_eq!(layer.offset(5_000), 5);
+    assert_eq!(layer.offset(6_000), 7);
+    layer.set_speed(0, 6_000);
+    assert_eq!(layer.offset(60_000), 7);
+}
+
+#[test]
+fn objects_wrap_around_the_edges() {
+    let mut layer = Layer::new([(Object::new(BAR, 4, 1), 1, 5)]);
+    layer.set_speed(60, 0);
+    // Half past the left edge shows on the right
+    assert_eq!(columns(&layer, 3_000), [0, 1, 62, 63]);
+    // Back at the start after scrolling the width of the display
+    assert_eq!(layer.offset(64_000), 0);
+    assert_eq!(columns(&layer, 64_000), [1, 2, 3, 4]);
+}
@@ -0,0 +1,81 @@
+use chrono::{DateTime, TimeZone, Utc};
+use mario_clock_core::nmea::{parse_time, NmeaError};
+
+fn utc(year: i32, month: u32, day: u32, hour: u32, min: u32, sec: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(year, month, day, hour, min, sec)
+        .unwrap()
+}
+
+// Sentences recorded from a u-blox NEO-6M and a multi constellation receiver
+const RECORDED: &[&str] = &[
+    "$GPRMC,143052.00,A,4723.06574,N,00832.47221,E,0.021,,150625,,,A*7E\r\n",
+    "$GNRMC,143053.00,A,4723.06573,N,00832.47219,E,0.015,,150625,,,A,V*10",
+    "$GPZDA,143054.00,15,06,2025,00,00*66",
+    "$GNZDA,143055.50,15,06,2025,,*7C",
+];
+
+#[test]
+fn recorded_sentences_are_parsed() {
+    let times: Vec<_> = RECORDED.iter().map(|s| parse_time(s)).collect();
+    assert_eq!(
+        times,
+        [
+            Ok(utc(2025, 6, 15, 14, 30, 52)),
+            Ok(utc(2025, 6, 15, 14, 30, 53)),
+            Ok(utc(2025, 6, 15, 14, 30, 54)),
+            Ok(utc(2025, 6, 15, 14, 30, 55) + chrono::TimeDelta::milliseconds(500)),
+        ]
+    );
+}
+
+#[test]
+fn sentences_without_fix_are_rejected() {
+    // Receiver without a fix, but already knows the time from the almanac
+    assert_eq!(
+        parse_time("$GPRMC,143052.00,V,,,,,,,150625,,,N*79"),
+        Err(NmeaError::NoFix)
+    );
+    assert_eq!(parse_time("$GPZDA,,,,,00,00*48"), Err(NmeaError::NoFix));
+}
+
+#[test]
+fn corrupt_sentences_are_rejected() {
+    assert_eq!(
+        parse_time("$GPZDA,143054.00,15,06,2025,00,00*6F"),
+        Err(NmeaError::Checksum)
+    );
+    assert_eq!(
+        parse_time("$GPZDA,143054.00,15,06,2025,00,00"),
+        Err(NmeaError::Checksum)
+    );
+    assert_eq!(
+        parse_time("$GPZDA,143054.00,15,06,2025,00,00*+E"),
+        Err(NmeaError::Checksum)
+    );
+    assert_eq!(
+        parse_time("GPZDA,143054.00,15,06,2025,00,00*6E"),
+        Err(NmeaError::Malformed)
+    );
+    // Valid checksums, invalid fields
+    assert_eq!(
+        parse_time("$GPZDA,143054.00,31,06,2025,00,00*60"),
+        Err(NmeaError::Malformed)
+    );
+    assert_eq!(
+        parse_time("$GPZDA,253054.00,15,06,2025,00,00*64"),
+        Err(NmeaError::Malformed)
+    );
+    assert_eq!(
+        parse_time("$GPRMC,143052.00,A,4723.06574,N,00832.47221,E,0.021,,1506,,,A*79"),
+        Err(NmeaError::Malformed)
+    );
+    assert_eq!(parse_time("$GPZDA*48"), Err(NmeaError::Malformed));
+}
+
+#[test]
+fn other_sentences_are_unsupported() {
+    assert_eq!(
+        parse_time("$GPGGA,143052.00,4723.06574,N,00832.47221,E,1,08,1.01,499.6,M,48.0,M,,*52"),
+        Err(NmeaError::Unsupported)
+    );
+}
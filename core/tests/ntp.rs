@@ -0,0 +1,316 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    thread,
+    time::Duration,
+};
+
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+use embassy_futures::block_on;
+use mario_clock_core::{
+    clock::SyncStatus,
+    ntp::{
+        from_ntp_timestamp, parse_reply, request, serve as serve_request, sync, to_ntp_timestamp,
+        NtpConfig, NtpError, NtpTransport, ServerStatus, PACKET_SIZE,
+    },
+    source::{Sample, SourceKind, PRIMARY_STRATUM, REFERENCE_GPS},
+    time::ManualTime,
+};
+
+/// Transport on top of the host network stack, delays are recorded instead
+/// of slept.
+struct HostTransport {
+    socket: UdpSocket,
+    delays: Vec<Duration>,
+}
+
+impl HostTransport {
+    fn new() -> Self {
+        HostTransport {
+            socket: UdpSocket::bind("127.0.0.1:0").unwrap(),
+            delays: Vec::new(),
+        }
+    }
+}
+
+impl NtpTransport for HostTransport {
+    async fn resolve(&mut self, host: &str) -> Result<IpAddr, NtpError> {
+        match host {
+            "localhost" => Ok(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            host => host.parse().map_err(|_| NtpError::Dns),
+        }
+    }
+
+    async fn exchange(
+        &mut self,
+        server: SocketAddr,
+        request: &[u8],
+        reply: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, NtpError> {
+        self.socket.set_read_timeout(Some(timeout)).unwrap();
+        self.socket
+            .send_to(request, server)
+            .map_err(|_| NtpError::Network)?;
+        loop {
+            match self.socket.recv_from(reply) {
+                Ok((len, from)) if from == server => return Ok(len),
+                Ok(_) => {}
+                Err(_) => return Err(NtpError::Timeout),
+            }
+        }
+    }
+
+    async fn delay(&mut self, duration: Duration) {
+        self.delays.push(duration);
+    }
+}
+
+fn server_time() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 6, 15, 12, 0, 0).unwrap() + TimeDelta::microseconds(250_000)
+}
+
+fn local_time() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 6, 15, 11, 59, 58).unwrap()
+}
+
+/// Reply to `request` as a stratum 2 server at [`server_time`].
+fn reply(request: &[u8]) -> [u8; PACKET_SIZE] {
+    let timestamp = to_ntp_timestamp(server_time()).to_be_bytes();
+    let mut reply = [0u8; PACKET_SIZE];
+    reply[0] = (4 << 3) | 4;
+    reply[1] = 2;
+    reply[24..32].copy_from_slice(&request[40..48]);
+    reply[32..40].copy_from_slice(&timestamp);
+    reply[40..48].copy_from_slice(&timestamp);
+    reply
+}
+
+/// Stand-in NTP server on a local port, `respond` builds the reply to the
+/// n-th request or drops it. Returns the address to put into the config.
+fn serve(mut respond: impl FnMut(usize, &[u8]) -> Option<Vec<u8>> + Send + 'static) -> String {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let addr = socket.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut buf = [0u8; 512];
+        let mut count = 0;
+        while let Ok((len, from)) = socket.recv_from(&mut buf) {
+            if let Some(reply) = respond(count, &buf[..len]) {
+                socket.send_to(&reply, from).unwrap();
+            }
+            count += 1;
+        }
+    });
+    addr.to_string()
+}
+
+fn config<'a>(servers: &'a [&'a str]) -> NtpConfig<'a> {
+    NtpConfig {
+        timeout: Duration::from_millis(200),
+        ..NtpConfig::new(servers)
+    }
+}
+
+#[test]
+fn sync_with_local_server() {
+    let server = serve(|_, request| Some(reply(request).to_vec()));
+    let time = ManualTime::new(local_time());
+    let mut transport = HostTransport::new();
+
+    let reply = block_on(sync(&config(&[&server]), &mut transport, &time)).unwrap();
+    assert_eq!(reply.server.to_string(), server);
+    assert_eq!(reply.offset, TimeDelta::microseconds(2_250_000));
+    assert_eq!(reply.round_trip, TimeDelta::zero());
+    assert_eq!(reply.stratum, 2);
+    assert_eq!(reply.server_time, server_time());
+    assert!(transport.delays.is_empty());
+}
+
+#[test]
+fn lost_reply_is_retried_after_backoff() {
+    let server = serve(|n, request| (n > 0).then(|| reply(request).to_vec()));
+    let time = ManualTime::new(local_time());
+    let mut transport = HostTransport::new();
+
+    let reply = block_on(sync(&config(&[&server]), &mut transport, &time)).unwrap();
+    assert_eq!(reply.offset, TimeDelta::microseconds(2_250_000));
+    assert_eq!(transport.delays, [Duration::from_secs(1)]);
+}
+
+#[test]
+fn next_server_is_tried_on_failure() {
+    let kiss = serve(|_, request| {
+        let mut reply = reply(request);
+        reply[1] = 0;
+        reply[12..16].copy_from_slice(b"RATE");
+        Some(reply.to_vec())
+    });
+    let server = serve(|_, request| Some(reply(request).to_vec()));
+    let time = ManualTime::new(local_time());
+    let mut transport = HostTransport::new();
+
+    let servers = ["ntp.invalid", kiss.as_str(), server.as_str()];
+    let reply = block_on(sync(&config(&servers), &mut transport, &time)).unwrap();
+    assert_eq!(reply.server.to_string(), server);
+    assert!(transport.delays.is_empty());
+}
+
+#[test]
+fn gives_up_with_last_error() {
+    let server = serve(|_, _| None);
+    let time = ManualTime::new(local_time());
+    let mut transport = HostTransport::new();
+
+    let result = block_on(sync(&config(&[&server]), &mut transport, &time));
+    assert_eq!(result, Err(NtpError::Timeout));
+    assert_eq!(
+        transport.delays,
+        [Duration::from_secs(1), Duration::from_secs(2)]
+    );
+
+    let result = block_on(sync(&config(&[]), &mut transport, &time));
+    assert_eq!(result, Err(NtpError::NoServers));
+}
+
+#[test]
+fn insane_replies_are_rejected() {
+    let server = SocketAddr::from((Ipv4Addr::LOCALHOST, 123));
+    let request = request(local_time());
+    let check = |f: &dyn Fn(&mut [u8; PACKET_SIZE])| {
+        let mut reply = reply(&request);
+        f(&mut reply);
+        parse_reply(server, &request, &reply, local_time()).map(|reply| reply.stratum)
+    };
+
+    assert_eq!(check(&|_| {}), Ok(2));
+    assert_eq!(
+        check(&|reply| {
+            reply[1] = 0;
+            reply[12..16].copy_from_slice(b"DENY");
+        }),
+        Err(NtpError::KissOfDeath(*b"DENY"))
+    );
+    assert_eq!(
+        check(&|reply| reply[0] |= 3 << 6),
+        Err(NtpError::Unsynchronized)
+    );
+    assert_eq!(check(&|reply| reply[1] = 16), Err(NtpError::Unsynchronized));
+    assert_eq!(
+        check(&|reply| reply[32..40].fill(0)),
+        Err(NtpError::ZeroTimestamp)
+    );
+    assert_eq!(
+        check(&|reply| reply[0] = (4 << 3) | 3),
+        Err(NtpError::InvalidReply)
+    );
+    assert_eq!(check(&|reply| reply[31] ^= 1), Err(NtpError::InvalidReply));
+    assert_eq!(
+        parse_reply(server, &request, &reply(&request)[..47], local_time()),
+        Err(NtpError::InvalidReply)
+    );
+}
+
+#[test]
+fn ntp_timestamps_convert() {
+    let time = server_time() + TimeDelta::microseconds(1);
+    assert_eq!(from_ntp_timestamp(to_ntp_timestamp(time)), Some(time));
+    assert_eq!(
+        from_ntp_timestamp(to_ntp_timestamp(DateTime::UNIX_EPOCH)),
+        Some(DateTime::UNIX_EPOCH)
+    );
+
+    // The seconds wrap in February 2036
+    let era1 = Utc.with_ymd_and_hms(2040, 1, 1, 0, 0, 0).unwrap();
+    assert!(to_ntp_timestamp(era1) >> 32 < 1 << 31);
+    assert_eq!(from_ntp_timestamp(to_ntp_timestamp(era1)), Some(era1));
+}
+
+/// Status of a clock set from a GPS ten minutes before [`server_time`].
+fn gps_status() -> ServerStatus {
+    let sample = Sample {
+        kind: SourceKind::Gps,
+        time: server_time() - TimeDelta::minutes(10),
+        uptime_micros: 60_000_000,
+        uncertainty: TimeDelta::milliseconds(500),
+        stratum: PRIMARY_STRATUM,
+        reference_id: REFERENCE_GPS,
+    };
+    let sync = SyncStatus {
+        sample,
+        offset: TimeDelta::zero(),
+        drift_ppm: None,
+    };
+    ServerStatus::new(Some(&sync), 660_000_000)
+}
+
+/// Stand-in for the clock serving `status` at [`server_time`].
+fn serve_time(status: ServerStatus) -> String {
+    serve(move |_, request| {
+        serve_request(request, &status, server_time(), server_time()).map(|reply| reply.to_vec())
+    })
+}
+
+#[test]
+fn served_time_syncs_client() {
+    let server = serve_time(gps_status());
+    let time = ManualTime::new(local_time());
+    let mut transport = HostTransport::new();
+
+    let reply = block_on(sync(&config(&[&server]), &mut transport, &time)).unwrap();
+    assert_eq!(reply.offset, TimeDelta::microseconds(2_250_000));
+    assert_eq!(reply.stratum, 1);
+    assert_eq!(reply.server_time, server_time());
+}
+
+#[test]
+fn served_reply_describes_the_clock() {
+    let status = gps_status();
+    // 500 ms and 100 ppm of 10 minutes
+    assert_eq!(status.dispersion, TimeDelta::milliseconds(560));
+
+    let request = request(local_time());
+    let reply = serve_request(&request, &status, server_time(), server_time()).unwrap();
+    assert_eq!(reply[0], (4 << 3) | 4);
+    assert_eq!(reply[1], 1);
+    assert_eq!(reply[3] as i8, -20);
+    assert_eq!(reply[4..8], [0; 4]);
+    // 0.56 s in 16.16 fixed point
+    assert_eq!(u32::from_be_bytes(reply[8..12].try_into().unwrap()), 36_700);
+    assert_eq!(&reply[12..16], b"GPS\0");
+    assert_eq!(
+        from_ntp_timestamp(u64::from_be_bytes(reply[16..24].try_into().unwrap())),
+        Some(server_time() - TimeDelta::minutes(10))
+    );
+    assert_eq!(reply[24..32], request[40..48]);
+}
+
+#[test]
+fn unknown_time_is_served_unsynchronized() {
+    let server = serve_time(ServerStatus::new(None, 0));
+    let time = ManualTime::new(local_time());
+    let mut transport = HostTransport::new();
+
+    let result = block_on(sync(&config(&[&server]), &mut transport, &time));
+    assert_eq!(result, Err(NtpError::Unsynchronized));
+}
+
+#[test]
+fn only_client_requests_are_answered() {
+    let status = gps_status();
+    let mut request = request(local_time());
+    let answer = |request: &[u8]| serve_request(request, &status, server_time(), server_time());
+
+    // Older clients get a reply in their version
+    request[0] = (3 << 3) | 3;
+    assert_eq!(answer(&request).unwrap()[0], (3 << 3) | 4);
+
+    assert_eq!(answer(&request[..47]), None);
+    request[0] = (4 << 3) | 4;
+    assert_eq!(answer(&request), None);
+    request[0] = (4 << 3) | 5;
+    assert_eq!(answer(&request), None);
+    request[0] = (7 << 3) | 3;
+    assert_eq!(answer(&request), None);
+}
@@ -0,0 +1,365 @@
+//! Fetches the weather from a stand-in for the Open-Meteo API on the host.
+
+mod common;
+
+use std::{
+    io::{Read, Write},
+    net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use chrono::{TimeZone, Utc};
+use common::Fixture;
+use embassy_futures::block_on;
+use mario_clock_core::{
+    command::Command,
+    http::{self, parse_response, HttpError, HttpTransport, Url},
+    json::Value,
+    open_meteo::{condition, parse, OpenMeteo, OPEN_METEO_URL},
+    sun::Location,
+    time::{ManualTime, TimeSource},
+    weather::{Conditions, Weather, WeatherCondition, WeatherError, WeatherProvider},
+};
+
+/// Body of a forecast response as sent by Open-Meteo.
+const FORECAST: &str = r#"{"latitude":47.36,"longitude":8.54,"generationtime_ms":0.03,"utc_offset_seconds":0,"timezone":"GMT","timezone_abbreviation":"GMT","elevation":411.0,"current_units":{"time":"iso8601","interval":"seconds","temperature_2m":"°C","weather_code":"wmo code"},"current":{"time":"2025-01-01T12:30","interval":900,"temperature_2m":-3.4,"weather_code":73}}"#;
+
+fn ok(body: &str) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+/// A tiny HTTP server on the loopback interface answering each connection
+/// with the next of its responses. Joining it returns the requests.
+struct FakeServer {
+    addr: SocketAddr,
+    handle: JoinHandle<Vec<String>>,
+}
+
+impl FakeServer {
+    fn start(responses: Vec<String>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 512];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let len = stream.read(&mut buf).unwrap();
+                    if len == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..len]);
+                }
+                requests.push(String::from_utf8(request).unwrap());
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        });
+        FakeServer { addr, handle }
+    }
+
+    fn url(&self) -> String {
+        format!("http://localhost:{}/v1/forecast", self.addr.port())
+    }
+
+    fn requests(self) -> Vec<String> {
+        self.handle.join().unwrap()
+    }
+}
+
+/// Transport on top of the host network stack.
+struct HostTransport;
+
+impl HttpTransport for HostTransport {
+    async fn resolve(&mut self, host: &str) -> Result<IpAddr, HttpError> {
+        match host {
+            "localhost" => Ok(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            host => host.parse().map_err(|_| HttpError::Dns),
+        }
+    }
+
+    async fn exchange(
+        &mut self,
+        server: SocketAddr,
+        request: &[u8],
+        response: &mut [u8],
+        timeout: Duration,
+    ) -> Result<usize, HttpError> {
+        let mut stream =
+            TcpStream::connect_timeout(&server, timeout).map_err(|_| HttpError::Network)?;
+        stream.set_read_timeout(Some(timeout)).unwrap();
+        stream.write_all(request).map_err(|_| HttpError::Network)?;
+        let mut len = 0;
+        loop {
+            if len == response.len() {
+                return Err(HttpError::TooLarge);
+            }
+            match stream.read(&mut response[len..]) {
+                Ok(0) => return Ok(len),
+                Ok(read) => len += read,
+                Err(_) => return Err(HttpError::Timeout),
+            }
+        }
+    }
+}
+
+fn zurich() -> Location {
+    Location::new(47.37, 8.54).unwrap()
+}
+
+#[test]
+fn parses_urls() {
+    assert_eq!(
+        Url::parse(OPEN_METEO_URL),
+        Ok(Url {
+            host: "api.open-meteo.com",
+            port: 80,
+            path: "/v1/forecast"
+        })
+    );
+    assert_eq!(
+        Url::parse("http://localhost:8080"),
+        Ok(Url {
+            host: "localhost",
+            port: 8080,
+            path: "/"
+        })
+    );
+    assert_eq!(
+        Url::parse("https://api.open-meteo.com/v1/forecast"),
+        Err(HttpError::InvalidUrl)
+    );
+    // A query needs a path before it
+    assert_eq!(
+        Url::parse("http://10.0.0.2?q=1"),
+        Err(HttpError::InvalidUrl)
+    );
+    assert_eq!(Url::parse("http://:80/"), Err(HttpError::InvalidUrl));
+    assert_eq!(Url::parse("http://host:port/"), Err(HttpError::InvalidUrl));
+}
+
+#[test]
+fn parses_responses() {
+    assert_eq!(parse_response(ok("{}").as_bytes()), Ok(&b"{}"[..]));
+    // The body ends at the content length, or with the connection
+    assert_eq!(
+        parse_response(b"HTTP/1.0 200 OK\r\nContent-Length: 2\r\n\r\n{}trailing"),
+        Ok(&b"{}"[..])
+    );
+    assert_eq!(parse_response(b"HTTP/1.0 200 OK\r\n\r\n{}"), Ok(&b"{}"[..]));
+    assert_eq!(
+        parse_response(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n{}"),
+        Err(HttpError::InvalidResponse)
+    );
+    assert_eq!(
+        parse_response(b"HTTP/1.1 404 Not Found\r\n\r\n"),
+        Err(HttpError::Status(404))
+    );
+    assert_eq!(
+        parse_response(
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\n{}\r\n0\r\n\r\n"
+        ),
+        Err(HttpError::InvalidResponse)
+    );
+    assert_eq!(
+        parse_response(b"SSH-2.0-OpenSSH\r\n\r\n"),
+        Err(HttpError::InvalidResponse)
+    );
+    assert_eq!(
+        parse_response(b"HTTP/1.1 200 OK\r\n"),
+        Err(HttpError::InvalidResponse)
+    );
+}
+
+#[test]
+fn reads_json() {
+    let json = Value::parse(FORECAST).unwrap();
+    assert_eq!(
+        json.path(&["current_units", "temperature_2m"])
+            .and_then(|value| value.as_str()),
+        Some("°C")
+    );
+    assert_eq!(
+        json.path(&["current", "temperature_2m"])
+            .and_then(|value| value.as_f32()),
+        Some(-3.4)
+    );
+    assert_eq!(json.path(&["current", "rain"]), None);
+    assert_eq!(json.path(&["latitude", "current"]), None);
+
+    let json = Value::parse(r#" { "a" : [1, {"b": "x\"}"}, null], "c": {"d": true} } "#).unwrap();
+    assert!(matches!(json.get("a"), Some(Value::Array(_))));
+    assert_eq!(json.path(&["c", "d"]), Some(Value::Bool(true)));
+    assert_eq!(Value::parse("{}").unwrap().get("a"), None);
+
+    for invalid in ["", "{", r#"{"a":}"#, r#"{"a":1,}"#, "[1 2]", "{} {}", "nul"] {
+        assert_eq!(Value::parse(invalid), None, "{invalid}");
+    }
+    // Nesting is bounded
+    assert_eq!(Value::parse(&"[".repeat(100)), None);
+}
+
+#[test]
+fn reads_the_current_conditions() {
+    assert_eq!(
+        parse(FORECAST.as_bytes()),
+        Ok(Conditions {
+            condition: WeatherCondition::Snow,
+            temperature: -3.4,
+        })
+    );
+    assert_eq!(parse(b"{}"), Err(WeatherError::InvalidData));
+    assert_eq!(
+        parse(br#"{"current":{"temperature_2m":3.4,"weather_code":42}}"#),
+        Err(WeatherError::InvalidData)
+    );
+    assert_eq!(parse(b"<html>"), Err(WeatherError::InvalidData));
+}
+
+#[test]
+fn maps_weather_codes() {
+    assert_eq!(condition(0), Some(WeatherCondition::Clear));
+    assert_eq!(condition(3), Some(WeatherCondition::Cloudy));
+    assert_eq!(condition(45), Some(WeatherCondition::Fog));
+    assert_eq!(condition(55), Some(WeatherCondition::Rain));
+    assert_eq!(condition(81), Some(WeatherCondition::Rain));
+    assert_eq!(condition(95), Some(WeatherCondition::Rain));
+    assert_eq!(condition(75), Some(WeatherCondition::Snow));
+    assert_eq!(condition(86), Some(WeatherCondition::Snow));
+    assert_eq!(condition(100), None);
+}
+
+#[test]
+fn fetches_from_the_server() {
+    let server = FakeServer::start(vec![ok(FORECAST)]);
+    let url = server.url();
+    let mut provider = OpenMeteo::new(HostTransport);
+    provider.url = &url;
+
+    let conditions = block_on(provider.fetch(zurich())).unwrap();
+    assert_eq!(conditions.condition, WeatherCondition::Snow);
+    assert_eq!(conditions.temperature, -3.4);
+
+    let requests = server.requests();
+    assert!(requests[0].starts_with(
+        "GET /v1/forecast?latitude=47.37&longitude=8.54&current=temperature_2m,weather_code HTTP/1.0\r\n"
+    ));
+    assert!(requests[0].contains("\r\nHost: localhost\r\n"));
+}
+
+#[test]
+fn keeps_the_last_report() {
+    let server = FakeServer::start(vec![
+        ok(FORECAST),
+        String::from("HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n"),
+        ok("{\"error\":true}"),
+    ]);
+    let url = server.url();
+    let mut provider = OpenMeteo::new(HostTransport);
+    provider.url = &url;
+    let time = ManualTime::new(Utc.with_ymd_and_hms(2025, 1, 1, 12, 30, 0).unwrap());
+    let weather = Weather::new();
+
+    let report = block_on(weather.update(&mut provider, zurich(), &time)).unwrap();
+    assert_eq!(report.fetched_ms, time.millis());
+    assert_eq!(weather.report(), Some(report));
+    assert_eq!(weather.condition(), Some(WeatherCondition::Snow));
+
+    time.advance(Duration::from_secs(20 * 60));
+    assert_eq!(
+        block_on(weather.update(&mut provider, zurich(), &time)),
+        Err(WeatherError::Http(HttpError::Status(503)))
+    );
+    assert_eq!(
+        block_on(weather.update(&mut provider, zurich(), &time)),
+        Err(WeatherError::InvalidData)
+    );
+    assert_eq!(weather.report(), Some(report));
+    assert_eq!(report.age_minutes(time.millis()), 20);
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+fn fails_without_a_server() {
+    // Nothing listens on a port just released
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let url = format!("http://127.0.0.1:{}/", addr.port());
+    let mut buffer = [0u8; 256];
+    assert_eq!(
+        block_on(http::get(
+            &mut HostTransport,
+            &url,
+            &mut buffer,
+            Duration::from_secs(1)
+        )),
+        Err(HttpError::Network)
+    );
+}
+
+#[test]
+fn rejects_oversized_responses() {
+    let server = FakeServer::start(vec![ok(&"x".repeat(4096))]);
+    let mut buffer = [0u8; 256];
+    assert_eq!(
+        block_on(http::get(
+            &mut HostTransport,
+            &server.url(),
+            &mut buffer,
+            Duration::from_secs(1)
+        )),
+        Err(HttpError::TooLarge)
+    );
+}
+
+#[test]
+fn weather_now_command() {
+    let fixture = Fixture {
+        time: ManualTime::new(Utc.with_ymd_and_hms(2025, 1, 1, 12, 30, 0).unwrap()),
+        ..Fixture::default()
+    };
+    let Fixture {
+        settings,
+        time,
+        weather,
+        ..
+    } = &fixture;
+    let context = fixture.context();
+
+    assert_eq!(Command::parse("weather now"), Ok(Command::FetchWeather));
+    assert_eq!(
+        Command::FetchWeather.execute(&context),
+        "weather needs a location, see the location command"
+    );
+    assert!(!weather.requested.signaled());
+
+    settings.set_location(Some(zurich()));
+    assert_eq!(Command::FetchWeather.execute(&context), "weather requested");
+    assert!(weather.requested.signaled());
+
+    let server = FakeServer::start(vec![ok(FORECAST)]);
+    let url = server.url();
+    let mut provider = OpenMeteo::new(HostTransport);
+    provider.url = &url;
+    block_on(weather.update(&mut provider, zurich(), &time)).unwrap();
+    time.advance(Duration::from_secs(5 * 60));
+    assert_eq!(
+        Command::ShowWeather.execute(&context),
+        "weather: snow, last fetched snow at -3.4 C 5 minutes ago"
+    );
+    // Set by hand the condition changes, the report stays
+    Command::parse("weather fog").unwrap().execute(&context);
+    assert_eq!(
+        Command::ShowWeather.execute(&context),
+        "weather: fog, last fetched snow at -3.4 C 5 minutes ago"
+    );
+}
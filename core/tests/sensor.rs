@@ -0,0 +1,334 @@
+//! Drives the sensor drivers against a scripted I2C bus.
+
+mod common;
+
+use std::collections::VecDeque;
+
+use common::Fixture;
+use embedded_hal::i2c::{ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation};
+use mario_clock_core::{
+    command::Command,
+    sensor::{
+        bme280::{Bme280, Calibration},
+        detect,
+        sht3x::{crc8, Sht3x},
+        AnySensor, Reading, Sensor, SensorError, SensorReadings, READING_MAX_AGE,
+    },
+    settings::TemperatureUnit,
+    time::TimeSource,
+};
+
+/// A transfer the bus expects next, with the bytes a device answers.
+#[derive(Debug, Clone, PartialEq)]
+enum Transaction {
+    Write(u8, Vec<u8>),
+    Read(u8, Vec<u8>),
+    WriteRead(u8, Vec<u8>, Vec<u8>),
+    /// Nobody answers at the address.
+    Nack(u8),
+}
+
+/// An I2C bus answering the transfers of its script in order. Panics on
+/// any other transfer.
+struct MockI2c {
+    expected: VecDeque<Transaction>,
+}
+
+impl MockI2c {
+    fn new(expected: Vec<Transaction>) -> Self {
+        MockI2c {
+            expected: expected.into(),
+        }
+    }
+
+    fn done(&self) {
+        assert!(self.expected.is_empty(), "not done: {:?}", self.expected);
+    }
+}
+
+impl ErrorType for MockI2c {
+    type Error = ErrorKind;
+}
+
+impl I2c for MockI2c {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let expected = self.expected.pop_front().expect("unexpected transfer");
+        match (expected, operations) {
+            (Transaction::Nack(expected), _) if expected == address => {
+                Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address))
+            }
+            (Transaction::Write(expected, bytes), [Operation::Write(written)])
+                if expected == address && bytes == *written =>
+            {
+                Ok(())
+            }
+            (Transaction::Read(expected, bytes), [Operation::Read(read)])
+                if expected == address && bytes.len() == read.len() =>
+            {
+                read.copy_from_slice(&bytes);
+                Ok(())
+            }
+            (
+                Transaction::WriteRead(expected, bytes, response),
+                [Operation::Write(written), Operation::Read(read)],
+            ) if expected == address && bytes == *written && response.len() == read.len() => {
+                read.copy_from_slice(&response);
+                Ok(())
+            }
+            (expected, operations) => {
+                panic!("expected {expected:?} at {address:#x}, got {operations:?}")
+            }
+        }
+    }
+}
+
+/// A big endian word followed by its CRC, as the SHT3x sends it.
+fn sht3x_word(word: u16) -> Vec<u8> {
+    let bytes = word.to_be_bytes();
+    vec![bytes[0], bytes[1], crc8(&bytes)]
+}
+
+/// Calibration of the example in the BMP280 datasheet, with typical
+/// humidity parameters.
+fn calibration() -> Calibration {
+    Calibration {
+        t1: 27504,
+        t2: 26435,
+        t3: -1000,
+        p1: 36477,
+        p2: -10685,
+        p3: 3024,
+        p4: 2855,
+        p5: 140,
+        p6: -7,
+        p7: 15500,
+        p8: -14600,
+        p9: 6000,
+        h1: 75,
+        h2: 370,
+        h3: 0,
+        h4: 301,
+        h5: 50,
+        h6: 30,
+    }
+}
+
+/// The transfers of [`Bme280::init`] for [`calibration`].
+fn bme280_init(address: u8) -> Vec<Transaction> {
+    let calibration = calibration();
+    let mut tp = Vec::new();
+    tp.extend(calibration.t1.to_le_bytes());
+    for value in [calibration.t2, calibration.t3] {
+        tp.extend(value.to_le_bytes());
+    }
+    tp.extend(calibration.p1.to_le_bytes());
+    for value in [
+        calibration.p2,
+        calibration.p3,
+        calibration.p4,
+        calibration.p5,
+        calibration.p6,
+        calibration.p7,
+        calibration.p8,
+        calibration.p9,
+    ] {
+        tp.extend(value.to_le_bytes());
+    }
+    let h2 = calibration.h2.to_le_bytes();
+    // H4 and H5 are 12 bits, sharing the nibbles of 0xE5
+    let h = vec![
+        h2[0],
+        h2[1],
+        calibration.h3,
+        (calibration.h4 >> 4) as u8,
+        (calibration.h5 as u8 & 0x0F) << 4 | (calibration.h4 as u8 & 0x0F),
+        (calibration.h5 >> 4) as u8,
+        calibration.h6 as u8,
+    ];
+    vec![
+        Transaction::WriteRead(address, vec![0xD0], vec![0x60]),
+        Transaction::WriteRead(address, vec![0x88], tp),
+        Transaction::WriteRead(address, vec![0xA1], vec![calibration.h1]),
+        Transaction::WriteRead(address, vec![0xE1], h),
+        Transaction::Write(address, vec![0xF2, 0x01]),
+    ]
+}
+
+fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "{actual} isn't {expected}"
+    );
+}
+
+#[test]
+fn sht3x_crc() {
+    // The example of the datasheet
+    assert_eq!(crc8(&[0xBE, 0xEF]), 0x92);
+}
+
+#[test]
+fn sht3x_measures() {
+    let mut data = sht3x_word(0x6666);
+    data.extend(sht3x_word(0x8000));
+    let i2c = MockI2c::new(vec![
+        Transaction::Write(0x44, vec![0xF3, 0x2D]),
+        Transaction::Read(0x44, sht3x_word(0x8010)),
+        Transaction::Write(0x44, vec![0x24, 0x00]),
+        Transaction::Read(0x44, data),
+    ]);
+    let mut sensor = Sht3x::new(i2c, 0x44);
+    sensor.probe().unwrap();
+    sensor.start().unwrap();
+    let reading = sensor.read().unwrap();
+    assert_close(reading.temperature, 25.0, 0.01);
+    assert_close(reading.humidity.unwrap(), 50.0, 0.01);
+    assert_eq!(reading.pressure, None);
+    sensor.release().done();
+}
+
+#[test]
+fn sht3x_rejects_corrupt_data() {
+    let mut data = sht3x_word(0x6666);
+    data.extend(sht3x_word(0x8000));
+    data[5] ^= 0x01;
+    let mut sensor = Sht3x::new(MockI2c::new(vec![Transaction::Read(0x44, data)]), 0x44);
+    assert_eq!(sensor.read(), Err(SensorError::Checksum));
+}
+
+#[test]
+fn bme280_compensation() {
+    let calibration = calibration();
+    // The example of the BMP280 datasheet
+    let (temperature, t_fine) = calibration.temperature(519888);
+    assert_eq!(t_fine, 128422);
+    assert_eq!(temperature, 25.08);
+    assert_close(
+        calibration.pressure(415148, t_fine).unwrap(),
+        1006.5327,
+        0.001,
+    );
+    assert_close(calibration.humidity(30000, t_fine), 60.564, 0.001);
+    // Clamped to the range of the sensor
+    assert_eq!(calibration.humidity(0, t_fine), 0.0);
+    assert_eq!(calibration.humidity(0xFFFF, t_fine), 100.0);
+    assert_eq!(Calibration::default().pressure(415148, t_fine), None);
+}
+
+#[test]
+fn bme280_measures() {
+    let mut expected = bme280_init(0x77);
+    let raw20 = |value: u32| [(value >> 12) as u8, (value >> 4) as u8, (value << 4) as u8];
+    let mut data = Vec::new();
+    data.extend(raw20(415148));
+    data.extend(raw20(519888));
+    data.extend(30000u16.to_be_bytes());
+    expected.push(Transaction::Write(0x77, vec![0xF4, 0x25]));
+    expected.push(Transaction::WriteRead(0x77, vec![0xF7], data));
+
+    let mut sensor = Bme280::new(MockI2c::new(expected), 0x77);
+    sensor.init().unwrap();
+    sensor.start().unwrap();
+    let reading = sensor.read().unwrap();
+    assert_eq!(reading.temperature, 25.08);
+    assert_close(reading.humidity.unwrap(), 60.564, 0.001);
+    assert_close(reading.pressure.unwrap(), 1006.5327, 0.001);
+    sensor.release().done();
+}
+
+#[test]
+fn bme280_rejects_other_chips() {
+    // A BMP280 has no humidity sensor
+    let i2c = MockI2c::new(vec![Transaction::WriteRead(0x76, vec![0xD0], vec![0x58])]);
+    let mut sensor = Bme280::new(i2c, 0x76);
+    assert_eq!(sensor.init(), Err(SensorError::UnknownChip(0x58)));
+}
+
+#[test]
+fn detects_the_sensor() {
+    let mut expected = vec![
+        Transaction::Nack(0x44),
+        Transaction::Nack(0x45),
+        Transaction::Nack(0x76),
+    ];
+    expected.extend(bme280_init(0x77));
+    let sensor = detect(MockI2c::new(expected)).ok().unwrap();
+    assert!(matches!(sensor, AnySensor::Bme280(_)));
+    assert_eq!(sensor.name(), "bme280");
+
+    let i2c = MockI2c::new(vec![
+        Transaction::Nack(0x44),
+        Transaction::Write(0x45, vec![0xF3, 0x2D]),
+        Transaction::Read(0x45, sht3x_word(0)),
+    ]);
+    let sensor = detect(i2c).ok().unwrap();
+    assert_eq!(sensor.name(), "sht3x");
+
+    // Without a sensor the bus comes back, e.g. for the RTC
+    let i2c = MockI2c::new(vec![
+        Transaction::Nack(0x44),
+        Transaction::Nack(0x45),
+        Transaction::Nack(0x76),
+        Transaction::Nack(0x77),
+    ]);
+    detect(i2c).err().unwrap().done();
+}
+
+#[test]
+fn readings_go_stale() {
+    let readings = SensorReadings::new();
+    assert_eq!(readings.latest(0), None);
+    assert_eq!(readings.sensor(), None);
+    let reading = Reading {
+        temperature: 21.5,
+        humidity: Some(45.0),
+        pressure: None,
+    };
+    readings.publish("sht3x", reading, 1_000);
+    assert_eq!(readings.sensor(), Some("sht3x"));
+    let max_age = READING_MAX_AGE.as_millis() as u64;
+    assert_eq!(readings.latest(1_000 + max_age), Some(reading));
+    assert_eq!(readings.latest(1_001 + max_age), None);
+}
+
+#[test]
+fn sensor_command() {
+    let fixture = Fixture::default();
+    let Fixture {
+        settings,
+        time,
+        sensor,
+        ..
+    } = &fixture;
+    let context = fixture.context();
+
+    assert_eq!(Command::parse("sensor"), Ok(Command::ShowSensor));
+    assert_eq!(Command::ShowSensor.execute(&context), "sensor: no reading");
+    let reading = Reading {
+        temperature: 21.5,
+        humidity: Some(45.2),
+        pressure: Some(1013.25),
+    };
+    sensor.publish("bme280", reading, time.millis());
+    assert_eq!(
+        Command::ShowSensor.execute(&context),
+        "sensor: bme280, 21.5 C, 45 % humidity, 1013.2 hPa"
+    );
+    settings.set_temperature_unit(TemperatureUnit::Fahrenheit);
+    sensor.publish(
+        "sht3x",
+        Reading {
+            pressure: None,
+            ..reading
+        },
+        time.millis(),
+    );
+    assert_eq!(
+        Command::ShowSensor.execute(&context),
+        "sensor: sht3x, 70.7 F, 45 % humidity"
+    );
+}
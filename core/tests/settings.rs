@@ -0,0 +1,488 @@
+mod common;
+
+use chrono::{NaiveTime, TimeZone, Utc};
+use chrono_tz::{America, Europe};
+use common::Fixture;
+use mario_clock_core::{
+    alarm::{Alarm, Weekdays, MAX_ALARMS},
+    command::{Command, CommandError},
+    settings::{
+        FaceId, HourFormat, RotationStep, Scenery, Settings, TemperatureUnit,
+        DEFAULT_SYNC_INTERVAL_MINUTES, MAX_ROTATION_STEPS, SETTINGS_SIZE,
+    },
+    sun::Location,
+    time::{ManualTime, TimeSource},
+    transition::TransitionKind,
+};
+
+#[test]
+fn settings_round_trip() {
+    let settings = Settings::new(Europe::Zurich);
+    settings.set_time_zone(America::New_York);
+    settings.set_sync_interval_minutes(90);
+    settings.set_slew_window_seconds(30);
+    settings.set_ntp_server(true);
+    settings.set_hour_format(HourFormat::H12);
+    settings.set_leading_zero(false);
+    for n in 0..MAX_ALARMS as u32 {
+        // The longest labels, the settings have to fit all of them
+        let alarm = Alarm {
+            time: NaiveTime::from_hms_opt(6 + n, 30, 0).unwrap(),
+            weekdays: Weekdays::from_bits(1 << (n % 7)),
+            recurring: n % 2 == 0,
+            enabled: n % 3 != 0,
+            label: "Long label 1".try_into().unwrap(),
+        };
+        settings.add_alarm(alarm).unwrap();
+    }
+    settings.set_active_face(FaceId::Date);
+    let rotation = (0..MAX_ROTATION_STEPS as u16)
+        .map(|n| RotationStep {
+            face: FaceId::ALL[n as usize % FaceId::ALL.len()],
+            seconds: 300 * n + 5,
+            transition: TransitionKind::ALL.get(n as usize + 2).copied(),
+        })
+        .collect();
+    settings.set_rotation(rotation);
+    settings.set_transition(TransitionKind::Dissolve);
+    settings.set_location(Location::new(-33.87, 151.21));
+    settings.set_scenery(Scenery::Scroll);
+    settings.set_temperature_unit(TemperatureUnit::Fahrenheit);
+    settings.set_show_temperature(false);
+    let mut buf = [0xFF; SETTINGS_SIZE];
+    let len = settings.save(&mut buf).unwrap();
+
+    let restored = Settings::new(Europe::Zurich);
+    assert!(restored.load(&buf[..len]));
+    assert_eq!(restored.values(), settings.values());
+}
+
+#[test]
+fn worst_case_settings_fit_with_room_to_spare() {
+    let settings = Settings::new(Europe::Zurich);
+    let longest_zone = chrono_tz::TZ_VARIANTS
+        .into_iter()
+        .max_by_key(|zone| zone.name().len())
+        .unwrap();
+    settings.set_time_zone(longest_zone);
+    for n in 0..MAX_ALARMS as u32 {
+        let alarm = Alarm {
+            time: NaiveTime::from_hms_opt(n, 0, 0).unwrap(),
+            weekdays: Weekdays::EVERY_DAY,
+            recurring: true,
+            enabled: true,
+            label: "Twelve chars".try_into().unwrap(),
+        };
+        settings.add_alarm(alarm).unwrap();
+    }
+    let rotation = (0..MAX_ROTATION_STEPS)
+        .map(|_| RotationStep {
+            face: FaceId::Date,
+            seconds: u16::MAX,
+            transition: Some(TransitionKind::Dissolve),
+        })
+        .collect();
+    settings.set_rotation(rotation);
+    settings.set_location(Location::new(-90.0, -180.0));
+
+    let mut buf = [0xFF; SETTINGS_SIZE];
+    let len = settings.save(&mut buf).unwrap();
+    // Half of the space is left for settings to come
+    assert!(len <= SETTINGS_SIZE / 2, "{len} bytes");
+    let restored = Settings::new(Europe::Zurich);
+    assert!(restored.load(&buf[..len]));
+    assert_eq!(restored.values(), settings.values());
+}
+
+#[test]
+fn erased_or_corrupt_settings_keep_defaults() {
+    let settings = Settings::new(Europe::Zurich);
+    assert!(!settings.load(&[0xFF; SETTINGS_SIZE]));
+    assert!(!settings.load(&[]));
+
+    let mut buf = [0xFF; SETTINGS_SIZE];
+    let len = Settings::new(America::New_York).save(&mut buf).unwrap();
+    buf[len - 3] ^= 0x01;
+    assert!(!settings.load(&buf));
+    assert_eq!(settings.time_zone(), Europe::Zurich);
+}
+
+#[test]
+fn unknown_records_are_skipped() {
+    // magic, length, unknown key 99 with two bytes, then the time zone
+    let mut buf = b"MCS1\x00\x00\x63\x02ab\x01\x0dEurope/London".to_vec();
+    buf[4] = (buf.len() - 6) as u8;
+    let checksum = fletcher16(&buf[6..]);
+    buf.extend_from_slice(&checksum.to_le_bytes());
+
+    let settings = Settings::new(Europe::Zurich);
+    assert!(settings.load(&buf));
+    assert_eq!(settings.time_zone(), Europe::London);
+    assert_eq!(
+        settings.sync_interval_minutes(),
+        DEFAULT_SYNC_INTERVAL_MINUTES
+    );
+}
+
+#[test]
+fn changes_are_signaled() {
+    let settings = Settings::new(Europe::Zurich);
+    assert!(!settings.changed.signaled());
+    settings.set_time_zone(Europe::London);
+    assert!(settings.changed.signaled());
+}
+
+#[test]
+fn time_zone_command() {
+    let fixture = Fixture::default();
+    let Fixture { settings, .. } = &fixture;
+    let context = fixture.context();
+
+    assert_eq!(Command::parse("tz"), Ok(Command::ShowTimeZone));
+    assert_eq!(
+        Command::parse(" tz  Asia/Tokyo "),
+        Ok(Command::SetTimeZone(chrono_tz::Asia::Tokyo))
+    );
+    assert_eq!(
+        Command::parse("tz Mars/Olympus"),
+        Err(CommandError::InvalidArgument)
+    );
+    assert_eq!(Command::parse("tz a b"), Err(CommandError::InvalidArgument));
+    assert_eq!(Command::parse("timezone"), Err(CommandError::Unknown));
+    assert_eq!(Command::parse(""), Err(CommandError::Empty));
+
+    let reply = Command::parse("tz America/New_York")
+        .unwrap()
+        .execute(&context);
+    assert_eq!(reply, "time zone: America/New_York");
+    assert_eq!(settings.time_zone(), America::New_York);
+}
+
+#[test]
+fn sync_commands() {
+    let fixture = Fixture::default();
+    let Fixture { settings, sync, .. } = &fixture;
+    let context = fixture.context();
+
+    assert_eq!(Command::parse("sync"), Ok(Command::ShowSync));
+    assert_eq!(Command::parse("sync now"), Ok(Command::SyncNow));
+    assert_eq!(
+        Command::parse("sync interval 30"),
+        Ok(Command::SetSyncInterval(30))
+    );
+    assert_eq!(
+        Command::parse("sync interval 0"),
+        Err(CommandError::InvalidArgument)
+    );
+    assert_eq!(
+        Command::parse("sync later"),
+        Err(CommandError::InvalidArgument)
+    );
+
+    let reply = Command::parse("sync").unwrap().execute(&context);
+    assert_eq!(reply, "not synced yet, interval 360 min, slew window 600 s");
+
+    Command::parse("sync now").unwrap().execute(&context);
+    assert!(sync.requested.signaled());
+
+    Command::SetSyncInterval(15).execute(&context);
+    assert_eq!(settings.sync_interval_minutes(), 15);
+
+    assert_eq!(Command::parse("sync slew 0"), Ok(Command::SetSlewWindow(0)));
+    assert_eq!(
+        Command::parse("sync slew -1"),
+        Err(CommandError::InvalidArgument)
+    );
+    let reply = Command::parse("sync slew 120").unwrap().execute(&context);
+    assert_eq!(reply, "not synced yet, interval 15 min, slew window 120 s");
+    assert_eq!(settings.slew_window_seconds(), 120);
+
+    assert_eq!(
+        Command::parse("sync serve on"),
+        Ok(Command::SetNtpServer(true))
+    );
+    assert_eq!(
+        Command::parse("sync serve maybe"),
+        Err(CommandError::InvalidArgument)
+    );
+    assert!(!settings.ntp_server());
+    Command::parse("sync serve on").unwrap().execute(&context);
+    assert!(settings.ntp_server());
+    Command::parse("sync serve off").unwrap().execute(&context);
+    assert!(!settings.ntp_server());
+}
+
+#[test]
+fn hour_formats() {
+    let hours: Vec<_> = [0, 1, 11, 12, 13, 23]
+        .iter()
+        .map(|&hour| HourFormat::H12.display_hour(hour))
+        .collect();
+    assert_eq!(hours, [12, 1, 11, 12, 1, 11]);
+    assert_eq!(HourFormat::H24.display_hour(0), 0);
+    assert_eq!(HourFormat::H24.display_hour(23), 23);
+    assert_eq!(HourFormat::from_hours(12), Some(HourFormat::H12));
+    assert_eq!(HourFormat::from_hours(13), None);
+}
+
+#[test]
+fn hours_commands() {
+    let fixture = Fixture::default();
+    let Fixture { settings, .. } = &fixture;
+    let context = fixture.context();
+
+    assert_eq!(Command::parse("hours"), Ok(Command::ShowHours));
+    assert_eq!(
+        Command::parse("hours 12"),
+        Ok(Command::SetHourFormat(HourFormat::H12))
+    );
+    assert_eq!(
+        Command::parse("hours 13"),
+        Err(CommandError::InvalidArgument)
+    );
+    assert_eq!(
+        Command::parse("hours zero"),
+        Err(CommandError::InvalidArgument)
+    );
+
+    assert_eq!(
+        Command::ShowHours.execute(&context),
+        "24 hour clock, with leading zero"
+    );
+    Command::parse("hours 12").unwrap().execute(&context);
+    let reply = Command::parse("hours zero off").unwrap().execute(&context);
+    assert_eq!(reply, "12 hour clock, without leading zero");
+    assert_eq!(settings.hour_format(), HourFormat::H12);
+    assert!(!settings.leading_zero());
+}
+
+fn fletcher16(data: &[u8]) -> u16 {
+    let (mut sum1, mut sum2) = (0u16, 0u16);
+    for byte in data {
+        sum1 = (sum1 + *byte as u16) % 255;
+        sum2 = (sum2 + sum1) % 255;
+    }
+    (sum2 << 8) | sum1
+}
+
+#[test]
+fn time_commands() {
+    let fixture = Fixture {
+        time: ManualTime::unknown(Utc.with_ymd_and_hms(2025, 6, 15, 12, 0, 0).unwrap()),
+        ..Fixture::default()
+    };
+    let Fixture { sync, time, .. } = &fixture;
+    let context = fixture.context();
+
+    assert_eq!(Command::parse("time"), Ok(Command::ShowTime));
+    assert_eq!(
+        Command::parse("time set 2025-06-15T14:30:00+02:00"),
+        Ok(Command::SetTime(
+            Utc.with_ymd_and_hms(2025, 6, 15, 12, 30, 0).unwrap()
+        ))
+    );
+    assert_eq!(
+        Command::parse("time set 14:30"),
+        Err(CommandError::InvalidArgument)
+    );
+    assert_eq!(
+        Command::parse("time now"),
+        Err(CommandError::InvalidArgument)
+    );
+
+    assert_eq!(
+        Command::ShowTime.execute(&context),
+        "time: unknown, clock reads 2025-06-15 14:00:00 CEST"
+    );
+    time.set(Utc.with_ymd_and_hms(2025, 6, 15, 12, 30, 0).unwrap());
+    assert_eq!(
+        Command::ShowTime.execute(&context),
+        "time: 2025-06-15 14:30:00 CEST"
+    );
+
+    Command::parse("time set 2025-06-15T12:30:00Z")
+        .unwrap()
+        .execute(&context);
+    assert_eq!(
+        sync.manual.try_take(),
+        Some(Utc.with_ymd_and_hms(2025, 6, 15, 12, 30, 0).unwrap())
+    );
+}
+
+#[test]
+fn alarm_commands() {
+    let fixture = Fixture {
+        time: ManualTime::new(Utc.with_ymd_and_hms(2025, 6, 13, 5, 29, 0).unwrap()),
+        ..Fixture::default()
+    };
+    let Fixture {
+        settings,
+        time,
+        alarms,
+        ..
+    } = &fixture;
+    let context = fixture.context();
+
+    assert_eq!(Command::parse("alarm"), Ok(Command::ShowAlarms));
+    assert_eq!(
+        Command::parse("alarm add 07:30 mon-fri Work"),
+        Ok(Command::AddAlarm(Alarm {
+            time: NaiveTime::from_hms_opt(7, 30, 0).unwrap(),
+            weekdays: "mon-fri".parse().unwrap(),
+            recurring: true,
+            enabled: true,
+            label: "Work".try_into().unwrap(),
+        }))
+    );
+    assert_eq!(
+        Command::parse("alarm remove 1"),
+        Ok(Command::RemoveAlarm(1))
+    );
+    assert_eq!(
+        Command::parse("alarm off 2"),
+        Ok(Command::EnableAlarm(2, false))
+    );
+    for invalid in [
+        "alarm add 7:30am daily",
+        "alarm add 24:00 daily",
+        "alarm add 07:30 weekdays",
+        "alarm add 07:30 daily Label longer than 12",
+        "alarm add 07:30 daily A very long label",
+        "alarm remove 0",
+        "alarm snooze 5",
+    ] {
+        assert_eq!(
+            Command::parse(invalid),
+            Err(CommandError::InvalidArgument),
+            "{invalid}"
+        );
+    }
+
+    assert_eq!(Command::ShowAlarms.execute(&context), "no alarms");
+    Command::parse("alarm add 07:30 mon-fri Work")
+        .unwrap()
+        .execute(&context);
+    let reply = Command::parse("alarm add 09:00 sat,once")
+        .unwrap()
+        .execute(&context);
+    assert_eq!(reply, "alarms:\n1: 07:30 mon-fri Work\n2: 09:00 sat,once");
+    let reply = Command::parse("alarm off 1").unwrap().execute(&context);
+    assert_eq!(
+        reply,
+        "alarms:\n1: 07:30 mon-fri off Work\n2: 09:00 sat,once"
+    );
+    assert_eq!(
+        Command::parse("alarm on 3").unwrap().execute(&context),
+        "no alarm 3"
+    );
+    assert_eq!(
+        Command::parse("alarm remove 2").unwrap().execute(&context),
+        "removed alarm 09:00 sat,once"
+    );
+
+    // Ring the first alarm, then snooze and dismiss it
+    Command::parse("alarm on 1").unwrap().execute(&context);
+    assert_eq!(
+        Command::SnoozeAlarm.execute(&context),
+        "no alarm is ringing"
+    );
+    let zone = settings.time_zone();
+    alarms.check(settings, time.now().with_timezone(&zone));
+    time.set(Utc.with_ymd_and_hms(2025, 6, 13, 5, 30, 0).unwrap());
+    assert!(alarms
+        .check(settings, time.now().with_timezone(&zone))
+        .is_some());
+    assert_eq!(
+        Command::SnoozeAlarm.execute(&context),
+        "snoozed until 07:39:00"
+    );
+    time.set(Utc.with_ymd_and_hms(2025, 6, 13, 5, 39, 0).unwrap());
+    assert!(alarms
+        .check(settings, time.now().with_timezone(&zone))
+        .is_some());
+    assert_eq!(
+        Command::DismissAlarm.execute(&context),
+        "dismissed alarm 07:30 mon-fri Work"
+    );
+}
+
+#[test]
+fn alarm_labels_take_the_rest_of_the_line() {
+    let Ok(Command::AddAlarm(alarm)) = Command::parse("alarm add 07:30 mon-fri Go to  work") else {
+        panic!("not an alarm");
+    };
+    assert_eq!(alarm.label, "Go to work");
+    // Only `alarm add` takes more words than that
+    assert_eq!(
+        Command::parse("alarm remove 1 2 3 4"),
+        Err(CommandError::InvalidArgument)
+    );
+}
+
+#[test]
+fn location_is_cleared() {
+    let settings = Settings::new(Europe::Zurich);
+    settings.set_location(Location::new(47.37, 8.54));
+    let mut buf = [0xFF; SETTINGS_SIZE];
+    let len = Settings::new(Europe::Zurich).save(&mut buf).unwrap();
+    assert!(settings.load(&buf[..len]));
+    assert_eq!(settings.location(), None);
+}
+
+#[test]
+fn location_commands() {
+    let fixture = Fixture {
+        time: ManualTime::new(Utc.with_ymd_and_hms(2025, 6, 21, 12, 0, 0).unwrap()),
+        ..Fixture::default()
+    };
+    let Fixture { settings, .. } = &fixture;
+    let context = fixture.context();
+
+    assert_eq!(Command::ShowLocation.execute(&context), "location: off");
+    let reply = Command::parse("location 47.37 8.54")
+        .unwrap()
+        .execute(&context);
+    assert_eq!(reply, "location: 47.37,8.54, sunrise 05:29, sunset 21:26");
+    assert_eq!(settings.location(), Location::new(47.37, 8.54));
+
+    let reply = Command::parse("location 69.65 18.96")
+        .unwrap()
+        .execute(&context);
+    assert_eq!(reply, "location: 69.65,18.96, sun up all day");
+
+    for invalid in [
+        "location 91 0",
+        "location 0 east",
+        "location NaN 0",
+        "location 47",
+    ] {
+        assert_eq!(
+            Command::parse(invalid),
+            Err(CommandError::InvalidArgument),
+            "{invalid}"
+        );
+    }
+
+    let reply = Command::parse("location off").unwrap().execute(&context);
+    assert_eq!(reply, "location: off");
+    assert_eq!(settings.location(), None);
+}
+
+#[test]
+fn scenery_commands() {
+    let fixture = Fixture {
+        time: ManualTime::new(Utc.with_ymd_and_hms(2025, 6, 21, 12, 0, 0).unwrap()),
+        ..Fixture::default()
+    };
+    let Fixture { settings, .. } = &fixture;
+    let context = fixture.context();
+
+    assert_eq!(Command::ShowScenery.execute(&context), "scenery: clouds");
+    let reply = Command::parse("scenery scroll").unwrap().execute(&context);
+    assert_eq!(reply, "scenery: scroll");
+    assert_eq!(settings.scenery(), Scenery::Scroll);
+    assert_eq!(
+        Command::parse("scenery fast"),
+        Err(CommandError::InvalidArgument)
+    );
+}
@@ -0,0 +1,186 @@
+use core::time::Duration;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+use embassy_futures::block_on;
+use mario_clock_core::{
+    clock::SyncTracker,
+    ntp::{to_ntp_timestamp, NtpConfig, NtpError, NtpTransport},
+    source::{ManualSource, NtpSource, Reference, Sample, SourceKind, LOCAL_STRATUM},
+    time::{ManualTime, TimeSource},
+};
+
+const SECOND: u64 = 1_000_000;
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 6, 15, 12, 0, 0).unwrap()
+}
+
+fn sample(kind: SourceKind, uptime_seconds: u64, uncertainty: TimeDelta) -> Sample {
+    Sample {
+        kind,
+        time: start() + TimeDelta::seconds(uptime_seconds as i64),
+        uptime_micros: uptime_seconds * SECOND,
+        uncertainty,
+        stratum: 2,
+        reference_id: [192, 168, 1, 1],
+    }
+}
+
+#[test]
+fn drift_is_estimated() {
+    let tracker = SyncTracker::new();
+    assert_eq!(tracker.status(), None);
+
+    let first = sample(SourceKind::Ntp, 0, TimeDelta::milliseconds(5));
+    let status = tracker
+        .offer(&first, first.time + TimeDelta::seconds(3))
+        .unwrap();
+    assert_eq!(status.offset, TimeDelta::seconds(3));
+    assert_eq!(status.drift_ppm, None);
+
+    // 36 ms fast after an hour is 10 ppm
+    let second = sample(SourceKind::Ntp, 3600, TimeDelta::milliseconds(5));
+    let status = tracker
+        .offer(&second, second.time + TimeDelta::milliseconds(36))
+        .unwrap();
+    assert_eq!(status.sample, second);
+    assert!((status.drift_ppm.unwrap() - 10.0).abs() < 0.01);
+
+    // Too soon after the last sync, the estimate is kept
+    let third = sample(SourceKind::Ntp, 3660, TimeDelta::milliseconds(5));
+    let status = tracker
+        .offer(&third, third.time - TimeDelta::milliseconds(5))
+        .unwrap();
+    assert_eq!(status.offset, TimeDelta::milliseconds(-5));
+    assert!((status.drift_ppm.unwrap() - 10.0).abs() < 0.01);
+    assert_eq!(tracker.status(), Some(status));
+}
+
+#[test]
+fn rtc_samples_dont_estimate_drift() {
+    let tracker = SyncTracker::new();
+    let rtc = sample(SourceKind::Rtc, 0, TimeDelta::seconds(1));
+    tracker.offer(&rtc, rtc.time);
+
+    let ntp = sample(SourceKind::Ntp, 3600, TimeDelta::milliseconds(5));
+    let status = tracker
+        .offer(&ntp, ntp.time + TimeDelta::milliseconds(700))
+        .unwrap();
+    assert_eq!(status.drift_ppm, None);
+}
+
+#[test]
+fn better_sources_win() {
+    let tracker = SyncTracker::new();
+    let ntp = sample(SourceKind::Ntp, 0, TimeDelta::milliseconds(10));
+    assert!(tracker.offer(&ntp, ntp.time).is_some());
+
+    // Worse than NTP a minute ago, the NTP time has only drifted by 6 ms
+    let gps = sample(SourceKind::Gps, 60, TimeDelta::milliseconds(500));
+    assert!(tracker.offer(&gps, gps.time).is_none());
+    let manual = sample(SourceKind::Manual, 60, TimeDelta::seconds(1));
+    assert!(tracker.offer(&manual, manual.time).is_none());
+    assert_eq!(tracker.status().unwrap().sample, ntp);
+
+    // After two hours without NTP the GPS is better
+    let gps = sample(SourceKind::Gps, 7200, TimeDelta::milliseconds(500));
+    assert!(tracker.offer(&gps, gps.time).is_some());
+
+    // Same uncertainty, the preferred kind wins
+    let ntp = sample(SourceKind::Ntp, 7200, TimeDelta::milliseconds(500));
+    assert!(tracker.offer(&ntp, ntp.time).is_some());
+    let gps = sample(SourceKind::Gps, 7200, TimeDelta::milliseconds(500));
+    assert!(tracker.offer(&gps, gps.time).is_none());
+}
+
+#[test]
+fn uncertainty_grows_with_age() {
+    let sample = sample(SourceKind::Ntp, 10, TimeDelta::milliseconds(2));
+    assert_eq!(sample.uncertainty_at(0), TimeDelta::milliseconds(2));
+    assert_eq!(
+        sample.uncertainty_at(10 * SECOND + 3600 * SECOND),
+        TimeDelta::microseconds(2_000 + 360_000)
+    );
+    assert!(sample.uncertainty_at(u64::MAX) > TimeDelta::days(365));
+}
+
+#[test]
+fn manual_source_waits_for_time() {
+    let tracker = SyncTracker::new();
+    let time = ManualTime::unknown(start());
+    time.advance(Duration::from_secs(5));
+    let mut source = ManualSource {
+        requested: &tracker.manual,
+        time: &time,
+    };
+    assert_eq!(source.kind(), SourceKind::Manual);
+
+    let set = Utc.with_ymd_and_hms(2025, 6, 15, 18, 0, 0).unwrap();
+    tracker.manual.signal(set);
+    let sample = block_on(source.sample()).unwrap();
+    assert_eq!(sample.time, set);
+    assert_eq!(sample.uptime_micros, 5 * SECOND);
+    assert_eq!(sample.uncertainty, TimeDelta::seconds(1));
+    assert_eq!(sample.stratum, LOCAL_STRATUM);
+    assert_eq!(sample.reference_id, *b"LOCL");
+}
+
+/// Answers every request like a server 2 seconds ahead that took 1 ms to
+/// answer, without touching the network.
+struct FakeServer<'a> {
+    time: &'a ManualTime,
+}
+
+impl NtpTransport for FakeServer<'_> {
+    async fn resolve(&mut self, _host: &str) -> Result<IpAddr, NtpError> {
+        Ok(IpAddr::V4(Ipv4Addr::LOCALHOST))
+    }
+
+    async fn exchange(
+        &mut self,
+        _server: SocketAddr,
+        request: &[u8],
+        reply: &mut [u8],
+        _timeout: Duration,
+    ) -> Result<usize, NtpError> {
+        // 10 ms there, 1 ms in the server, 30 ms back
+        self.time.advance(Duration::from_millis(10));
+        let receive = self.time.now() + TimeDelta::seconds(2);
+        let transmit = receive + TimeDelta::milliseconds(1);
+        self.time.advance(Duration::from_millis(31));
+
+        reply[..48].fill(0);
+        reply[0] = (4 << 3) | 4;
+        reply[1] = 1;
+        reply[24..32].copy_from_slice(&request[40..48]);
+        reply[32..40].copy_from_slice(&to_ntp_timestamp(receive).to_be_bytes());
+        reply[40..48].copy_from_slice(&to_ntp_timestamp(transmit).to_be_bytes());
+        Ok(48)
+    }
+
+    async fn delay(&mut self, _duration: Duration) {}
+}
+
+#[test]
+fn ntp_source_samples_server_time() {
+    let time = ManualTime::new(start());
+    let mut source = NtpSource {
+        config: NtpConfig::new(&["ntp.example"]),
+        transport: FakeServer { time: &time },
+        time: &time,
+    };
+    assert_eq!(source.kind(), SourceKind::Ntp);
+
+    let sample = block_on(source.sample()).unwrap();
+    assert_eq!(sample.uptime_micros, time.micros());
+    // Off by half the asymmetry of the paths, at most half the round trip
+    assert_eq!(
+        sample.time,
+        time.now() + TimeDelta::seconds(2) - TimeDelta::milliseconds(10)
+    );
+    assert_eq!(sample.uncertainty, TimeDelta::milliseconds(20));
+    // One below the stratum 1 server
+    assert_eq!(sample.stratum, 2);
+    assert_eq!(sample.reference_id, [127, 0, 0, 1]);
+}
@@ -0,0 +1,172 @@
+use chrono::{DateTime, NaiveDate, TimeDelta, TimeZone, Utc};
+use mario_clock_core::{
+    mario::sky::SkyPhase,
+    sun::{events, position, Location, SunEvents},
+};
+
+const ZURICH: Location = Location {
+    latitude: 47.37,
+    longitude: 8.54,
+};
+const NEW_YORK: Location = Location {
+    latitude: 40.71,
+    longitude: -74.01,
+};
+const SYDNEY: Location = Location {
+    latitude: -33.87,
+    longitude: 151.21,
+};
+const TROMSO: Location = Location {
+    latitude: 69.65,
+    longitude: 18.96,
+};
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+}
+
+/// Asserts `events` are within two minutes of the published times.
+fn assert_events(events: SunEvents, sunrise: DateTime<Utc>, sunset: DateTime<Utc>) {
+    let SunEvents::Daily {
+        sunrise: actual_sunrise,
+        sunset: actual_sunset,
+    } = events
+    else {
+        panic!("no sunrise and sunset: {events:?}");
+    };
+    let close = |actual: DateTime<Utc>, expected: DateTime<Utc>| {
+        (actual - expected).abs() <= TimeDelta::minutes(2)
+    };
+    assert!(close(actual_sunrise, sunrise), "sunrise {actual_sunrise}");
+    assert!(close(actual_sunset, sunset), "sunset {actual_sunset}");
+}
+
+// Times of the NOAA solar calculator for the longest and shortest days,
+// converted to UTC
+#[test]
+fn known_sunrise_and_sunset() {
+    // 05:29 and 21:26 CEST
+    assert_events(
+        events(ZURICH, date(2025, 6, 21)),
+        utc(2025, 6, 21, 3, 29),
+        utc(2025, 6, 21, 19, 26),
+    );
+    // 08:10 and 16:37 CET
+    assert_events(
+        events(ZURICH, date(2025, 12, 21)),
+        utc(2025, 12, 21, 7, 10),
+        utc(2025, 12, 21, 15, 37),
+    );
+    // 07:16 and 16:31 EST
+    assert_events(
+        events(NEW_YORK, date(2025, 12, 21)),
+        utc(2025, 12, 21, 12, 16),
+        utc(2025, 12, 21, 21, 31),
+    );
+}
+
+// The local morning is the previous day in UTC
+#[test]
+fn southern_hemisphere_east_of_greenwich() {
+    // 05:47 and 20:09 AEDT
+    assert_events(
+        events(SYDNEY, date(2025, 1, 1)),
+        utc(2024, 12, 31, 18, 47),
+        utc(2025, 1, 1, 9, 9),
+    );
+}
+
+#[test]
+fn midnight_sun_and_polar_night() {
+    assert_eq!(events(TROMSO, date(2025, 6, 21)), SunEvents::PolarDay);
+    assert_eq!(events(TROMSO, date(2025, 12, 21)), SunEvents::PolarNight);
+}
+
+#[test]
+fn elevation_follows_the_day() {
+    let noon = position(ZURICH, utc(2025, 6, 21, 11, 28));
+    // 90° - latitude + tilt of the axis
+    assert!((noon.elevation - 66.1).abs() < 0.5, "{noon:?}");
+
+    // The center of the sun is a bit below the horizon at sunrise and sunset
+    let sunrise = position(ZURICH, utc(2025, 6, 21, 3, 29));
+    assert!((sunrise.elevation + 0.8).abs() < 0.5, "{sunrise:?}");
+    assert!(sunrise.rising);
+
+    let sunset = position(ZURICH, utc(2025, 6, 21, 19, 26));
+    assert!((sunset.elevation + 0.8).abs() < 0.5, "{sunset:?}");
+    assert!(!sunset.rising);
+
+    assert!(position(ZURICH, utc(2025, 6, 21, 23, 0)).elevation < -15.0);
+}
+
+#[test]
+fn location_range() {
+    assert_eq!(Location::new(47.37, 8.54), Some(ZURICH));
+    assert_eq!(Location::new(91.0, 0.0), None);
+    assert_eq!(Location::new(0.0, -181.0), None);
+    assert_eq!(ZURICH.to_string(), "47.37,8.54");
+}
+
+#[test]
+fn sky_phases_through_the_day() {
+    let phase = |h, mi| SkyPhase::at(Some(ZURICH), utc(2025, 6, 21, h, mi));
+    assert_eq!(phase(1, 0), SkyPhase::Night);
+    assert_eq!(phase(3, 20), SkyPhase::Dawn);
+    assert_eq!(phase(11, 0), SkyPhase::Day);
+    assert_eq!(phase(19, 30), SkyPhase::Dusk);
+    assert_eq!(phase(21, 0), SkyPhase::Night);
+    // The sky stays blue without a location
+    assert_eq!(SkyPhase::at(None, utc(2025, 6, 21, 1, 0)), SkyPhase::Day);
+}
+
+#[test]
+fn night_tint_keeps_dark_colors() {
+    let tint = SkyPhase::Night.tint();
+    assert_eq!(tint(0xFFFF), 0x5AEB);
+    // Dark enough to round down to black, which the bitmaps leave out
+    assert_eq!(tint(0x0801), 0x0001);
+    assert_eq!(tint(0x0000), 0x0000);
+}
+
+/// The first minute within two hours of `from` the sky of `location` shows
+/// `phase`.
+fn first_minute_of(phase: SkyPhase, location: Location, from: DateTime<Utc>) -> DateTime<Utc> {
+    (0..120)
+        .map(|minute| from + TimeDelta::minutes(minute))
+        .find(|&time| SkyPhase::at(Some(location), time) == phase)
+        .unwrap_or_else(|| panic!("no {phase:?} within two hours of {from}"))
+}
+
+// The sky follows the elevation of the sun, not the sunrise and sunset of
+// `events`: dawn starts when the sun is 6° below the horizon and the day
+// when it is 6° above. How long that takes around the sunrise depends on the
+// latitude and the season.
+#[test]
+fn day_starts_after_the_sunrise() {
+    // Minutes from the start of dawn to the sunrise and from the sunrise to
+    // the day, from the hour angles of the sun at -6°, -0.833° and 6°
+    for (date, dawn, day) in [(date(2025, 6, 21), 40, 48), (date(2025, 12, 21), 36, 53)] {
+        let SunEvents::Daily { sunrise, .. } = events(ZURICH, date) else {
+            panic!("no sunrise on {date}");
+        };
+        assert_eq!(SkyPhase::at(Some(ZURICH), sunrise), SkyPhase::Dawn);
+
+        let dawn_start = first_minute_of(SkyPhase::Dawn, ZURICH, sunrise - TimeDelta::hours(2));
+        let minutes = (sunrise - dawn_start).num_minutes();
+        assert!(
+            (minutes - dawn).abs() <= 2,
+            "dawn {minutes} min before the sunrise on {date}"
+        );
+        let day_start = first_minute_of(SkyPhase::Day, ZURICH, sunrise);
+        let minutes = (day_start - sunrise).num_minutes();
+        assert!(
+            (minutes - day).abs() <= 2,
+            "day {minutes} min after the sunrise on {date}"
+        );
+    }
+}
@@ -0,0 +1,150 @@
+mod common;
+
+use core::time::Duration;
+
+use chrono::{TimeZone, Utc};
+use common::Fixture;
+use mario_clock_core::{
+    command::{Command, CommandError},
+    time::TimeSource,
+    timer::{Timer, TimerReading, MAX_COUNTDOWN},
+};
+
+const MINUTE: u64 = 60_000;
+const HOUR: u64 = 60 * MINUTE;
+
+#[test]
+fn countdown_shows_minutes_and_seconds() {
+    let timer = Timer::new();
+    assert_eq!(timer.reading(0), None);
+
+    timer.start_countdown(Duration::from_secs(5 * 60), 1_000);
+    let reading = timer.reading(1_000).unwrap();
+    assert_eq!(reading, TimerReading::Countdown(Duration::from_secs(300)));
+    assert_eq!(reading.blocks(), (5, 0));
+    // Rounded up, the last second shows 00:01 until it ran out
+    assert_eq!(timer.reading(1_999).unwrap().blocks(), (5, 0));
+    assert_eq!(timer.reading(2_000).unwrap().blocks(), (4, 59));
+    assert_eq!(timer.reading(300_999).unwrap().blocks(), (0, 1));
+    assert!(!timer.reading(300_999).unwrap().is_done());
+
+    let done = timer.reading(301_000).unwrap();
+    assert_eq!(done.blocks(), (0, 0));
+    assert!(done.is_done());
+    assert_eq!(timer.reading(10 * HOUR).unwrap(), done);
+}
+
+#[test]
+fn long_timers_show_hours_and_minutes() {
+    let timer = Timer::new();
+    timer.start_countdown(Duration::from_secs(90 * 60), 0);
+    assert_eq!(timer.reading(0).unwrap().blocks(), (1, 30));
+    assert_eq!(timer.reading(1_000).unwrap().blocks(), (1, 29));
+    assert_eq!(timer.reading(30 * MINUTE).unwrap().blocks(), (1, 0));
+    assert_eq!(
+        timer.reading(30 * MINUTE + 1_000).unwrap().blocks(),
+        (59, 59)
+    );
+
+    timer.start_stopwatch(0);
+    assert_eq!(timer.reading(HOUR - 1).unwrap().blocks(), (59, 59));
+    assert_eq!(timer.reading(HOUR).unwrap().blocks(), (1, 0));
+    assert_eq!(timer.reading(200 * HOUR).unwrap().blocks(), (99, 0));
+
+    timer.start_countdown(Duration::from_secs(1_000 * 3600), 0);
+    assert_eq!(
+        timer.reading(0),
+        Some(TimerReading::Countdown(MAX_COUNTDOWN))
+    );
+}
+
+#[test]
+fn pause_and_resume() {
+    let timer = Timer::new();
+    assert!(!timer.pause(0));
+    assert!(!timer.resume(0));
+
+    timer.start_stopwatch(1_000);
+    assert!(timer.pause(6_000));
+    assert!(timer.is_paused());
+    assert_eq!(timer.reading(60_000).unwrap().blocks(), (0, 5));
+
+    assert!(timer.resume(60_000));
+    assert!(!timer.is_paused());
+    // Resuming a running stopwatch changes nothing
+    assert!(timer.resume(61_000));
+    assert_eq!(timer.reading(62_500).unwrap().blocks(), (0, 7));
+
+    timer.stop();
+    assert_eq!(timer.reading(62_500), None);
+    assert!(!timer.is_paused());
+}
+
+#[test]
+fn timer_commands() {
+    let fixture = Fixture::default();
+    let Fixture { time, .. } = &fixture;
+    let context = fixture.context();
+
+    assert_eq!(Command::parse("timer"), Ok(Command::ShowTimer));
+    assert_eq!(
+        Command::parse("timer 05:00"),
+        Ok(Command::StartCountdown(Duration::from_secs(300)))
+    );
+    assert_eq!(
+        Command::parse("timer 90:00"),
+        Ok(Command::StartCountdown(Duration::from_secs(90 * 60)))
+    );
+    assert_eq!(
+        Command::parse("timer 1:30:00"),
+        Ok(Command::StartCountdown(Duration::from_secs(90 * 60)))
+    );
+    for invalid in [
+        "timer 5",
+        "timer 00:00",
+        "timer 5:60",
+        "timer 100:00:00",
+        "timer 1:2:3:4",
+        "timer 99999999999999999:00",
+        "timer -1:00",
+        "timer later",
+    ] {
+        assert_eq!(
+            Command::parse(invalid),
+            Err(CommandError::InvalidArgument),
+            "{invalid}"
+        );
+    }
+
+    assert_eq!(
+        Command::ShowTimer.execute(&context),
+        "timer off, showing the clock"
+    );
+    assert_eq!(
+        Command::parse("timer 05:00").unwrap().execute(&context),
+        "countdown 00:05:00 left"
+    );
+    time.advance(Duration::from_secs(61));
+    assert_eq!(
+        Command::PauseTimer.execute(&context),
+        "countdown 00:03:59 left, paused"
+    );
+    // Steps of the wall clock don't affect the timer
+    time.set(Utc.with_ymd_and_hms(2025, 6, 15, 18, 0, 0).unwrap());
+    time.advance(Duration::from_secs(10));
+    assert_eq!(
+        Command::ResumeTimer.execute(&context),
+        "countdown 00:03:59 left"
+    );
+    assert_eq!(
+        Command::StartStopwatch.execute(&context),
+        "stopwatch 00:00:00"
+    );
+    time.advance(Duration::from_secs(3_723));
+    assert_eq!(Command::ShowTimer.execute(&context), "stopwatch 01:02:03");
+    assert_eq!(
+        Command::StopTimer.execute(&context),
+        "timer off, showing the clock"
+    );
+    assert_eq!(time.millis(), 3_794_000);
+}
@@ -0,0 +1,112 @@
+use embedded_graphics::{
+    mock_display::MockDisplay,
+    pixelcolor::Rgb888,
+    prelude::{DrawTarget, Point, RgbColor},
+    Pixel,
+};
+use mario_clock_core::transition::{
+    blend, Transition, TransitionKind, EMPTY_SNAPSHOT, TRANSITION_MS,
+};
+
+const FROM: Rgb888 = Rgb888::RED;
+const TO: Rgb888 = Rgb888::BLUE;
+
+/// Pixels of the display showing the incoming face at `progress`.
+fn incoming(kind: TransitionKind, progress: u8) -> usize {
+    (0..64)
+        .flat_map(|y| (0..64).map(move |x| (x, y)))
+        .filter(|&(x, y)| blend(kind, x, y, progress, FROM, TO) == TO)
+        .count()
+}
+
+#[test]
+fn starts_on_the_outgoing_and_ends_on_the_incoming_face() {
+    for kind in [
+        TransitionKind::Fade,
+        TransitionKind::Wipe,
+        TransitionKind::Dissolve,
+    ] {
+        assert_eq!(incoming(kind, 0), 0, "{kind:?}");
+        assert_eq!(incoming(kind, 255), 64 * 64, "{kind:?}");
+        assert!(incoming(kind, 100) <= incoming(kind, 200), "{kind:?}");
+    }
+}
+
+#[test]
+fn fade_mixes_the_colors() {
+    assert_eq!(
+        blend(
+            TransitionKind::Fade,
+            0,
+            0,
+            128,
+            Rgb888::BLACK,
+            Rgb888::WHITE
+        ),
+        Rgb888::new(128, 128, 128)
+    );
+}
+
+#[test]
+fn wipe_moves_from_left_to_right() {
+    assert_eq!(blend(TransitionKind::Wipe, 31, 10, 128, FROM, TO), TO);
+    assert_eq!(blend(TransitionKind::Wipe, 32, 10, 128, FROM, TO), FROM);
+    assert_eq!(incoming(TransitionKind::Wipe, 128), 32 * 64);
+}
+
+#[test]
+fn dissolve_replaces_about_half_the_pixels_halfway() {
+    let replaced = incoming(TransitionKind::Dissolve, 128);
+    assert!((1800..2300).contains(&replaced), "{replaced}");
+}
+
+#[test]
+fn iris_closes_to_black_between_the_faces() {
+    let center = |progress| blend(TransitionKind::Iris, 32, 32, progress, FROM, TO);
+    let corner = |progress| blend(TransitionKind::Iris, 0, 0, progress, FROM, TO);
+    assert_eq!((center(0), corner(0)), (FROM, FROM));
+    assert_eq!((center(64), corner(64)), (FROM, Rgb888::BLACK));
+    assert_eq!(corner(127), Rgb888::BLACK);
+    assert_eq!((center(192), corner(192)), (TO, Rgb888::BLACK));
+    assert_eq!((center(255), corner(255)), (TO, TO));
+}
+
+#[test]
+fn progress_runs_for_the_transition_time() {
+    let transition = Transition::new(TransitionKind::Wipe, 1000);
+    assert_eq!(transition.progress(1000), Some(0));
+    assert_eq!(transition.progress(1000 + TRANSITION_MS / 2), Some(127));
+    assert_eq!(transition.progress(1000 + TRANSITION_MS), None);
+    // A cut is done right away
+    assert_eq!(
+        Transition::new(TransitionKind::Cut, 1000).progress(1000),
+        None
+    );
+}
+
+#[test]
+fn the_outgoing_face_is_recorded_into_the_snapshot() {
+    let transition = Transition::new(TransitionKind::Wipe, 0);
+    let mut snapshot = EMPTY_SNAPSHOT;
+    let mut display = MockDisplay::<Rgb888>::new();
+    display.set_allow_overdraw(true);
+    let pixels = [
+        Pixel(Point::new(1, 0), FROM),
+        Pixel(Point::new(40, 0), FROM),
+    ];
+    transition
+        .record(&mut display, &mut snapshot)
+        .draw_iter(pixels)
+        .unwrap();
+    assert_eq!(snapshot[1], FROM);
+    assert_eq!(snapshot[40], FROM);
+
+    // Halfway the left half shows the incoming face
+    let pixels = [Pixel(Point::new(1, 0), TO), Pixel(Point::new(40, 0), TO)];
+    transition
+        .blend(&mut display, &snapshot, 128)
+        .draw_iter(pixels)
+        .unwrap();
+    assert_eq!(display.get_pixel(Point::new(1, 0)), Some(TO));
+    assert_eq!(display.get_pixel(Point::new(40, 0)), Some(FROM));
+}
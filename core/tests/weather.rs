@@ -0,0 +1,214 @@
+//! Sets the weather condition and checks what its effects draw.
+
+mod common;
+
+use core::convert::Infallible;
+use std::collections::BTreeSet;
+
+use common::Fixture;
+use embedded_graphics::{
+    pixelcolor::Rgb888,
+    prelude::{DrawTarget, OriginDimensions, Size},
+    Pixel,
+};
+use mario_clock_core::{
+    command::{Command, CommandError},
+    mario::{
+        sky::SkyPhase,
+        weather::{self, MAX_PARTICLES},
+    },
+    settings::TemperatureUnit,
+    time::TimeSource,
+    weather::{Weather, WeatherCondition, TEMPERATURE_MAX_AGE_MINUTES},
+};
+
+/// Records the pixels drawn to.
+#[derive(Default)]
+struct Pixels(BTreeSet<(i32, i32)>);
+
+impl OriginDimensions for Pixels {
+    fn size(&self) -> Size {
+        Size::new(64, 64)
+    }
+}
+
+impl DrawTarget for Pixels {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.0
+            .extend(pixels.into_iter().map(|Pixel(point, _)| (point.x, point.y)));
+        Ok(())
+    }
+}
+
+fn pixels(condition: WeatherCondition, sky: SkyPhase, millis: u64) -> BTreeSet<(i32, i32)> {
+    let mut fb = Pixels::default();
+    weather::draw(condition, sky, &mut fb, millis);
+    fb.0
+}
+
+#[test]
+fn starts_without_weather() {
+    let weather = Weather::new();
+    assert_eq!(weather.condition(), None);
+    weather.set_condition(Some(WeatherCondition::Snow));
+    assert_eq!(weather.condition(), Some(WeatherCondition::Snow));
+    weather.set_condition(None);
+    assert_eq!(weather.condition(), None);
+}
+
+#[test]
+fn temperature_goes_stale() {
+    let weather = Weather::new();
+    assert_eq!(weather.temperature(0), None);
+    weather.set_temperature(-3.4, 1_000);
+    assert_eq!(weather.temperature(1_000), Some(-3.4));
+    let max_age = TEMPERATURE_MAX_AGE_MINUTES * 60_000;
+    assert_eq!(weather.temperature(1_000 + max_age), Some(-3.4));
+    assert_eq!(weather.temperature(1_001 + max_age), None);
+}
+
+#[test]
+fn temperature_units() {
+    assert_eq!(TemperatureUnit::Celsius.convert(-3.5), -3.5);
+    assert_eq!(TemperatureUnit::Fahrenheit.convert(-40.0), -40.0);
+    assert_eq!(TemperatureUnit::Fahrenheit.convert(100.0), 212.0);
+    for unit in TemperatureUnit::ALL {
+        assert_eq!(TemperatureUnit::from_name(unit.name()), Some(unit));
+    }
+    assert_eq!(TemperatureUnit::from_name("kelvin"), None);
+}
+
+#[test]
+fn condition_names() {
+    for condition in WeatherCondition::ALL {
+        assert_eq!(
+            WeatherCondition::from_name(condition.name()),
+            Some(condition)
+        );
+    }
+    assert_eq!(WeatherCondition::from_name("hail"), None);
+}
+
+#[test]
+fn rain_is_capped_and_falls() {
+    let drawn = pixels(WeatherCondition::Rain, SkyPhase::Day, 0);
+    assert!(!drawn.is_empty());
+    // A raindrop is a streak of 3 pixels
+    assert!(drawn.len() <= 3 * MAX_PARTICLES);
+    assert!(drawn
+        .iter()
+        .all(|&(x, y)| (0..64).contains(&x) && (-2..64).contains(&y)));
+    assert_ne!(drawn, pixels(WeatherCondition::Rain, SkyPhase::Day, 500));
+}
+
+#[test]
+fn snow_falls_slower_than_rain() {
+    let moved = |condition| {
+        let before = pixels(condition, SkyPhase::Day, 0);
+        let after = pixels(condition, SkyPhase::Day, 200);
+        before.symmetric_difference(&after).count()
+    };
+    let (rain, snow) = (moved(WeatherCondition::Rain), moved(WeatherCondition::Snow));
+    assert!(snow < rain, "snow {snow}, rain {rain}");
+}
+
+#[test]
+fn the_sun_shines_by_day_only() {
+    let drawn = pixels(WeatherCondition::Clear, SkyPhase::Day, 0);
+    assert!(!drawn.is_empty());
+    // In the top right corner, clear of the blocks
+    assert!(drawn.iter().all(|&(x, y)| x >= 50 && y < 14));
+    assert_eq!(
+        pixels(WeatherCondition::Clear, SkyPhase::Dusk, 0).len(),
+        drawn.len()
+    );
+    assert!(pixels(WeatherCondition::Clear, SkyPhase::Night, 0).is_empty());
+    // The rays take turns
+    assert_ne!(drawn, pixels(WeatherCondition::Clear, SkyPhase::Day, 500));
+}
+
+#[test]
+fn cloudy_adds_nothing() {
+    assert!(pixels(WeatherCondition::Cloudy, SkyPhase::Day, 0).is_empty());
+}
+
+#[test]
+fn fog_drifts() {
+    let drawn = pixels(WeatherCondition::Fog, SkyPhase::Night, 0);
+    assert!(!drawn.is_empty());
+    assert_ne!(drawn, pixels(WeatherCondition::Fog, SkyPhase::Night, 500));
+}
+
+#[test]
+fn weather_commands() {
+    let fixture = Fixture::default();
+    let Fixture { weather, .. } = &fixture;
+    let context = fixture.context();
+
+    assert_eq!(Command::parse("weather"), Ok(Command::ShowWeather));
+    assert_eq!(
+        Command::parse("weather rain"),
+        Ok(Command::SetWeather(Some(WeatherCondition::Rain)))
+    );
+    assert_eq!(Command::parse("weather off"), Ok(Command::SetWeather(None)));
+    assert_eq!(
+        Command::parse("weather hail"),
+        Err(CommandError::InvalidArgument)
+    );
+    assert_eq!(
+        Command::parse("weather rain snow"),
+        Err(CommandError::InvalidArgument)
+    );
+
+    assert_eq!(Command::ShowWeather.execute(&context), "weather: off");
+    let reply = Command::parse("weather snow").unwrap().execute(&context);
+    assert_eq!(reply, "weather: snow");
+    assert_eq!(weather.condition(), Some(WeatherCondition::Snow));
+    let reply = Command::parse("weather off").unwrap().execute(&context);
+    assert_eq!(reply, "weather: off");
+    assert_eq!(weather.condition(), None);
+}
+
+#[test]
+fn temperature_commands() {
+    let fixture = Fixture::default();
+    let Fixture {
+        settings,
+        time,
+        weather,
+        ..
+    } = &fixture;
+    let context = fixture.context();
+
+    assert_eq!(
+        Command::ShowTemperature.execute(&context),
+        "temperature: unknown, shown in celsius"
+    );
+    weather.set_temperature(21.5, time.millis());
+    assert_eq!(
+        Command::parse("temperature").unwrap().execute(&context),
+        "temperature: 21.5 C, shown in celsius"
+    );
+    let reply = Command::parse("temperature fahrenheit")
+        .unwrap()
+        .execute(&context);
+    assert_eq!(reply, "temperature: 70.7 F, shown in fahrenheit");
+    assert_eq!(settings.temperature_unit(), TemperatureUnit::Fahrenheit);
+    let reply = Command::parse("temperature off").unwrap().execute(&context);
+    assert_eq!(reply, "temperature: 70.7 F, hidden in fahrenheit");
+    assert!(!settings.show_temperature());
+    assert_eq!(
+        Command::parse("temperature on"),
+        Ok(Command::SetShowTemperature(true))
+    );
+    assert_eq!(
+        Command::parse("temperature kelvin"),
+        Err(CommandError::InvalidArgument)
+    );
+}
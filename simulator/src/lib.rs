@@ -0,0 +1,133 @@
+//! Host side rendering of the clockfaces.
+//!
+//! [`FrameBuffer`] is a plain RGB888 [`DrawTarget`] with the size of the HUB75
+//! panel. Time is driven by [`mario_clock_core::time::ManualTime`].
+
+use std::{
+    convert::Infallible,
+    fs::File,
+    io::{self, BufWriter},
+    path::Path,
+};
+
+use embedded_graphics::{
+    pixelcolor::{Rgb888, RgbColor},
+    prelude::{DrawTarget, OriginDimensions, Size},
+    Pixel,
+};
+use mario_clock_core::{COLS, ROWS};
+
+/// An in-memory framebuffer with the same dimensions as the LED matrix.
+pub struct FrameBuffer {
+    pixels: [[Rgb888; COLS]; ROWS],
+}
+
+impl Default for FrameBuffer {
+    fn default() -> Self {
+        Self {
+            pixels: [[Rgb888::BLACK; COLS]; ROWS],
+        }
+    }
+}
+
+impl FrameBuffer {
+    pub fn pixel(&self, x: usize, y: usize) -> Rgb888 {
+        self.pixels[y][x]
+    }
+
+    /// Reads a frame written by [`FrameBuffer::save_png`] with a scale of 1.
+    pub fn load_png(path: &Path) -> Result<Self, png::DecodingError> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info()?;
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data)?;
+
+        if info.width as usize != COLS
+            || info.height as usize != ROWS
+            || info.color_type != png::ColorType::Rgb
+            || info.bit_depth != png::BitDepth::Eight
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected a {COLS}x{ROWS} RGB image"),
+            )
+            .into());
+        }
+
+        let mut fb = Self::default();
+        for (i, rgb) in data.chunks_exact(3).take(COLS * ROWS).enumerate() {
+            fb.pixels[i / COLS][i % COLS] = Rgb888::new(rgb[0], rgb[1], rgb[2]);
+        }
+        Ok(fb)
+    }
+
+    /// Compares two frames pixel by pixel. Returns the number of differing
+    /// pixels and an image with the differences in red on top of a
+    /// grayscale copy of `self`.
+    pub fn diff(&self, other: &FrameBuffer) -> (usize, FrameBuffer) {
+        let mut count = 0;
+        let mut diff = FrameBuffer::default();
+        for y in 0..ROWS {
+            for x in 0..COLS {
+                let (a, b) = (self.pixels[y][x], other.pixels[y][x]);
+                diff.pixels[y][x] = if a == b {
+                    let luma = ((a.r() as u16 + a.g() as u16 + a.b() as u16) / 3) as u8;
+                    Rgb888::new(luma, luma, luma)
+                } else {
+                    count += 1;
+                    Rgb888::RED
+                };
+            }
+        }
+        (count, diff)
+    }
+
+    /// Writes the frame as RGB PNG, every pixel scaled up to a `scale` x `scale` square.
+    pub fn save_png(&self, path: &Path, scale: u32) -> Result<(), png::EncodingError> {
+        let scale = scale.max(1) as usize;
+        let width = COLS * scale;
+        let height = ROWS * scale;
+
+        let mut data = Vec::with_capacity(width * height * 3);
+        for row in &self.pixels {
+            for _ in 0..scale {
+                for color in row {
+                    for _ in 0..scale {
+                        data.extend_from_slice(&[color.r(), color.g(), color.b()]);
+                    }
+                }
+            }
+        }
+
+        let writer = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)
+    }
+}
+
+impl OriginDimensions for FrameBuffer {
+    fn size(&self) -> Size {
+        Size::new(COLS as u32, ROWS as u32)
+    }
+}
+
+impl DrawTarget for FrameBuffer {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if (0..COLS as i32).contains(&point.x) && (0..ROWS as i32).contains(&point.y) {
+                self.pixels[point.y as usize][point.x as usize] = color;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,318 @@
+use std::{num::ParseIntError, path::PathBuf, process::exit, str::FromStr, time::Duration};
+
+use chrono::{DateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use embassy_futures::block_on;
+use heapless::Vec;
+use mario_clock_core::{
+    alarm::{Alarm, AlarmClock, Weekdays},
+    engine::EventChannel,
+    faces::{parse_rotation, Faces},
+    sensor::{Reading, SensorReadings},
+    settings::{
+        FaceId, HourFormat, RotationStep, Scenery, Settings, TemperatureUnit, MAX_ROTATION_STEPS,
+    },
+    sun::Location,
+    time::{ManualTime, TimeSource},
+    timer::{parse_duration, Timer},
+    transition::{TransitionKind, EMPTY_SNAPSHOT},
+    weather::{Weather, WeatherCondition},
+    ClockfaceTrait,
+};
+use mario_clock_simulator::FrameBuffer;
+
+const USAGE: &str = "Usage: mario-clock-simulator [OPTIONS]
+
+Renders the Mario clockface frame by frame into PNG files.
+
+Options:
+  --start <RFC3339>  wall clock time of the first frame, e.g. 2025-01-01T12:59:58+01:00 [default: now]
+  --tz <IANA>        time zone the clock displays [default: Europe/Zurich]
+  --hours <12|24>    12 hour clock with AM/PM or 24 hour clock [default: 24]
+  --zero <on|off>    leading zero on hours below 10 [default: on]
+  --alarm <HH:MM>    daily alarm that rings in the rendered frames, e.g. 07:00
+  --face <NAME>      face to show, mario or date [default: mario]
+  --rotate <FACES>   faces shown in turn with their seconds and optionally the
+                     transition to them, e.g. mario:55,date:5:iris
+  --transition <KIND>
+                     transition between faces: cut, fade, wipe, dissolve or iris
+                     [default: fade]
+  --timer <MM:SS>    count down from the first frame instead of showing the time,
+                     or `watch` for the stopwatch, e.g. 00:03
+  --location <LAT,LON>
+                     where the clock is, paints the sky for the time of day,
+                     e.g. 47.37,8.54 [default: always day]
+  --scenery <MODE>   still, clouds drifting or scroll the hills and bushes too
+                     [default: clouds]
+  --weather <KIND>   clear, cloudy, rain, snow or fog in front of the scenery
+                     [default: none]
+  --temperature <C>  outside temperature in degrees Celsius, e.g. -3.4
+                     [default: unknown]
+  --unit <UNIT>      celsius or fahrenheit [default: celsius]
+  --indoor <C[,RH]>  reading of the local sensor in degrees Celsius and
+                     optionally percent humidity, e.g. 21.5,45 [default: none]
+  --frames <N>       number of frames to render [default: 60]
+  --step-ms <MS>     time between two frames in milliseconds [default: 50]
+  --scale <N>        scale factor of the written images [default: 4]
+  --out <DIR>        output directory [default: frames]";
+
+enum TimerMode {
+    Countdown(Duration),
+    Stopwatch,
+}
+
+struct Args {
+    start: DateTime<Utc>,
+    time_zone: Tz,
+    hour_format: HourFormat,
+    leading_zero: bool,
+    alarm: Option<NaiveTime>,
+    timer: Option<TimerMode>,
+    face: FaceId,
+    rotation: Vec<RotationStep, MAX_ROTATION_STEPS>,
+    transition: TransitionKind,
+    location: Option<Location>,
+    scenery: Scenery,
+    weather: Option<WeatherCondition>,
+    temperature: Option<f32>,
+    unit: TemperatureUnit,
+    indoor: Option<Reading>,
+    frames: u32,
+    step_ms: u64,
+    scale: u32,
+    out: PathBuf,
+}
+
+fn parse_number<T: FromStr<Err = ParseIntError>>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|e| format!("invalid {arg} {value}: {e}"))
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        start: Utc::now(),
+        time_zone: chrono_tz::Europe::Zurich,
+        hour_format: HourFormat::H24,
+        leading_zero: true,
+        alarm: None,
+        timer: None,
+        face: FaceId::Mario,
+        rotation: Vec::new(),
+        transition: TransitionKind::Fade,
+        location: None,
+        scenery: Scenery::Clouds,
+        weather: None,
+        temperature: None,
+        unit: TemperatureUnit::Celsius,
+        indoor: None,
+        frames: 60,
+        step_ms: 50,
+        scale: 4,
+        out: PathBuf::from("frames"),
+    };
+
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "-h" || arg == "--help" {
+            println!("{USAGE}");
+            exit(0);
+        }
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("missing value for {arg}"))?;
+        match arg.as_str() {
+            "--start" => {
+                args.start = DateTime::parse_from_rfc3339(&value)
+                    .map_err(|e| format!("invalid --start {value}: {e}"))?
+                    .with_timezone(&Utc)
+            }
+            "--tz" => {
+                args.time_zone = value
+                    .parse()
+                    .map_err(|e| format!("invalid --tz {value}: {e}"))?
+            }
+            "--hours" => {
+                args.hour_format = value
+                    .parse()
+                    .ok()
+                    .and_then(HourFormat::from_hours)
+                    .ok_or_else(|| format!("invalid --hours {value}, expected 12 or 24"))?
+            }
+            "--zero" => {
+                args.leading_zero = match value.as_str() {
+                    "on" => true,
+                    "off" => false,
+                    _ => return Err(format!("invalid --zero {value}, expected on or off")),
+                }
+            }
+            "--alarm" => {
+                args.alarm = Some(
+                    NaiveTime::parse_from_str(&value, "%H:%M")
+                        .map_err(|e| format!("invalid --alarm {value}: {e}"))?,
+                )
+            }
+            "--timer" => {
+                args.timer = Some(match value.as_str() {
+                    "watch" => TimerMode::Stopwatch,
+                    _ => TimerMode::Countdown(parse_duration(&value).ok_or_else(|| {
+                        format!("invalid --timer {value}, expected [HH:]MM:SS or watch")
+                    })?),
+                })
+            }
+            "--face" => {
+                args.face = FaceId::from_name(&value)
+                    .ok_or_else(|| format!("invalid --face {value}, expected mario or date"))?
+            }
+            "--rotate" => {
+                args.rotation = parse_rotation(&value).ok_or_else(|| {
+                    format!("invalid --rotate {value}, expected e.g. mario:55,date:5")
+                })?
+            }
+            "--transition" => {
+                args.transition = TransitionKind::from_name(&value).ok_or_else(|| {
+                    format!(
+                        "invalid --transition {value}, expected cut, fade, wipe, dissolve or iris"
+                    )
+                })?
+            }
+            "--location" => {
+                args.location = Some(
+                    value
+                        .split_once(',')
+                        .and_then(|(latitude, longitude)| {
+                            Location::new(latitude.parse().ok()?, longitude.parse().ok()?)
+                        })
+                        .ok_or_else(|| {
+                            format!("invalid --location {value}, expected e.g. 47.37,8.54")
+                        })?,
+                )
+            }
+            "--scenery" => {
+                args.scenery = Scenery::from_name(&value).ok_or_else(|| {
+                    format!("invalid --scenery {value}, expected still, clouds or scroll")
+                })?
+            }
+            "--weather" => {
+                args.weather = Some(WeatherCondition::from_name(&value).ok_or_else(|| {
+                    format!("invalid --weather {value}, expected clear, cloudy, rain, snow or fog")
+                })?)
+            }
+            "--temperature" => {
+                args.temperature = Some(
+                    value
+                        .parse()
+                        .map_err(|e| format!("invalid --temperature {value}: {e}"))?,
+                )
+            }
+            "--unit" => {
+                args.unit = TemperatureUnit::from_name(&value).ok_or_else(|| {
+                    format!("invalid --unit {value}, expected celsius or fahrenheit")
+                })?
+            }
+            "--indoor" => {
+                let (temperature, humidity) = match value.split_once(',') {
+                    Some((temperature, humidity)) => (temperature, Some(humidity)),
+                    None => (value.as_str(), None),
+                };
+                args.indoor = Some(
+                    temperature
+                        .parse()
+                        .ok()
+                        .and_then(|temperature| {
+                            Some(Reading {
+                                temperature,
+                                humidity: humidity.map(str::parse).transpose().ok()?,
+                                pressure: None,
+                            })
+                        })
+                        .ok_or_else(|| {
+                            format!("invalid --indoor {value}, expected e.g. 21.5,45")
+                        })?,
+                )
+            }
+            "--frames" => args.frames = parse_number(&arg, &value)?,
+            "--step-ms" => args.step_ms = parse_number(&arg, &value)?,
+            "--scale" => args.scale = parse_number(&arg, &value)?,
+            "--out" => args.out = PathBuf::from(value),
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+
+    Ok(args)
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        exit(2);
+    });
+
+    if let Err(e) = std::fs::create_dir_all(&args.out) {
+        eprintln!("Unable to create {}: {e}", args.out.display());
+        exit(1);
+    }
+
+    let time = ManualTime::new(args.start);
+    let channel = Box::leak(Box::new(EventChannel::new()));
+    let settings = Box::leak(Box::new(Settings::new(args.time_zone)));
+    settings.set_hour_format(args.hour_format);
+    settings.set_leading_zero(args.leading_zero);
+    settings.set_active_face(args.face);
+    settings.set_rotation(args.rotation);
+    settings.set_transition(args.transition);
+    settings.set_location(args.location);
+    settings.set_scenery(args.scenery);
+    settings.set_temperature_unit(args.unit);
+    if let Some(alarm) = args.alarm {
+        let alarm = Alarm {
+            time: alarm,
+            weekdays: Weekdays::EVERY_DAY,
+            recurring: true,
+            enabled: true,
+            label: Default::default(),
+        };
+        // The settings start without alarms, there is room for one
+        let _ = settings.add_alarm(alarm);
+    }
+    let alarms = Box::leak(Box::new(AlarmClock::new()));
+    let timer = Box::leak(Box::new(Timer::new()));
+    match args.timer {
+        Some(TimerMode::Countdown(duration)) => timer.start_countdown(duration, time.millis()),
+        Some(TimerMode::Stopwatch) => timer.start_stopwatch(time.millis()),
+        None => {}
+    }
+    let weather = Box::leak(Box::new(Weather::new()));
+    weather.set_condition(args.weather);
+    if let Some(celsius) = args.temperature {
+        weather.set_temperature(celsius, time.millis());
+    }
+    let sensor = Box::leak(Box::new(SensorReadings::new()));
+    if let Some(reading) = args.indoor {
+        sensor.publish("simulated", reading, time.millis());
+    }
+    let snapshot = Box::leak(Box::new(EMPTY_SNAPSHOT));
+    let mut cf = Faces::new(channel, &time, settings, alarms, timer, weather, sensor)
+        .with_snapshot(snapshot);
+    let mut fb = FrameBuffer::default();
+
+    for frame in 0..args.frames {
+        alarms.check(settings, time.now().with_timezone(&settings.time_zone()));
+        block_on(cf.update(&mut fb));
+
+        let path = args.out.join(format!("frame_{frame:04}.png"));
+        if let Err(e) = fb.save_png(&path, args.scale) {
+            eprintln!("Unable to write {}: {e}", path.display());
+            exit(1);
+        }
+
+        time.advance(Duration::from_millis(args.step_ms));
+    }
+
+    println!(
+        "Rendered {} frames starting at {} to {}",
+        args.frames,
+        args.start,
+        args.out.display()
+    );
+}
@@ -0,0 +1,528 @@
+//! Renders the Mario clockface at fixed wall clock times and compares the
+//! result pixel by pixel against the reference images in `tests/golden/`.
+//!
+//! On a mismatch the actual frame and a diff image (differences in red) are
+//! written to the cargo target tmp directory. Run with `UPDATE_GOLDEN=1` to
+//! replace the references after an intended scene change.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, NaiveTime, Utc};
+use embassy_futures::block_on;
+use mario_clock_core::{
+    alarm::{Alarm, AlarmClock, Weekdays},
+    engine::EventChannel,
+    faces::{parse_rotation, Faces},
+    sensor::{Reading, SensorReadings},
+    settings::{FaceId, HourFormat, Scenery, Settings, TemperatureUnit},
+    sun::Location,
+    time::{ManualTime, TimeSource},
+    timer::Timer,
+    transition::{TransitionKind, EMPTY_SNAPSHOT},
+    weather::{Weather, WeatherCondition},
+    ClockfaceTrait,
+};
+use mario_clock_simulator::FrameBuffer;
+
+/// Time between two rendered frames, roughly the refresh rate of the device.
+const FRAME_MS: u64 = 50;
+
+/// A clock booted at a fixed wall clock time. Settings, the timer, the
+/// weather and the local sensor start out as on a fresh device and are set
+/// up with the builder methods before rendering.
+struct Scene {
+    time: ManualTime,
+    settings: &'static Settings,
+    timer: &'static Timer,
+    weather: &'static Weather,
+    sensor: &'static SensorReadings,
+    step: Option<(u32, DateTime<Utc>)>,
+}
+
+impl Scene {
+    fn at(start: &str) -> Self {
+        Scene::new(ManualTime::new(parse(start)))
+    }
+
+    fn new(time: ManualTime) -> Self {
+        Scene {
+            time,
+            settings: Box::leak(Box::new(Settings::new(chrono_tz::Europe::Zurich))),
+            timer: Box::leak(Box::new(Timer::new())),
+            weather: Box::leak(Box::new(Weather::new())),
+            sensor: Box::leak(Box::new(SensorReadings::new())),
+            step: None,
+        }
+    }
+
+    fn settings(self, f: impl FnOnce(&Settings)) -> Self {
+        f(self.settings);
+        self
+    }
+
+    /// Places the clock in Zurich, the sky follows the sun.
+    fn in_zurich(self) -> Self {
+        self.settings(|settings| settings.set_location(Location::new(47.37, 8.54)))
+    }
+
+    /// Sets up the timer, given the uptime at boot.
+    fn timer(self, f: impl FnOnce(&Timer, u64)) -> Self {
+        f(self.timer, self.time.millis());
+        self
+    }
+
+    /// Sets up the weather, reports are fetched at boot.
+    fn weather(self, f: impl FnOnce(&Weather)) -> Self {
+        f(self.weather);
+        self
+    }
+
+    /// Publishes `reading` of the local sensor at boot.
+    fn sensor(self, reading: Reading) -> Self {
+        self.sensor.publish("sht3x", reading, self.time.millis());
+        self
+    }
+
+    /// Steps the clock to `to` before rendering `frame`, like a large
+    /// correction of a sync.
+    fn step(mut self, frame: u32, to: &str) -> Self {
+        self.step = Some((frame, parse(to)));
+        self
+    }
+
+    /// Boots the faces and returns the frame rendered after stepping `frame`
+    /// times. The alarms are checked before every frame.
+    fn render(self, frame: u32) -> FrameBuffer {
+        let Scene {
+            time,
+            settings,
+            timer,
+            weather,
+            sensor,
+            step,
+        } = self;
+        let channel = Box::leak(Box::new(EventChannel::new()));
+        let alarms = Box::leak(Box::new(AlarmClock::new()));
+        let snapshot = Box::leak(Box::new(EMPTY_SNAPSHOT));
+        let mut faces = Faces::new(channel, &time, settings, alarms, timer, weather, sensor)
+            .with_snapshot(snapshot);
+        let mut fb = FrameBuffer::default();
+
+        for n in 0..=frame {
+            if let Some((at, to)) = step {
+                if n == at {
+                    time.set(to);
+                }
+            }
+            alarms.check(settings, time.now().with_timezone(&settings.time_zone()));
+            block_on(faces.update(&mut fb));
+            if n < frame {
+                time.advance(Duration::from_millis(FRAME_MS));
+            }
+        }
+        fb
+    }
+}
+
+fn parse(time: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(time).unwrap().to_utc()
+}
+
+fn assert_golden(name: &str, start: &str, frame: u32) {
+    assert_golden_frame(name, Scene::at(start).render(frame));
+}
+
+fn assert_golden_frame(name: &str, actual: FrameBuffer) {
+    let reference_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.png"));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        actual.save_png(&reference_path, 1).unwrap();
+        return;
+    }
+
+    let expected = FrameBuffer::load_png(&reference_path).unwrap_or_else(|e| {
+        panic!(
+            "unable to read {}: {e} (run with UPDATE_GOLDEN=1 to create it)",
+            reference_path.display()
+        )
+    });
+
+    let (mismatches, diff) = expected.diff(&actual);
+    if mismatches > 0 {
+        let out = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden");
+        std::fs::create_dir_all(&out).unwrap();
+        let actual_path = out.join(format!("{name}.actual.png"));
+        let diff_path = out.join(format!("{name}.diff.png"));
+        actual.save_png(&actual_path, 1).unwrap();
+        diff.save_png(&diff_path, 4).unwrap();
+
+        panic!(
+            "{name}: {mismatches} pixels differ from {}\n  actual: {}\n  diff:   {}",
+            reference_path.display(),
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+#[test]
+fn midnight() {
+    assert_golden("midnight", "2025-01-01T00:00:30+01:00", 0);
+}
+
+#[test]
+fn before_one_pm() {
+    assert_golden("before_one_pm", "2025-01-01T12:59:30+01:00", 0);
+}
+
+#[test]
+fn before_midnight() {
+    assert_golden("before_midnight", "2025-01-01T23:59:30+01:00", 0);
+}
+
+// Mario jumps on the full minute, one second after boot.
+#[test]
+fn jump_start() {
+    assert_golden("jump_start", "2025-01-01T12:59:59+01:00", 20);
+}
+
+#[test]
+fn mid_jump() {
+    assert_golden("mid_jump", "2025-01-01T12:59:59+01:00", 23);
+}
+
+// Mario hits the blocks on frame 25, they reach their highest point two
+// animation steps later.
+#[test]
+fn block_highest() {
+    assert_golden("block_highest", "2025-01-01T12:59:59+01:00", 29);
+}
+
+// Dashes instead of digits and no jump while the time is unknown, e.g. after
+// the RTC lost power.
+#[test]
+fn time_unknown() {
+    let time = ManualTime::unknown(parse("2025-01-01T12:59:59+01:00"));
+    assert_golden_frame("time_unknown", Scene::new(time).render(29));
+}
+
+// A step over the full minute still makes Mario hit the blocks, at the same
+// pace as without the step.
+#[test]
+fn step_over_minute_jumps() {
+    let actual = Scene::at("2025-01-01T12:59:30+01:00")
+        .step(20, "2025-01-01T13:00:30+01:00")
+        .render(23);
+    assert_golden_frame("mid_jump", actual);
+}
+
+// A step back into the second Mario already jumped for doesn't jump again.
+#[test]
+fn step_back_jumps_once() {
+    // The drifting clouds would differ from the freshly booted clockface
+    let actual = Scene::at("2025-01-01T12:59:59+01:00")
+        .settings(|settings| settings.set_scenery(Scenery::Still))
+        .step(60, "2025-01-01T13:00:00+01:00")
+        .render(63);
+    let expected = Scene::at("2025-01-01T13:00:05+01:00").render(0);
+    assert_eq!(actual.diff(&expected).0, 0);
+}
+
+// A step into the next minute while Mario is still in the air retries the
+// jump once he may jump again, within the full minute's second.
+#[test]
+fn step_mid_jump_jumps_again() {
+    let actual = Scene::at("2025-01-01T12:59:59+01:00")
+        .settings(|settings| settings.set_scenery(Scenery::Still))
+        .step(23, "2025-01-01T13:01:00+01:00")
+        .render(44);
+    let expected = Scene::at("2025-01-01T13:00:59+01:00")
+        .settings(|settings| settings.set_scenery(Scenery::Still))
+        .render(23);
+    assert_eq!(actual.diff(&expected).0, 0);
+}
+
+// 12 hour clock without the leading zero, PM in the top left corner.
+#[test]
+fn twelve_hour_pm() {
+    let actual = Scene::at("2025-01-01T21:05:30+01:00")
+        .settings(|settings| {
+            settings.set_hour_format(HourFormat::H12);
+            settings.set_leading_zero(false);
+        })
+        .render(0);
+    assert_golden_frame("twelve_hour_pm", actual);
+}
+
+// The indicator stays clear of the hour block while it bounces.
+#[test]
+fn twelve_hour_mid_jump() {
+    let actual = Scene::at("2025-01-01T12:59:59+01:00")
+        .settings(|settings| settings.set_hour_format(HourFormat::H12))
+        .render(29);
+    assert_golden_frame("twelve_hour_mid_jump", actual);
+}
+
+// Midnight is 12 AM.
+#[test]
+fn twelve_hour_midnight() {
+    let actual = Scene::at("2025-01-01T00:05:30+01:00")
+        .settings(|settings| settings.set_hour_format(HourFormat::H12))
+        .render(0);
+    assert_golden_frame("twelve_hour_midnight", actual);
+}
+
+// A ringing alarm flashes its label in the top row and keeps Mario jumping.
+#[test]
+fn alarm_ringing() {
+    let actual = Scene::at("2025-01-01T06:59:59+01:00")
+        .settings(|settings| {
+            let alarm = Alarm {
+                time: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+                weekdays: Weekdays::EVERY_DAY,
+                recurring: true,
+                enabled: true,
+                label: "WAKE UP".try_into().unwrap(),
+            };
+            settings.add_alarm(alarm).unwrap();
+        })
+        .render(43);
+    assert_golden_frame("alarm_ringing", actual);
+}
+
+// A countdown shows minutes and seconds on the blocks.
+#[test]
+fn countdown() {
+    let actual = Scene::at("2025-01-01T12:00:00+01:00")
+        .timer(|timer, now| timer.start_countdown(Duration::from_secs(5 * 60), now))
+        .render(40);
+    assert_golden_frame("countdown", actual);
+}
+
+// Mario hits the blocks when the countdown reaches zero, under a flashing
+// TIME UP.
+#[test]
+fn countdown_done() {
+    let actual = Scene::at("2025-01-01T12:00:00+01:00")
+        .timer(|timer, now| timer.start_countdown(Duration::from_secs(1), now))
+        .render(23);
+    assert_golden_frame("countdown_done", actual);
+}
+
+// A stopwatch past an hour shows hours and minutes, paused it flashes.
+#[test]
+fn stopwatch_paused() {
+    let actual = Scene::at("2025-01-01T12:00:00+01:00")
+        .timer(|timer, _| {
+            timer.start_stopwatch(0);
+            timer.pause(3_723_000);
+        })
+        .render(0);
+    assert_golden_frame("stopwatch_paused", actual);
+}
+
+#[test]
+fn date_face() {
+    let actual = Scene::at("2025-01-01T12:59:30+01:00")
+        .settings(|settings| settings.set_active_face(FaceId::Date))
+        .render(0);
+    assert_golden_frame("date_face", actual);
+}
+
+// The date is shown for the last 5 seconds of every minute, Mario still
+// hits the blocks on the full minute.
+#[test]
+fn rotation() {
+    let rotate = |settings: &Settings| {
+        settings.set_transition(TransitionKind::Cut);
+        settings.set_rotation(parse_rotation("mario:55,date:5").unwrap());
+    };
+    let date = Scene::at("2025-01-01T12:59:55+01:00")
+        .settings(rotate)
+        .render(0);
+    assert_golden_frame("date_face", date);
+    // Back on the full minute, Mario hits the blocks right away
+    let actual = Scene::at("2025-01-01T12:59:58+01:00")
+        .settings(rotate)
+        .render(43);
+    assert_golden_frame("mid_jump", actual);
+}
+
+/// Renders the switch from Mario to the date with `kind`, 300 ms into the
+/// transition.
+fn render_transition(kind: TransitionKind) -> FrameBuffer {
+    let rotation = format!("mario:55,date:5:{}", kind.name());
+    Scene::at("2025-01-01T12:59:54+01:00")
+        .settings(|settings| settings.set_rotation(parse_rotation(&rotation).unwrap()))
+        .render(26)
+}
+
+#[test]
+fn transition_fade() {
+    assert_golden_frame("transition_fade", render_transition(TransitionKind::Fade));
+}
+
+#[test]
+fn transition_wipe() {
+    assert_golden_frame("transition_wipe", render_transition(TransitionKind::Wipe));
+}
+
+#[test]
+fn transition_dissolve() {
+    assert_golden_frame(
+        "transition_dissolve",
+        render_transition(TransitionKind::Dissolve),
+    );
+}
+
+#[test]
+fn transition_iris() {
+    assert_golden_frame("transition_iris", render_transition(TransitionKind::Iris));
+}
+
+// Once done the date is shown as without a transition
+#[test]
+fn transition_done() {
+    let actual = Scene::at("2025-01-01T12:59:54+01:00")
+        .settings(|settings| {
+            settings.set_transition(TransitionKind::Iris);
+            settings.set_rotation(parse_rotation("mario:55,date:5").unwrap());
+        })
+        .render(40);
+    assert_golden_frame("date_face", actual);
+}
+
+// Stars, the moon and darker scenery at night
+#[test]
+fn night_sky() {
+    let actual = Scene::at("2025-01-01T23:00:00+01:00").in_zurich().render(0);
+    assert_golden_frame("night_sky", actual);
+}
+
+// Sunrise in Zurich is at 08:14 on new year's day
+#[test]
+fn dawn_sky() {
+    let actual = Scene::at("2025-01-01T08:00:00+01:00").in_zurich().render(0);
+    assert_golden_frame("dawn_sky", actual);
+}
+
+#[test]
+fn date_face_at_dusk() {
+    let actual = Scene::at("2025-01-01T16:55:00+01:00")
+        .in_zurich()
+        .settings(|settings| settings.set_active_face(FaceId::Date))
+        .render(0);
+    assert_golden_frame("date_face_at_dusk", actual);
+}
+
+// The sky of the day looks like before the location was set
+#[test]
+fn day_sky() {
+    let actual = Scene::at("2025-01-01T12:59:59+01:00")
+        .in_zurich()
+        .render(20);
+    assert_golden_frame("jump_start", actual);
+}
+
+// The clouds drift slowly, the hills, the bushes and the ground scroll at
+// their own speed, wrapping around the edges
+#[test]
+fn scrolling_scenery() {
+    let actual = Scene::at("2025-01-01T12:30:00+01:00")
+        .settings(|settings| settings.set_scenery(Scenery::Scroll))
+        .render(300);
+    assert_golden_frame("scrolling_scenery", actual);
+}
+
+// Rain streaks, falling snow and banks of fog in front of the scenery, behind
+// Mario and the blocks
+#[test]
+fn rain() {
+    let actual = Scene::at("2025-01-01T12:30:10+01:00")
+        .weather(|weather| weather.set_condition(Some(WeatherCondition::Rain)))
+        .render(20);
+    assert_golden_frame("rain", actual);
+}
+
+#[test]
+fn snow_at_night() {
+    let actual = Scene::at("2025-01-01T23:30:10+01:00")
+        .in_zurich()
+        .weather(|weather| weather.set_condition(Some(WeatherCondition::Snow)))
+        .render(20);
+    assert_golden_frame("snow_at_night", actual);
+}
+
+#[test]
+fn fog_on_the_date() {
+    let actual = Scene::at("2025-01-01T12:30:10+01:00")
+        .settings(|settings| settings.set_active_face(FaceId::Date))
+        .weather(|weather| weather.set_condition(Some(WeatherCondition::Fog)))
+        .render(20);
+    assert_golden_frame("fog_on_the_date", actual);
+}
+
+// The sun with its rays in the top right corner, by day only
+#[test]
+fn sunshine() {
+    let actual = Scene::at("2025-01-01T12:30:10+01:00")
+        .weather(|weather| weather.set_condition(Some(WeatherCondition::Clear)))
+        .render(20);
+    assert_golden_frame("sunshine", actual);
+    let actual = Scene::at("2025-01-01T23:30:10+01:00")
+        .in_zurich()
+        .weather(|weather| weather.set_condition(Some(WeatherCondition::Clear)))
+        .render(20);
+    assert_golden_frame("clear_night", actual);
+}
+
+// The temperature above the bush, clear of Mario's jump and of the blocks
+#[test]
+fn temperature() {
+    // Measured at boot, the uptime starts at 0
+    let actual = Scene::at("2025-01-01T12:59:59+01:00")
+        .weather(|weather| weather.set_temperature(-12.3, 0))
+        .render(23);
+    assert_golden_frame("temperature", actual);
+
+    let actual = Scene::at("2025-07-01T14:00:00+02:00")
+        .settings(|settings| settings.set_temperature_unit(TemperatureUnit::Fahrenheit))
+        .weather(|weather| {
+            weather.set_condition(Some(WeatherCondition::Clear));
+            weather.set_temperature(31.0, 0);
+        })
+        .render(0);
+    assert_golden_frame("temperature_in_fahrenheit", actual);
+}
+
+// The indoor temperature and humidity below the date
+#[test]
+fn indoor_reading() {
+    let actual = Scene::at("2025-01-01T12:59:30+01:00")
+        .settings(|settings| settings.set_active_face(FaceId::Date))
+        .sensor(Reading {
+            temperature: 22.4,
+            humidity: Some(45.0),
+            pressure: None,
+        })
+        .render(0);
+    assert_golden_frame("indoor_reading", actual);
+}
+
+// Without a weather report Mario's scene shows the indoor temperature
+#[test]
+fn indoor_temperature_fallback() {
+    let actual = Scene::at("2025-01-01T12:30:10+01:00")
+        .sensor(Reading {
+            temperature: 19.6,
+            humidity: None,
+            pressure: None,
+        })
+        .render(0);
+    assert_golden_frame("indoor_temperature_fallback", actual);
+}
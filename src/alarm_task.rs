@@ -0,0 +1,32 @@
+use embassy_time::{Duration, Ticker};
+use esp_println::println;
+use mario_clock_core::{alarm::AlarmClock, settings::Settings};
+
+use crate::clock::Clock;
+
+/// The alarms, rung by [`alarm_task`] and shown on the clockface. Dismissed or
+/// snoozed on the console.
+pub static ALARM_CLOCK: AlarmClock = AlarmClock::new();
+
+/// How often the alarms are checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Rings the alarms in `settings` at their wall clock time in the configured
+/// time zone. Nothing rings while the time is unknown.
+#[embassy_executor::task]
+pub async fn alarm_task(settings: &'static Settings) {
+    let mut ticker = Ticker::every(CHECK_INTERVAL);
+    let mut was_ringing = false;
+    loop {
+        ticker.next().await;
+        if !Clock::is_time_known() {
+            continue;
+        }
+        let now = Clock::get_time_in_zone(settings.time_zone());
+        let ringing = ALARM_CLOCK.check(settings, now);
+        if let (Some(ringing), false) = (&ringing, was_ringing) {
+            println!("Alarm {} ringing at {}", ringing.alarm, now);
+        }
+        was_ringing = ringing.is_some();
+    }
+}
@@ -1,16 +1,45 @@
-use chrono::{DateTime, Datelike, Timelike, Utc};
-use core::net::SocketAddr::V4;
-use core::net::SocketAddrV4;
+use chrono::{DateTime, TimeDelta, Utc};
+use core::cell::Cell;
+use core::net::{IpAddr, SocketAddr};
 use core::sync::atomic::{AtomicU32, Ordering};
+use embassy_net::dns::DnsQueryType;
 use embassy_net::udp::{PacketMetadata, UdpSocket};
-use embassy_net::{dns, IpAddress, Ipv4Address, Stack};
-use embassy_time::Instant;
-use embedded_hal::i2c::I2c;
+use embassy_net::{IpAddress, IpEndpoint, Stack};
+use embassy_sync::blocking_mutex::{raw::CriticalSectionRawMutex, Mutex};
+use embassy_sync::signal::Signal;
+use embassy_time::{with_timeout, Duration, Instant, Timer};
+use embedded_hal::i2c::{Error as _, I2c};
 use esp_println::println;
-use pcf8563::PCF8563;
-use sntpc::{sntp_process_response, sntp_send_request, NtpContext, NtpTimestampGenerator};
+use mario_clock_core::{
+    clock::{
+        offset_from_time, rtc_registers_to_timestamp, time_from_offset, timestamp_to_rtc_datetime,
+        until_next_second, Correction, RtcError, SlewedOffset, SyncTracker, PCF8563_ADDRESS,
+        PCF8563_TIME_REGISTER, SLEW_THRESHOLD,
+    },
+    ntp::{NtpError, NtpTransport},
+    settings::Settings,
+    source::{Reference, Sample, SourceError, SourceKind, LOCAL_STRATUM, REFERENCE_LOCAL},
+    time::TimeSource,
+};
 
-static TIME_OFFSET_SECONDS: AtomicU32 = AtomicU32::new(0);
+/// Unix time at boot in microseconds, the current time is the uptime plus this
+/// offset. Small corrections are slewed in, see [`SlewedOffset`]. There are
+/// no 64 bit atomics on the ESP32-S3, hence the mutex.
+static TIME_OFFSET: Mutex<CriticalSectionRawMutex, Cell<SlewedOffset>> =
+    Mutex::new(Cell::new(SlewedOffset::new(0)));
+
+/// Number of times the clock stepped, see [`TimeSource::steps`].
+static TIME_STEPS: AtomicU32 = AtomicU32::new(0);
+
+/// Picks the time sources and tracks the syncs, shared with the console.
+pub static SYNC_TRACKER: SyncTracker = SyncTracker::new();
+
+/// Signaled when the clock was set from a source better than the RTC.
+pub static RTC_WRITE_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// The RTC only counts whole seconds and drifts by a few ppm while the
+/// board is off.
+const RTC_UNCERTAINTY: TimeDelta = TimeDelta::seconds(2);
 
 pub struct ClockBuffs {
     rx_meta: [PacketMetadata; 16],
@@ -30,160 +59,255 @@ impl Default for ClockBuffs {
     }
 }
 
-pub struct Clock<'a, I2C: I2c> {
-    rtc: PCF8563<I2C>,
-    socket: Option<UdpSocket<'a>>,
+impl ClockBuffs {
+    /// UDP socket on top of the buffers, unbound.
+    pub fn socket<'a>(&'a mut self, stack: Stack<'a>) -> UdpSocket<'a> {
+        UdpSocket::new(
+            stack,
+            &mut self.rx_meta,
+            &mut self.rx_buffer,
+            &mut self.tx_meta,
+            &mut self.tx_buffer,
+        )
+    }
 }
 
-impl<'a, I2C: I2c> Clock<'a, I2C> {
-    pub fn new<T: I2c>(i2c: T) -> Self
-    where
-        I2C: From<T>,
-    {
-        let mut rtc = PCF8563::new(i2c.into());
-        let datetime = rtc.get_datetime().ok();
-
-        println!("RTC time: {:?}", datetime);
-
-        if let Some(time) = datetime {
-            let timestamp = Self::rtc_datetime_to_timestamp(time);
-            TIME_OFFSET_SECONDS.store(timestamp, Ordering::Relaxed);
-        } else {
-            println!("Failed to read RTC time - you should call sync_ntp() - otherwise we are unable to determine the time");
-        };
+/// The PCF8563 real time clock, read at boot and written after every sync
+/// with a better source. The registers are accessed directly, the driver
+/// crate hides the voltage low flag and the century bit.
+pub struct Rtc<I2C: I2c> {
+    i2c: I2C,
+}
 
-        Clock { rtc, socket: None }
+impl<I2C: I2c> Rtc<I2C> {
+    pub fn new(i2c: I2C) -> Self {
+        Rtc { i2c }
     }
 
-    pub async fn sync_ntp(
-        &mut self,
-        stack: Stack<'a>,
-        buffs: &'a mut ClockBuffs,
-    ) -> Result<(), dns::Error> {
-        let mut socket = UdpSocket::new(
-            stack,
-            &mut buffs.rx_meta,
-            &mut buffs.rx_buffer,
-            &mut buffs.tx_meta,
-            &mut buffs.tx_buffer,
-        );
-        socket.bind(123).unwrap();
-
-        let addr: Ipv4Address = self.dns_query(&stack, "pool.ntp.org").await?;
+    /// Writes the current time to the RTC. The RTC only counts whole seconds,
+    /// so this waits for the next second boundary to keep the fraction.
+    pub async fn write(&mut self) {
+        // The RTC gets the corrected time right away, not the slewed one
+        let wait = until_next_second(Clock::get_target_time());
+        Timer::after(Duration::from_micros(
+            wait.num_microseconds().unwrap_or(0) as u64
+        ))
+        .await;
+        let time_seconds = Clock::get_target_time().timestamp();
+        let Some(t) = timestamp_to_rtc_datetime(time_seconds) else {
+            println!("Unable to store {} in the RTC", time_seconds);
+            return;
+        };
+        // Writing the seconds also clears the voltage low flag
+        let mut write = [0u8; 8];
+        write[0] = PCF8563_TIME_REGISTER;
+        write[1..].copy_from_slice(&t.to_registers());
+        if let Err(e) = self.i2c.write(PCF8563_ADDRESS, &write) {
+            println!("Failed to set RTC time: {:?}", e.kind());
+        }
+    }
+}
 
-        self.socket = Some(socket);
+impl<I2C: I2c> Reference for Rtc<I2C> {
+    fn kind(&self) -> SourceKind {
+        SourceKind::Rtc
+    }
 
-        let offset_seconds = TIME_OFFSET_SECONDS.load(Ordering::Relaxed);
-        let context = NtpContext::new(TimeStampGen::new(offset_seconds as i64 * 1_000_000));
+    async fn sample(&mut self) -> Result<Sample, SourceError> {
+        let mut registers = [0u8; 7];
+        let uptime_micros = Instant::now().as_micros();
+        self.i2c
+            .write_read(PCF8563_ADDRESS, &[PCF8563_TIME_REGISTER], &mut registers)
+            .map_err(|_| SourceError::Unavailable)?;
+        let time = rtc_registers_to_timestamp(&registers).and_then(|timestamp| {
+            DateTime::from_timestamp(timestamp, 0).ok_or(RtcError::InvalidData)
+        })?;
+        Ok(Sample {
+            kind: SourceKind::Rtc,
+            time,
+            uptime_micros,
+            uncertainty: RTC_UNCERTAINTY,
+            stratum: LOCAL_STRATUM,
+            reference_id: REFERENCE_LOCAL,
+        })
+    }
+}
 
-        println!("getting time from {}", addr);
-        let addr = V4(SocketAddrV4::new(addr, 123));
+/// Sets the clock from `sample` unless it is already set from a better
+/// source, see [`SyncTracker::offer`]. Corrections up to [`SLEW_THRESHOLD`]
+/// are slewed over the slew window in `settings`, larger ones step the clock.
+/// The RTC is updated from any source but itself. Returns whether the sample
+/// was taken.
+pub fn offer(sample: &Sample, settings: &Settings) -> bool {
+    let local = time_from_offset(time_offset(sample.uptime_micros), sample.uptime_micros);
+    let Some(status) = SYNC_TRACKER.offer(sample, local) else {
+        println!(
+            "Ignoring {} time {}, the clock is set from a better source",
+            sample.kind.name(),
+            sample.time
+        );
+        return false;
+    };
+    let window = core::time::Duration::from_secs(settings.slew_window_seconds() as u64);
+    let correction = TIME_OFFSET.lock(|offset| {
+        let mut slewed = offset.get();
+        let correction = slewed.correct(
+            offset_from_time(sample.time, sample.uptime_micros),
+            Instant::now().as_micros(),
+            window,
+            SLEW_THRESHOLD,
+        );
+        offset.set(slewed);
+        correction
+    });
+    if let Correction::Stepped(_) = correction {
+        TIME_STEPS.fetch_add(1, Ordering::Relaxed);
+    }
+    println!(
+        "Clock set from {} to {} (+/- {} us), was off by {} us, {:?}, drift {:?} ppm",
+        sample.kind.name(),
+        sample.time,
+        sample.uncertainty.num_microseconds().unwrap_or(i64::MAX),
+        status.offset.num_microseconds().unwrap_or(i64::MAX),
+        correction,
+        status.drift_ppm
+    );
+    if sample.kind != SourceKind::Rtc {
+        RTC_WRITE_SIGNAL.signal(());
+    }
+    true
+}
 
-        let req = sntp_send_request(addr, self.socket.as_ref().unwrap(), context)
-            .await
-            .unwrap();
-
-        if let Ok(response) =
-            sntp_process_response(addr, self.socket.as_ref().unwrap(), context, req).await
-        {
-            println!("received NTP response: {:?}", response);
-            TIME_OFFSET_SECONDS.store(response.seconds, Ordering::Relaxed);
-            self.set_rtc();
-        } else {
-            println!("Failed to process NTP response");
-        }
+/// The synchronised wall clock.
+pub struct Clock;
 
-        Ok(())
+impl Clock {
+    /// Whether the time is known, `false` until the clock was set from any
+    /// source, e.g. if the RTC lost power and there was no NTP sync yet.
+    pub fn is_time_known() -> bool {
+        SYNC_TRACKER.status().is_some()
     }
 
+    /// Current time with microsecond resolution.
     pub fn get_time() -> DateTime<Utc> {
-        let instant_seconds = Instant::now().as_secs();
-        let offset_seconds: u64 = TIME_OFFSET_SECONDS.load(Ordering::Relaxed) as u64;
-        let time_seconds = instant_seconds + offset_seconds;
-        DateTime::from_timestamp(
-            time_seconds.try_into().expect("Unable to convert to i64"),
-            0,
-        )
-        .unwrap()
+        let uptime = Instant::now().as_micros();
+        time_from_offset(time_offset(uptime), uptime)
+    }
+
+    /// The time the clock will read once the current correction is slewed in.
+    pub fn get_target_time() -> DateTime<Utc> {
+        let target = TIME_OFFSET.lock(|offset| offset.get().target());
+        time_from_offset(target, Instant::now().as_micros())
     }
 
     pub fn get_time_in_zone(zone: chrono_tz::Tz) -> DateTime<chrono_tz::Tz> {
         Self::get_time().with_timezone(&zone)
     }
+}
 
-    fn rtc_datetime_to_timestamp(datetime: pcf8563::DateTime) -> u32 {
-        let year = datetime.year as i32 + 2000; // pcf8563 year is since 2000
-        let month = datetime.month as u32;
-        let day = datetime.day as u32;
-        let hour = datetime.hours as u32;
-        let minute = datetime.minutes as u32;
-        let second = datetime.seconds as u32;
-
-        // Use chrono to create a DateTime object
-        let naive = chrono::NaiveDate::from_ymd_opt(year, month, day)
-            .and_then(|date| date.and_hms_opt(hour, minute, second))
-            .expect("Failed to create NaiveDateTime");
-
-        // Convert to DateTime<Utc>
-        let datetime_utc: DateTime<Utc> = DateTime::from_naive_utc_and_offset(naive, chrono::Utc);
-        // Get the Unix timestamp (seconds since epoch)
-        datetime_utc.timestamp() as u32
-    }
-
-    fn set_rtc(&mut self) {
-        let time_seconds = TIME_OFFSET_SECONDS.load(Ordering::Relaxed);
-        let t = DateTime::<Utc>::from_timestamp(time_seconds as i64, 0).unwrap();
-        // Set RTC time
-        if let Err(e) = self.rtc.set_datetime(&pcf8563::DateTime {
-            hours: t.hour() as u8,
-            minutes: t.minute() as u8,
-            seconds: t.second() as u8,
-            year: (t.year() - 2000) as u8,
-            month: t.month() as u8,
-            day: t.day() as u8,
-            weekday: t.weekday() as u8,
-        }) {
-            println!("Failed to set RTC time: {:?}", e);
-        }
+fn time_offset(uptime_micros: u64) -> i64 {
+    TIME_OFFSET.lock(|offset| offset.get().at(uptime_micros))
+}
+
+/// [`TimeSource`] backed by the embassy time driver and the synchronised clock.
+#[derive(Copy, Clone)]
+pub struct SystemTime;
+
+impl TimeSource for SystemTime {
+    fn millis(&self) -> u64 {
+        Instant::now().as_millis()
     }
 
-    async fn dns_query(&self, stack: &Stack<'_>, domain: &str) -> Result<Ipv4Address, dns::Error> {
-        stack
-            .dns_query(domain, embassy_net::dns::DnsQueryType::A)
-            .await
-            .and_then(|addrs| {
-                addrs
-                    .iter()
-                    .find_map(|item| match item {
-                        IpAddress::Ipv4(v4) => Some(*v4),
-                        _ => None,
-                    })
-                    .ok_or(dns::Error::Failed)
-            })
+    fn micros(&self) -> u64 {
+        Instant::now().as_micros()
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        Clock::get_time()
+    }
+
+    fn is_known(&self) -> bool {
+        Clock::is_time_known()
+    }
+
+    fn steps(&self) -> u32 {
+        TIME_STEPS.load(Ordering::Relaxed)
     }
 }
 
-#[derive(Copy, Clone)]
-struct TimeStampGen {
-    val: i64, // Boot offset in microseconds
+/// [`NtpTransport`] on top of the embassy network stack.
+pub struct NetTransport<'a> {
+    stack: Stack<'a>,
+    socket: UdpSocket<'a>,
 }
-impl TimeStampGen {
-    fn new(boot_offset: i64) -> Self {
-        TimeStampGen { val: boot_offset }
+
+impl<'a> NetTransport<'a> {
+    pub fn new(stack: Stack<'a>, buffs: &'a mut ClockBuffs) -> Result<Self, NtpError> {
+        let mut socket = buffs.socket(stack);
+        // Any free port, replies are matched by the server address
+        socket.bind(0).map_err(|_| NtpError::Network)?;
+        Ok(NetTransport { stack, socket })
     }
 }
 
-impl NtpTimestampGenerator for TimeStampGen {
-    fn init(&mut self) {
-        let stamp: i64 = Instant::now().as_micros().try_into().unwrap();
-        self.val += stamp;
+impl NtpTransport for NetTransport<'_> {
+    async fn resolve(&mut self, host: &str) -> Result<IpAddr, NtpError> {
+        resolve(self.stack, host).await.ok_or(NtpError::Dns)
+    }
+
+    async fn exchange(
+        &mut self,
+        server: SocketAddr,
+        request: &[u8],
+        reply: &mut [u8],
+        timeout: core::time::Duration,
+    ) -> Result<usize, NtpError> {
+        let IpAddr::V4(ip) = server.ip() else {
+            return Err(NtpError::Network);
+        };
+        let endpoint = IpEndpoint::new(IpAddress::Ipv4(ip), server.port());
+        self.socket
+            .send_to(request, endpoint)
+            .await
+            .map_err(|_| NtpError::Network)?;
+
+        let receive = async {
+            loop {
+                match self.socket.recv_from(reply).await {
+                    Ok((len, meta)) if meta.endpoint == endpoint => return Ok(len),
+                    // Not from the server we asked
+                    Ok(_) => {}
+                    Err(_) => return Err(NtpError::Network),
+                }
+            }
+        };
+        with_timeout(to_duration(timeout), receive)
+            .await
+            .map_err(|_| NtpError::Timeout)?
     }
 
-    fn timestamp_sec(&self) -> u64 {
-        (self.val.div_euclid(1_000_000)).try_into().unwrap()
+    async fn delay(&mut self, duration: core::time::Duration) {
+        Timer::after(to_duration(duration)).await
     }
+}
 
-    fn timestamp_subsec_micros(&self) -> u32 {
-        (self.val.rem_euclid(1_000_000)).try_into().unwrap()
+/// The IPv4 address of a host name or an IP address literal, `None` if DNS
+/// doesn't know it.
+pub async fn resolve(stack: Stack<'_>, host: &str) -> Option<IpAddr> {
+    if let Ok(addr) = host.parse() {
+        return Some(addr);
     }
+    stack
+        .dns_query(host, DnsQueryType::A)
+        .await
+        .ok()?
+        .iter()
+        .find_map(|item| match item {
+            IpAddress::Ipv4(v4) => Some(IpAddr::V4(*v4)),
+            _ => None,
+        })
+}
+
+pub fn to_duration(duration: core::time::Duration) -> Duration {
+    Duration::from_micros(duration.as_micros() as u64)
 }
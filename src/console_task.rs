@@ -0,0 +1,67 @@
+use embedded_io_async::Read;
+use esp_hal::{peripherals::USB_DEVICE, usb_serial_jtag::UsbSerialJtag};
+use esp_println::println;
+use heapless::String;
+use mario_clock_core::{
+    command::{Command, CommandContext, CommandError},
+    settings::Settings,
+    source::{ManualSource, Reference},
+};
+
+use crate::clock::{self, SystemTime, SYNC_TRACKER};
+
+/// Reads commands line by line from the USB serial/JTAG port and applies them
+/// to the settings, see [`Command`] for the available commands.
+#[embassy_executor::task]
+pub async fn console_task(usb_device: USB_DEVICE, context: &'static CommandContext<'static>) {
+    let (mut rx, _tx) = UsbSerialJtag::new(usb_device).into_async().split();
+    let mut line = String::<64>::new();
+    let mut buf = [0u8; 32];
+
+    loop {
+        let len = match rx.read(&mut buf).await {
+            Ok(len) => len,
+            Err(e) => {
+                println!("Failed to read from console: {:?}", e);
+                continue;
+            }
+        };
+
+        for &byte in &buf[..len] {
+            match byte {
+                b'\r' | b'\n' => {
+                    execute(&line, context);
+                    line.clear();
+                }
+                _ => {
+                    if line.push(byte as char).is_err() {
+                        println!("Command too long");
+                        line.clear();
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn execute(line: &str, context: &CommandContext) {
+    match Command::parse(line) {
+        Ok(command) => println!("{}", command.execute(context)),
+        Err(CommandError::Empty) => {}
+        Err(e) => println!("{:?}: {}", e, line),
+    }
+}
+
+/// Sets the clock from the times requested with `time set` on the console.
+#[embassy_executor::task]
+pub async fn manual_time_task(settings: &'static Settings) {
+    let mut source = ManualSource {
+        requested: &SYNC_TRACKER.manual,
+        time: SystemTime,
+    };
+    loop {
+        if let Ok(sample) = source.sample().await {
+            clock::offer(&sample, settings);
+        }
+    }
+}
@@ -2,23 +2,52 @@ use core::sync::atomic::Ordering;
 use embassy_executor::task;
 use esp_hal::system::Cpu;
 use esp_println::println;
+use mario_clock_core::{
+    alarm::AlarmClock,
+    engine::EventChannel,
+    faces::Faces,
+    sensor::SensorReadings,
+    settings::Settings,
+    timer::Timer,
+    transition::{Snapshot, EMPTY_SNAPSHOT},
+    weather::Weather,
+    ClockfaceTrait,
+};
+use static_cell::{ConstStaticCell, StaticCell};
 
-use crate::mario::clockface::Clockface;
-use crate::{ClockfaceTrait, FBType, FrameBufferExchange, REFRESH_RATE};
+use crate::clock::SystemTime;
+use crate::{FBType, FrameBufferExchange, REFRESH_RATE};
 
 #[task]
 pub(crate) async fn display_task(
     rx: &'static FrameBufferExchange,
     tx: &'static FrameBufferExchange,
     mut fb: &'static mut FBType,
+    settings: &'static Settings,
+    alarms: &'static AlarmClock,
+    timer: &'static Timer,
+    weather: &'static Weather,
+    sensor: &'static SensorReadings,
 ) {
     println!(
         "Starting display_task() on core {}",
         Cpu::current() as usize
     );
 
-    // Initialize clockface
-    let mut cf = Clockface::new();
+    // Initialize the clockfaces once, switching between them keeps their
+    // sprites and the channel. A transition between two faces blends them
+    // while drawing into the back buffer `fb`, the panel keeps showing the
+    // other one. The outgoing face is recorded into a static snapshot, so a
+    // switch doesn't allocate. The exchanged frame buffers can't be blended
+    // from: they are bit planes for the DMA that can't be read back, and the
+    // front one is being sent to the panel.
+    static CHANNEL: StaticCell<EventChannel> = StaticCell::new();
+    static SNAPSHOT: ConstStaticCell<Snapshot> = ConstStaticCell::new(EMPTY_SNAPSHOT);
+    let channel: &'static mut _ = CHANNEL.init(EventChannel::new());
+    let mut cf = Faces::new(
+        channel, SystemTime, settings, alarms, timer, weather, sensor,
+    )
+    .with_snapshot(SNAPSHOT.take());
 
     loop {
         // Update clock logic
@@ -0,0 +1,141 @@
+use chrono::TimeDelta;
+use embassy_time::{Duration, Instant};
+use embedded_io_async::Read;
+use esp_hal::{uart::UartRx, Async};
+use esp_println::println;
+use heapless::String;
+use mario_clock_core::{
+    nmea::{parse_time, NmeaError, MAX_SENTENCE_LEN},
+    settings::Settings,
+    source::{Reference, Sample, SourceError, SourceKind, PRIMARY_STRATUM, REFERENCE_GPS},
+};
+
+use crate::clock;
+
+/// How often the clock is set from the GPS once it has a fix. The receiver
+/// keeps sending, the sentences in between are read and dropped.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// The time of a sentence refers to the start of the second, the sentence
+/// itself follows up to a few hundred milliseconds later depending on the
+/// receiver.
+const GPS_UNCERTAINTY: TimeDelta = TimeDelta::milliseconds(500);
+
+/// NMEA 0183 GPS receiver on a UART, sampled from its RMC and ZDA sentences.
+pub struct GpsSource {
+    rx: UartRx<'static, Async>,
+    line: String<MAX_SENTENCE_LEN>,
+    /// Uptime at which the `$` of the current sentence arrived.
+    line_start: u64,
+    buf: [u8; 64],
+    pos: usize,
+    len: usize,
+}
+
+impl GpsSource {
+    pub fn new(rx: UartRx<'static, Async>) -> Self {
+        GpsSource {
+            rx,
+            line: String::new(),
+            line_start: 0,
+            buf: [0; 64],
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    /// Reads the next complete sentence, returns it with the uptime at which
+    /// it started.
+    async fn next_sentence(&mut self) -> (String<MAX_SENTENCE_LEN>, u64) {
+        loop {
+            if self.pos == self.len {
+                self.pos = 0;
+                self.len = match self.rx.read(&mut self.buf).await {
+                    Ok(len) => len,
+                    Err(e) => {
+                        println!("Failed to read from GPS: {:?}", e);
+                        0
+                    }
+                };
+                continue;
+            }
+
+            let byte = self.buf[self.pos];
+            self.pos += 1;
+            match byte {
+                b'$' => {
+                    self.line.clear();
+                    self.line.push('$').ok();
+                    self.line_start = Instant::now().as_micros();
+                }
+                b'\r' | b'\n' if !self.line.is_empty() => {
+                    return (core::mem::take(&mut self.line), self.line_start);
+                }
+                _ if !self.line.is_empty() => {
+                    if self.line.push(byte as char).is_err() {
+                        // Garbage or a lost line ending, wait for the next `$`
+                        self.line.clear();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Reference for GpsSource {
+    fn kind(&self) -> SourceKind {
+        SourceKind::Gps
+    }
+
+    /// Waits for the next sentence with a valid time.
+    async fn sample(&mut self) -> Result<Sample, SourceError> {
+        loop {
+            let (sentence, uptime_micros) = self.next_sentence().await;
+            match parse_time(&sentence) {
+                Ok(time) => {
+                    return Ok(Sample {
+                        kind: SourceKind::Gps,
+                        time,
+                        uptime_micros,
+                        uncertainty: GPS_UNCERTAINTY,
+                        stratum: PRIMARY_STRATUM,
+                        reference_id: REFERENCE_GPS,
+                    });
+                }
+                // Position and satellite sentences
+                Err(NmeaError::Unsupported) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Sets the clock from a GPS receiver, at the first fix and then every
+/// [`SAMPLE_INTERVAL`]. Without a receiver connected this just waits.
+#[embassy_executor::task]
+pub async fn gps_task(mut gps: GpsSource, settings: &'static Settings) {
+    let mut last_offer: Option<Instant> = None;
+    let mut has_fix = false;
+    loop {
+        match gps.sample().await {
+            Ok(sample) => {
+                if !has_fix {
+                    println!("GPS fix, time is {}", sample.time);
+                    has_fix = true;
+                }
+                if last_offer.is_none_or(|last| last.elapsed() >= SAMPLE_INTERVAL) {
+                    clock::offer(&sample, settings);
+                    last_offer = Some(Instant::now());
+                }
+            }
+            Err(SourceError::Nmea(NmeaError::NoFix)) => {
+                if has_fix {
+                    println!("GPS lost its fix");
+                    has_fix = false;
+                }
+            }
+            Err(e) => println!("Invalid GPS sentence: {:?}", e),
+        }
+    }
+}
@@ -1,14 +1,21 @@
 #![no_std]
 #![no_main]
+#![feature(impl_trait_in_assoc_type)]
 
-use clock::{Clock, ClockBuffs};
-use core::{future::Future, sync::atomic::AtomicU32};
+use alarm_task::{alarm_task, ALARM_CLOCK};
+use clock::{ClockBuffs, Rtc, SystemTime, SYNC_TRACKER};
+use console_task::{console_task, manual_time_task};
+use core::{cell::RefCell, sync::atomic::AtomicU32};
 use display::{
     display_task::display_task,
     hub75_task::{hub75_task, Hub75Peripherals},
 };
+use embassy_embedded_hal::shared_bus::blocking::i2c::I2cDevice;
 use embassy_executor::Spawner;
-use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
+use embassy_sync::{
+    blocking_mutex::{raw::CriticalSectionRawMutex, Mutex},
+    signal::Signal,
+};
 use embassy_time::{Duration, Timer};
 use esp_alloc::heap_allocator;
 use esp_backtrace as _;
@@ -19,24 +26,42 @@ use esp_hal::{
     system::{CpuControl, Stack},
     time::Rate,
     timer::timg::TimerGroup,
+    uart::{self, Uart},
     Blocking,
 };
 use esp_hal_embassy::{main, InterruptExecutor};
 use esp_hub75::framebuffer::DmaFrameBuffer;
 use esp_hub75::framebuffer::{compute_frame_count, compute_rows};
 use esp_println::println;
-use wifi_task::{connect_to_wifi, STOP_WIFI_SIGNAL};
+use esp_storage::FlashStorage;
+use gps_task::{gps_task, GpsSource};
+use mario_clock_core::{
+    command::CommandContext, sensor::SensorReadings, settings::Settings, source::Reference, timer,
+    weather::Weather, COLS, ROWS,
+};
+use ntp_task::ntp_task;
+use rtc_task::rtc_task;
+use sensor_task::sensor_task;
+use sntp_task::sntp_task;
+use storage::{load_settings, storage_task};
+use weather_task::{weather_task, WeatherBuffs};
+use wifi_task::connect_to_wifi;
 
+mod alarm_task;
 mod clock;
+mod console_task;
 mod display;
-mod engine;
-mod mario;
+mod gps_task;
+mod ntp_task;
+mod rtc_task;
+mod sensor_task;
+mod sntp_task;
+mod storage;
+mod weather_task;
 mod wifi_task;
 
 extern crate alloc;
 
-const ROWS: usize = 64;
-const COLS: usize = 64;
 const BITS: u8 = 4;
 const NROWS: usize = compute_rows(ROWS);
 const FRAME_COUNT: usize = compute_frame_count(BITS);
@@ -46,6 +71,9 @@ const FRAME_COUNT: usize = compute_frame_count(BITS);
 type FBType = DmaFrameBuffer<ROWS, COLS, NROWS, BITS, FRAME_COUNT>;
 type FrameBufferExchange = Signal<CriticalSectionRawMutex, &'static mut FBType>;
 pub type I2CType = I2c<'static, Blocking>;
+/// The I2C bus shared by the RTC and the temperature sensor.
+type I2CBus = Mutex<CriticalSectionRawMutex, RefCell<I2CType>>;
+pub type I2CDevice = I2cDevice<'static, CriticalSectionRawMutex, I2CType>;
 
 macro_rules! mk_static {
     ($t:ty,$val:expr) => {{
@@ -58,32 +86,74 @@ macro_rules! mk_static {
 
 static REFRESH_RATE: AtomicU32 = AtomicU32::new(0);
 
-pub(crate) trait ClockfaceTrait {
-    fn update(&mut self, fb: &mut FBType) -> impl Future<Output = ()> + Send;
-}
+/// Countdown and stopwatch, set on the console and shown by the display task.
+/// Kept outside of the task so a running timer survives a restart of it.
+static TIMER: timer::Timer = timer::Timer::new();
+
+/// Weather condition shown in front of the scenery, set on the console or
+/// fetched for the location.
+static WEATHER: Weather = Weather::new();
+
+/// Latest reading of the temperature sensor next to the RTC, if one is
+/// connected.
+static SENSOR: SensorReadings = SensorReadings::new();
 
 #[main]
 async fn main(spawner: Spawner) {
     let peripherals = esp_hal::init(esp_hal::Config::default());
 
     // --- RTC Initialization Start ---
-    println!("Initializing I2C for BM8563 RTC...");
+    println!("Initializing I2C for BM8563 RTC and temperature sensor...");
     let config = Config::default().with_frequency(Rate::from_khz(100));
     let i2c = I2c::new(peripherals.I2C0, config)
         .expect("Unable to create I2C instance")
         .with_scl(peripherals.GPIO42)
         .with_sda(peripherals.GPIO41);
+    let i2c_bus = mk_static!(I2CBus, Mutex::new(RefCell::new(i2c)));
 
     heap_allocator!(size: 72 * 1024);
 
-    let mut clock_buffs = ClockBuffs::default();
-    let mut clock = Clock::<I2CType>::new(i2c);
+    let default_time_zone = env!("TIME_ZONE").parse().unwrap_or_else(|_| {
+        println!(
+            "Invalid TIME_ZONE {}, falling back to UTC",
+            env!("TIME_ZONE")
+        );
+        chrono_tz::UTC
+    });
+    let settings = mk_static!(Settings, Settings::new(default_time_zone));
+    let mut flash = FlashStorage::new();
+    load_settings(&mut flash, settings);
+
+    let clock_buffs = mk_static!(ClockBuffs, ClockBuffs::default());
 
     let timg0 = TimerGroup::new(peripherals.TIMG0);
     let timg1 = TimerGroup::new(peripherals.TIMG1);
 
     esp_hal_embassy::init([timg0.timer0, timg0.timer1]);
 
+    // The RTC keeps the time until a better source is available
+    let mut rtc = Rtc::<I2CDevice>::new(I2cDevice::new(i2c_bus));
+    match rtc.sample().await {
+        Ok(sample) => {
+            clock::offer(&sample, settings);
+        }
+        Err(e) => println!(
+            "Unable to read the RTC: {:?}, the time is unknown until synced",
+            e
+        ),
+    }
+    // --- RTC Initialization End ---
+
+    // --- GPS Initialization Start ---
+    let uart_config = uart::Config::default().with_baudrate(9600);
+    let (gps_rx, _gps_tx) = Uart::new(peripherals.UART1, uart_config)
+        .expect("Unable to create UART instance")
+        .with_rx(peripherals.GPIO18)
+        .with_tx(peripherals.GPIO17)
+        .into_async()
+        .split();
+    // --- GPS Initialization End ---
+
     let sw_ints = SoftwareInterruptControl::new(peripherals.SW_INTERRUPT);
     let software_interrupt = sw_ints.software_interrupt2;
 
@@ -129,12 +199,23 @@ async fn main(spawner: Spawner) {
             // hub75 runs as high priority task
             high_pri_spawner
                 .spawn(hub75_task(hub75_peripherals, &RX, &TX, fb1))
-                .ok();
+                .expect("spawn hub75_task");
 
             let lp_executor = mk_static!(Executor, Executor::new());
             // display task runs as low priority task
             lp_executor.run(|spawner| {
-                spawner.spawn(display_task(&TX, &RX, fb0)).ok();
+                spawner
+                    .spawn(display_task(
+                        &TX,
+                        &RX,
+                        fb0,
+                        settings,
+                        &ALARM_CLOCK,
+                        &TIMER,
+                        &WEATHER,
+                        &SENSOR,
+                    ))
+                    .expect("spawn display_task");
             });
         }
     };
@@ -148,32 +229,62 @@ async fn main(spawner: Spawner) {
         .start_app_core(app_core_stack, cpu1_fnctn)
         .unwrap();
 
-    let stack = connect_to_wifi(
+    spawner
+        .spawn(storage_task(flash, settings))
+        .expect("spawn storage_task");
+    let console_context = mk_static!(
+        CommandContext<'static>,
+        CommandContext {
+            settings,
+            sync: &SYNC_TRACKER,
+            time: &SystemTime,
+            alarms: &ALARM_CLOCK,
+            timer: &TIMER,
+            weather: &WEATHER,
+            sensor: &SENSOR,
+        }
+    );
+    spawner
+        .spawn(console_task(peripherals.USB_DEVICE, console_context))
+        .expect("spawn console_task");
+    spawner
+        .spawn(manual_time_task(settings))
+        .expect("spawn manual_time_task");
+    spawner
+        .spawn(alarm_task(settings))
+        .expect("spawn alarm_task");
+    spawner.spawn(rtc_task(rtc)).expect("spawn rtc_task");
+    spawner
+        .spawn(sensor_task(I2cDevice::new(i2c_bus), &SENSOR))
+        .expect("spawn sensor_task");
+    spawner
+        .spawn(gps_task(GpsSource::new(gps_rx), settings))
+        .expect("spawn gps_task");
+
+    // The clock keeps running on the RTC, the GPS and the console without
+    // WiFi, the network tasks wait for the connection themselves
+    match connect_to_wifi(
         peripherals.WIFI,
         timg1.timer0,
         peripherals.RADIO_CLK,
         peripherals.RNG,
         spawner,
-    )
-    .await
-    .expect("Failed to connect to WiFi");
-
-    if let Some(stack_config) = stack.config_v4() {
-        println!("Client IP: {}", stack_config.address);
-    } else {
-        println!("Failed to get stack config");
+    ) {
+        Ok(stack) => {
+            spawner
+                .spawn(ntp_task(stack, clock_buffs, settings))
+                .expect("spawn ntp_task");
+            let sntp_buffs = mk_static!(ClockBuffs, ClockBuffs::default());
+            spawner
+                .spawn(sntp_task(stack, sntp_buffs, settings))
+                .expect("spawn sntp_task");
+            let weather_buffs = mk_static!(WeatherBuffs, WeatherBuffs::default());
+            spawner
+                .spawn(weather_task(stack, weather_buffs, settings, &WEATHER))
+                .expect("spawn weather_task");
+        }
+        Err(e) => println!("Unable to start WiFi, running without network: {:?}", e),
     }
-    clock
-        .sync_ntp(stack, &mut clock_buffs)
-        .await
-        .expect("Failed to sync NTP");
-
-    let time = Clock::<I2CType>::get_time_in_zone(chrono_tz::Europe::Zurich);
-    println!("Current time: {}", time);
-
-    println!("Request to disconnect wifi");
-
-    STOP_WIFI_SIGNAL.signal(());
 
     loop {
         // The main task keeps running so the executor doesn't exit
@@ -1,4 +0,0 @@
-pub mod assets;
-pub mod block;
-pub mod font;
-pub mod mario;
@@ -1,2 +0,0 @@
-pub mod clockface;
-pub mod gfx;
@@ -0,0 +1,108 @@
+use core::fmt::Write;
+use embassy_net::Stack;
+use embassy_time::{with_timeout, Duration};
+use esp_println::println;
+use heapless::{String, Vec};
+use mario_clock_core::{
+    dhcp::MAX_NTP_SERVERS,
+    ntp::NtpConfig,
+    settings::Settings,
+    source::{NtpSource, Reference},
+};
+
+use crate::{
+    clock::{self, Clock, ClockBuffs, NetTransport, SystemTime, SYNC_TRACKER},
+    wifi_task::{dhcp_ntp_servers, wait_for_network, START_WIFI_SIGNAL, STOP_WIFI_SIGNAL},
+};
+
+/// How long to wait for WiFi before giving up on a sync.
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(60);
+/// Retry interval while the time is unknown, the clockface shows no digits
+/// until the first sync.
+const UNKNOWN_TIME_RETRY: Duration = Duration::from_secs(60);
+/// Most NTP servers taken from `NTP_SERVERS`.
+const MAX_SERVERS: usize = 4;
+/// Most NTP servers from DHCP and `NTP_SERVERS` together.
+const MAX_ALL_SERVERS: usize = MAX_SERVERS + MAX_NTP_SERVERS;
+
+/// Synchronises the clock with NTP every sync interval, or when a sync is
+/// requested on the console. WiFi only runs while syncing, unless the time is
+/// served or the weather fetched. NTP servers handed out by DHCP are tried
+/// before the ones in `NTP_SERVERS`.
+#[embassy_executor::task]
+pub async fn ntp_task(
+    stack: Stack<'static>,
+    buffs: &'static mut ClockBuffs,
+    settings: &'static Settings,
+) {
+    let configured: Vec<&str, MAX_SERVERS> = env!("NTP_SERVERS")
+        .split(',')
+        .map(str::trim)
+        .filter(|server| !server.is_empty())
+        .take(MAX_SERVERS)
+        .collect();
+
+    loop {
+        match with_timeout(NETWORK_TIMEOUT, wait_for_network(stack)).await {
+            Ok(()) => {
+                let mut dhcp: Vec<String<15>, MAX_NTP_SERVERS> = Vec::new();
+                for addr in dhcp_ntp_servers() {
+                    let mut name = String::new();
+                    // An IPv4 address has at most 15 characters
+                    write!(name, "{}", addr).ok();
+                    dhcp.push(name).ok();
+                }
+                let servers: Vec<&str, MAX_ALL_SERVERS> = dhcp
+                    .iter()
+                    .map(String::as_str)
+                    .chain(configured.iter().copied())
+                    .collect();
+
+                match NetTransport::new(stack, &mut *buffs) {
+                    Ok(transport) => {
+                        let mut source = NtpSource {
+                            config: NtpConfig::new(&servers),
+                            transport,
+                            time: SystemTime,
+                        };
+                        match source.sample().await {
+                            Ok(sample) => {
+                                clock::offer(&sample, settings);
+                            }
+                            Err(e) => println!("Failed to sync NTP: {:?}", e),
+                        }
+                    }
+                    Err(e) => println!("Failed to open NTP socket: {:?}", e),
+                }
+                let time = Clock::get_time_in_zone(settings.time_zone());
+                println!("Current time: {}", time);
+            }
+            Err(_) => println!("No network, skipping NTP sync"),
+        }
+
+        // WiFi stays on while the time is served to the LAN and while the
+        // weather is fetched for the location
+        let serving = settings.ntp_server() || settings.location().is_some();
+        if !serving {
+            println!("Request to disconnect wifi");
+            STOP_WIFI_SIGNAL.signal(());
+        }
+
+        let interval = if Clock::is_time_known() {
+            Duration::from_secs(settings.sync_interval_minutes() as u64 * 60)
+        } else {
+            UNKNOWN_TIME_RETRY
+        };
+        if with_timeout(interval, SYNC_TRACKER.requested.wait())
+            .await
+            .is_ok()
+        {
+            println!("NTP sync requested");
+        }
+
+        if !serving {
+            println!("Request to connect wifi");
+            START_WIFI_SIGNAL.signal(());
+        }
+    }
+}
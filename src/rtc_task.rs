@@ -0,0 +1,17 @@
+use esp_println::println;
+
+use crate::{
+    clock::{Clock, Rtc, RTC_WRITE_SIGNAL},
+    I2CDevice,
+};
+
+/// Writes the time to the RTC whenever the clock was set from a better
+/// source, so it survives a power cycle.
+#[embassy_executor::task]
+pub async fn rtc_task(mut rtc: Rtc<I2CDevice>) {
+    loop {
+        RTC_WRITE_SIGNAL.wait().await;
+        rtc.write().await;
+        println!("RTC updated, time is {}", Clock::get_time());
+    }
+}
@@ -0,0 +1,40 @@
+use embassy_time::Timer;
+use esp_println::println;
+use mario_clock_core::{
+    sensor::{detect, Sensor, SensorReadings, SENSOR_INTERVAL},
+    time::TimeSource,
+};
+
+use crate::{
+    clock::{to_duration, SystemTime},
+    I2CDevice,
+};
+
+/// Measures the temperature sensor on the I2C bus of the RTC every
+/// [`SENSOR_INTERVAL`] and publishes the readings to `readings`. Ends right
+/// away if no supported sensor answers at boot.
+#[embassy_executor::task]
+pub async fn sensor_task(i2c: I2CDevice, readings: &'static SensorReadings) {
+    let Ok(mut sensor) = detect(i2c) else {
+        println!("No temperature sensor found");
+        return;
+    };
+    println!("Found a {} temperature sensor", sensor.name());
+
+    loop {
+        // The bus is only held for the transfers, the RTC can be written
+        // while the sensor measures
+        let reading = match sensor.start() {
+            Ok(()) => {
+                Timer::after(to_duration(sensor.measurement_time())).await;
+                sensor.read()
+            }
+            Err(e) => Err(e),
+        };
+        match reading {
+            Ok(reading) => readings.publish(sensor.name(), reading, SystemTime.millis()),
+            Err(e) => println!("Unable to read the {}: {:?}", sensor.name(), e),
+        }
+        Timer::after(to_duration(SENSOR_INTERVAL)).await;
+    }
+}
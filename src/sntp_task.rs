@@ -0,0 +1,49 @@
+use embassy_net::Stack;
+use embassy_time::Instant;
+use esp_println::println;
+use mario_clock_core::{
+    ntp::{serve, ServerStatus, NTP_PORT},
+    settings::Settings,
+};
+
+use crate::clock::{Clock, ClockBuffs, SYNC_TRACKER};
+
+/// Answers NTP requests on port 123 while enabled in the settings, so devices
+/// on an isolated network can sync from the clock. The stratum follows the
+/// source the clock was last set from, e.g. 1 for a GPS or 10 for the RTC.
+#[embassy_executor::task]
+pub async fn sntp_task(
+    stack: Stack<'static>,
+    buffs: &'static mut ClockBuffs,
+    settings: &'static Settings,
+) {
+    let mut socket = buffs.socket(stack);
+    if let Err(e) = socket.bind(NTP_PORT) {
+        println!("Failed to bind NTP server port: {:?}", e);
+        return;
+    }
+
+    // Room for requests with extension fields, only the header is used
+    let mut request = [0u8; 128];
+    loop {
+        let (len, meta) = match socket.recv_from(&mut request).await {
+            Ok(received) => received,
+            Err(e) => {
+                println!("Failed to receive NTP request: {:?}", e);
+                continue;
+            }
+        };
+        let receive = Clock::get_time();
+        if !settings.ntp_server() {
+            continue;
+        }
+
+        let status = ServerStatus::new(SYNC_TRACKER.status().as_ref(), Instant::now().as_micros());
+        let Some(reply) = serve(&request[..len], &status, receive, Clock::get_time()) else {
+            continue;
+        };
+        if let Err(e) = socket.send_to(&reply, meta.endpoint).await {
+            println!("Failed to answer NTP request: {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use embassy_time::{Duration, Timer};
+use embedded_storage::{ReadStorage, Storage};
+use esp_println::println;
+use esp_storage::FlashStorage;
+use mario_clock_core::settings::{Settings, SETTINGS_SIZE};
+
+/// Flash offset of the settings, the start of the NVS partition which is
+/// otherwise unused without ESP-IDF.
+const SETTINGS_OFFSET: u32 = 0x9000;
+
+/// Time to wait for further changes before writing the settings to flash.
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+/// Restores the settings stored in flash, keeps the defaults if there are none.
+pub fn load_settings(flash: &mut FlashStorage, settings: &Settings) {
+    let mut buf = [0u8; SETTINGS_SIZE];
+    match flash.read(SETTINGS_OFFSET, &mut buf) {
+        Ok(()) if settings.load(&buf) => println!("Loaded settings: {:?}", settings.values()),
+        Ok(()) => println!("No stored settings, using defaults"),
+        Err(e) => println!("Failed to read settings: {:?}", e),
+    }
+}
+
+/// Writes the settings to flash whenever they change.
+#[embassy_executor::task]
+pub async fn storage_task(mut flash: FlashStorage, settings: &'static Settings) {
+    loop {
+        settings.changed.wait().await;
+        Timer::after(SAVE_DELAY).await;
+
+        let mut buf = [0xFF; SETTINGS_SIZE];
+        if let Err(e) = settings.save(&mut buf) {
+            // The stored settings stay as they are
+            println!("Failed to save settings: {:?}", e);
+            continue;
+        }
+        match flash.write(SETTINGS_OFFSET, &buf) {
+            Ok(()) => println!("Saved settings: {:?}", settings.values()),
+            Err(e) => println!("Failed to save settings: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use core::net::{IpAddr, SocketAddr};
+use embassy_net::{tcp::TcpSocket, IpAddress, IpEndpoint, Stack};
+use embassy_time::{with_timeout, Duration};
+use embedded_io_async::{Read, Write};
+use esp_println::println;
+use mario_clock_core::{
+    http::{HttpError, HttpTransport},
+    open_meteo::OpenMeteo,
+    settings::Settings,
+    weather::{Weather, WeatherProvider, WEATHER_INTERVAL_MINUTES},
+};
+
+use crate::{
+    clock::{self, to_duration, SystemTime},
+    wifi_task::wait_for_network,
+};
+
+/// How long to wait for WiFi before giving up on a fetch.
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(60);
+/// Retry interval after a failed fetch.
+const FAILED_RETRY: Duration = Duration::from_secs(60);
+
+pub struct WeatherBuffs {
+    rx_buffer: [u8; 1024],
+    tx_buffer: [u8; 256],
+}
+
+impl Default for WeatherBuffs {
+    fn default() -> Self {
+        Self {
+            rx_buffer: [0; 1024],
+            tx_buffer: [0; 256],
+        }
+    }
+}
+
+/// HTTP over a TCP socket of the network stack, one connection per request.
+pub struct TcpTransport<'a> {
+    stack: Stack<'a>,
+    buffs: &'a mut WeatherBuffs,
+}
+
+impl<'a> TcpTransport<'a> {
+    pub fn new(stack: Stack<'a>, buffs: &'a mut WeatherBuffs) -> Self {
+        TcpTransport { stack, buffs }
+    }
+}
+
+impl HttpTransport for TcpTransport<'_> {
+    async fn resolve(&mut self, host: &str) -> Result<IpAddr, HttpError> {
+        clock::resolve(self.stack, host).await.ok_or(HttpError::Dns)
+    }
+
+    async fn exchange(
+        &mut self,
+        server: SocketAddr,
+        request: &[u8],
+        response: &mut [u8],
+        timeout: core::time::Duration,
+    ) -> Result<usize, HttpError> {
+        let IpAddr::V4(ip) = server.ip() else {
+            return Err(HttpError::Network);
+        };
+        let endpoint = IpEndpoint::new(IpAddress::Ipv4(ip), server.port());
+        let mut socket = TcpSocket::new(
+            self.stack,
+            &mut self.buffs.rx_buffer,
+            &mut self.buffs.tx_buffer,
+        );
+
+        let exchange = async {
+            socket
+                .connect(endpoint)
+                .await
+                .map_err(|_| HttpError::Network)?;
+            socket
+                .write_all(request)
+                .await
+                .map_err(|_| HttpError::Network)?;
+            let mut len = 0;
+            loop {
+                if len == response.len() {
+                    return Err(HttpError::TooLarge);
+                }
+                match socket.read(&mut response[len..]).await {
+                    Ok(0) => return Ok(len),
+                    Ok(read) => len += read,
+                    Err(_) => return Err(HttpError::Network),
+                }
+            }
+        };
+        let result = with_timeout(to_duration(timeout), exchange)
+            .await
+            .unwrap_or(Err(HttpError::Timeout));
+        // Don't wait for the server to acknowledge the close
+        socket.abort();
+        result
+    }
+}
+
+/// Fetches the current weather at the location from Open-Meteo every
+/// [`WEATHER_INTERVAL_MINUTES`], or when a fetch is requested on the console.
+/// Nothing is fetched while the location is unset, the weather then stays as
+/// set by hand.
+#[embassy_executor::task]
+pub async fn weather_task(
+    stack: Stack<'static>,
+    buffs: &'static mut WeatherBuffs,
+    settings: &'static Settings,
+    weather: &'static Weather,
+) {
+    loop {
+        let mut interval = Duration::from_secs(WEATHER_INTERVAL_MINUTES * 60);
+        if let Some(location) = settings.location() {
+            match with_timeout(NETWORK_TIMEOUT, wait_for_network(stack)).await {
+                Ok(()) => {
+                    let mut provider = OpenMeteo::new(TcpTransport::new(stack, &mut *buffs));
+                    match weather.update(&mut provider, location, &SystemTime).await {
+                        Ok(report) => println!(
+                            "Weather from {}: {} at {:.1} C",
+                            provider.name(),
+                            report.conditions.condition.name(),
+                            report.conditions.temperature
+                        ),
+                        Err(e) => {
+                            println!("Failed to fetch the weather: {:?}", e);
+                            interval = FAILED_RETRY;
+                        }
+                    }
+                }
+                Err(_) => {
+                    println!("No network, skipping weather fetch");
+                    interval = FAILED_RETRY;
+                }
+            }
+        }
+
+        if with_timeout(interval, weather.requested.wait())
+            .await
+            .is_ok()
+        {
+            println!("Weather fetch requested");
+        }
+    }
+}
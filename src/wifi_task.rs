@@ -1,55 +1,99 @@
-use core::str::FromStr;
+use core::cell::RefCell;
+use core::net::Ipv4Addr;
 use embassy_executor::Spawner;
-use embassy_net::{Runner, Stack, StackResources};
-use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
-use embassy_time::{Duration, Timer};
+use embassy_net::udp::{PacketMetadata, UdpSocket};
+use embassy_net::{
+    ConfigV4, HardwareAddress, IpAddress, IpEndpoint, IpListenEndpoint, Ipv4Cidr, Runner, Stack,
+    StackResources, StaticConfigV4,
+};
+use embassy_sync::{
+    blocking_mutex::{raw::CriticalSectionRawMutex, Mutex},
+    signal::Signal,
+};
+use embassy_time::{with_timeout, Duration, Instant, Timer};
 use esp_hal::{
     peripherals::{self, RNG},
     rng::Rng,
 };
 use esp_println::println;
-use esp_wifi::wifi::{
-    ClientConfiguration, Configuration, WifiController, WifiDevice, WifiError, WifiEvent, WifiState,
+use esp_wifi::{
+    wifi::{
+        ClientConfiguration, Configuration, WifiController, WifiDevice, WifiError, WifiEvent,
+        WifiState,
+    },
+    InitializationError,
+};
+use heapless::Vec;
+use mario_clock_core::dhcp::{
+    DhcpClient, DhcpError, DhcpTransport, Lease, DHCP_CLIENT_PORT, DHCP_SERVER_PORT,
+    MAX_NTP_SERVERS, MAX_REPLY_SIZE, MESSAGE_SIZE,
 };
-use heapless::String;
 use static_cell::StaticCell;
 
-/// Static cell for network stack resources
-static STACK_RESOURCES: StaticCell<StackResources<3>> = StaticCell::new();
+use crate::clock::to_duration;
+
+/// Static cell for network stack resources: the DHCP client, DNS, the NTP
+/// client, the NTP server, the weather client and one spare
+static STACK_RESOURCES: StaticCell<StackResources<6>> = StaticCell::new();
 
 /// Signal to request to stop WiFi
 pub(crate) static STOP_WIFI_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
 
-pub async fn connect_to_wifi(
+static DHCP_BUFFS: StaticCell<DhcpBuffs> = StaticCell::new();
+
+/// Host name sent to the DHCP server
+const HOSTNAME: &str = "mario-clock-rs";
+/// How often the DHCP client checks the link and its lease
+const LINK_POLL: Duration = Duration::from_secs(1);
+/// Retry interval after the DHCP server didn't answer
+const DHCP_RETRY: Duration = Duration::from_secs(10);
+
+/// NTP servers of the current DHCP lease
+static DHCP_NTP_SERVERS: Mutex<CriticalSectionRawMutex, RefCell<Vec<Ipv4Addr, MAX_NTP_SERVERS>>> =
+    Mutex::new(RefCell::new(Vec::new()));
+
+/// Signal to request to start WiFi again after it was stopped
+pub(crate) static START_WIFI_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
+
+/// Starts WiFi and the network stack. Connecting and leasing an address
+/// happen in the background, see [`wait_for_network`].
+pub fn connect_to_wifi(
     wifi: peripherals::WIFI,
     timer: esp_hal::timer::timg::Timer,
     radio_clocks: peripherals::RADIO_CLK,
     rng: RNG,
     spawner: Spawner,
-) -> Result<Stack<'static>, WifiError> {
+) -> Result<Stack<'static>, InitializationError> {
     let mut rng = Rng::new(rng);
 
     static INIT: StaticCell<esp_wifi::EspWifiController<'static>> = StaticCell::new();
-    let init = INIT.init(esp_wifi::init(timer, rng, radio_clocks).unwrap());
+    let init = INIT.init(esp_wifi::init(timer, rng, radio_clocks)?);
 
-    let (controller, interfaces) = esp_wifi::wifi::new(init, wifi).unwrap();
+    let (controller, interfaces) = esp_wifi::wifi::new(init, wifi)?;
 
     let wifi_interface = interfaces.sta;
 
-    // initialize network stack
-    let mut dhcp_config = embassy_net::DhcpConfig::default();
-    dhcp_config.hostname = Some(String::<32>::from_str("mario-clock-rs").unwrap());
-
+    // initialize network stack, the address comes from `dhcp_task`
     let seed = rng.random();
-    let config = embassy_net::Config::dhcpv4(dhcp_config);
+    let config = embassy_net::Config::default();
 
     println!("Initialize network stack");
     let stack_resources: &'static mut _ = STACK_RESOURCES.init(StackResources::new());
     let (stack, runner) = embassy_net::new(wifi_interface, config, stack_resources, seed.into());
 
-    spawner.spawn(connection(controller)).ok();
-    spawner.spawn(net_task(runner)).ok();
+    spawner
+        .spawn(connection(controller))
+        .expect("spawn connection");
+    spawner.spawn(net_task(runner)).expect("spawn net_task");
+    spawner
+        .spawn(dhcp_task(stack, DHCP_BUFFS.init(DhcpBuffs::default())))
+        .expect("spawn dhcp_task");
+
+    Ok(stack)
+}
 
+/// Waits until the link is up and DHCP assigned an IP address.
+pub async fn wait_for_network(stack: Stack<'_>) {
     println!("Wait for network link");
     loop {
         if stack.is_link_up() {
@@ -66,8 +110,164 @@ pub async fn connect_to_wifi(
         }
         Timer::after(Duration::from_millis(500)).await;
     }
+}
+
+/// The NTP servers of the current DHCP lease (option 42), empty without a
+/// lease or if the server doesn't know any.
+pub fn dhcp_ntp_servers() -> Vec<Ipv4Addr, MAX_NTP_SERVERS> {
+    DHCP_NTP_SERVERS.lock(|servers| servers.borrow().clone())
+}
+
+pub struct DhcpBuffs {
+    rx_meta: [PacketMetadata; 2],
+    rx_buffer: [u8; MAX_REPLY_SIZE],
+    tx_meta: [PacketMetadata; 2],
+    tx_buffer: [u8; MESSAGE_SIZE],
+}
+
+impl Default for DhcpBuffs {
+    fn default() -> Self {
+        Self {
+            rx_meta: [PacketMetadata::EMPTY; 2],
+            rx_buffer: [0; MAX_REPLY_SIZE],
+            tx_meta: [PacketMetadata::EMPTY; 2],
+            tx_buffer: [0; MESSAGE_SIZE],
+        }
+    }
+}
 
-    Ok(stack)
+/// The DHCP client port of the network stack.
+struct DhcpSocket<'a> {
+    socket: UdpSocket<'a>,
+}
+
+impl DhcpSocket<'_> {
+    /// Binds to the client port. Without an address the requests have to be
+    /// sent from 0.0.0.0, the stack has no source address to pick.
+    fn bind(&mut self, leased: bool) {
+        self.socket.close();
+        let addr = (!leased).then_some(IpAddress::Ipv4(Ipv4Addr::UNSPECIFIED));
+        let endpoint = IpListenEndpoint {
+            addr,
+            port: DHCP_CLIENT_PORT,
+        };
+        if let Err(e) = self.socket.bind(endpoint) {
+            println!("Unable to bind the DHCP client port: {:?}", e);
+        }
+    }
+}
+
+impl DhcpTransport for DhcpSocket<'_> {
+    async fn send(&mut self, server: Ipv4Addr, request: &[u8]) -> Result<(), DhcpError> {
+        let endpoint = IpEndpoint::new(IpAddress::Ipv4(server), DHCP_SERVER_PORT);
+        self.socket
+            .send_to(request, endpoint)
+            .await
+            .map_err(|_| DhcpError::Network)
+    }
+
+    async fn receive(
+        &mut self,
+        reply: &mut [u8],
+        timeout: core::time::Duration,
+    ) -> Result<usize, DhcpError> {
+        match with_timeout(to_duration(timeout), self.socket.recv_from(reply)).await {
+            Ok(Ok((len, _))) => Ok(len),
+            Ok(Err(_)) => Err(DhcpError::Network),
+            Err(_) => Err(DhcpError::Timeout),
+        }
+    }
+
+    fn millis(&self) -> u64 {
+        Instant::now().as_millis()
+    }
+}
+
+/// Configures the stack with `lease` and keeps its NTP servers.
+fn apply_lease(stack: Stack<'_>, lease: &Lease) {
+    println!(
+        "DHCP lease of {}/{} from {}, NTP servers {:?}",
+        lease.address, lease.prefix_len, lease.server, lease.ntp_servers
+    );
+    stack.set_config_v4(ConfigV4::Static(StaticConfigV4 {
+        address: Ipv4Cidr::new(lease.address, lease.prefix_len),
+        gateway: lease.router,
+        dns_servers: lease.dns_servers.iter().copied().collect(),
+    }));
+    DHCP_NTP_SERVERS.lock(|servers| *servers.borrow_mut() = lease.ntp_servers.clone());
+}
+
+fn next_xid() -> u32 {
+    Instant::now().as_ticks() as u32
+}
+
+/// Leases the address of the clock. The DHCP client of embassy-net doesn't
+/// ask for the NTP servers (option 42) and takes every reply to the client
+/// port, so the clock runs its own. A new lease is acquired whenever the link
+/// comes up, e.g. after WiFi was stopped between two syncs, and it is renewed
+/// while the link stays up.
+#[embassy_executor::task]
+async fn dhcp_task(stack: Stack<'static>, buffs: &'static mut DhcpBuffs) {
+    #[allow(irrefutable_let_patterns)]
+    let HardwareAddress::Ethernet(mac) = stack.hardware_address() else {
+        println!("No MAC address, DHCP disabled");
+        return;
+    };
+    let client = DhcpClient {
+        mac: mac.0,
+        hostname: HOSTNAME,
+    };
+    let mut socket = DhcpSocket {
+        socket: UdpSocket::new(
+            stack,
+            &mut buffs.rx_meta,
+            &mut buffs.rx_buffer,
+            &mut buffs.tx_meta,
+            &mut buffs.tx_buffer,
+        ),
+    };
+
+    loop {
+        while !stack.is_link_up() {
+            Timer::after(LINK_POLL).await;
+        }
+        socket.bind(false);
+        let mut lease = match client.acquire(&mut socket, next_xid()).await {
+            Ok(lease) => lease,
+            Err(e) => {
+                println!("Failed to get a DHCP lease: {:?}", e);
+                Timer::after(DHCP_RETRY).await;
+                continue;
+            }
+        };
+        apply_lease(stack, &lease);
+        socket.bind(true);
+
+        let mut leased_at = Instant::now();
+        let mut renew_at = leased_at + to_duration(lease.renewal_time);
+        while stack.is_link_up() {
+            if Instant::now() >= leased_at + to_duration(lease.lease_time) {
+                println!("DHCP lease expired");
+                break;
+            }
+            if Instant::now() >= renew_at {
+                match client.renew(&mut socket, next_xid(), &lease).await {
+                    Ok(renewed) => {
+                        lease = renewed;
+                        apply_lease(stack, &lease);
+                        leased_at = Instant::now();
+                        renew_at = leased_at + to_duration(lease.renewal_time);
+                    }
+                    Err(e) => {
+                        println!("Failed to renew the DHCP lease: {:?}", e);
+                        renew_at = Instant::now() + DHCP_RETRY;
+                    }
+                }
+            }
+            Timer::after(LINK_POLL).await;
+        }
+        stack.set_config_v4(ConfigV4::None);
+    }
 }
 
 #[embassy_executor::task]
@@ -122,7 +322,9 @@ async fn connection_fallible(mut controller: WifiController<'static>) -> Result<
                 STOP_WIFI_SIGNAL.wait().await;
                 println!("Received signal to stop wifi");
                 controller.stop_async().await?;
-                break;
+                println!("Wait for request to start wifi");
+                START_WIFI_SIGNAL.wait().await;
+                println!("Received signal to start wifi");
             }
             Err(error) => {
                 println!("Failed to connect to WiFi network: {:?}", error);
@@ -130,6 +332,4 @@ async fn connection_fallible(mut controller: WifiController<'static>) -> Result<
             }
         }
     }
-    println!("Leave connection task");
-    Ok(())
 }